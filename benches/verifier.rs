@@ -0,0 +1,180 @@
+//! Benchmarks `keygen_vk`, `keygen_pk`, `create_proof`, halo2's `verify_proof`
+//! and `explicit_verify` with its pairing check, on `MyCircuit` padded to 2^k
+//! rows for k = `BENCH_K_MIN..=BENCH_K_MAX`, 4 and 18 by default, e.g. with
+//! `BENCH_K_MAX=12 cargo bench --bench verifier`.
+//!
+//! Next to criterion's own report, the mean of every estimate is appended to
+//! the CSV file at `BENCH_CSV` (`bench.csv` by default) as
+//! `timestamp,operation,k,mean_ns`, so the runs can be compared over time.
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use criterion::{BenchmarkId, Criterion};
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bls12_381::{Bls12, Scalar};
+use halo2_proofs::halo2curves::ff::Field;
+use halo2_proofs::plonk::{keygen_pk, keygen_vk, verify_proof, Circuit, ConstraintSystem, Error};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::kzg::multiopen::{ProverGWC, VerifierGWC};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::transcript::{Blake2bRead, Challenge255, TranscriptReadBuffer};
+use halo2_test::{
+    explicit_verify, keygen, prove, FieldChip, FieldConfig, MyCircuit, NumericInstructions, PreparedVerifyingKey,
+};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// The operations, in the order they are benchmarked for every k.
+const OPERATIONS: [&str; 5] = ["keygen_vk", "keygen_pk", "create_proof", "verify_proof", "explicit_verify"];
+
+/// `MyCircuit`, followed by `muls` squarings of `a` in their own mul regions,
+/// so that it fills the rows of a larger k.
+struct PaddedCircuit {
+    circuit: MyCircuit<Scalar>,
+    muls: usize,
+}
+
+impl PaddedCircuit {
+    /// `MyCircuit` takes the 10 usable rows of k = 4, the padding takes the
+    /// load of `a` and two rows for every multiplication.
+    fn new(k: u32) -> Self {
+        let constant = Scalar::from(7);
+        let (a, b) = (Scalar::from(2), Scalar::from(3));
+        PaddedCircuit {
+            circuit: MyCircuit {
+                constant,
+                a: Value::known(a),
+                b: Value::known(b),
+                c: Value::known(constant * a.square() * b.square()),
+            },
+            muls: ((1usize << k) - 16).saturating_sub(1) / 2,
+        }
+    }
+}
+
+impl Circuit<Scalar> for PaddedCircuit {
+    type Config = FieldConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        PaddedCircuit {
+            circuit: self.circuit.without_witnesses(),
+            muls: self.muls,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+        MyCircuit::<Scalar>::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<Scalar>) -> Result<(), Error> {
+        self.circuit.synthesize(config.clone(), layouter.namespace(|| "MyCircuit"))?;
+        if self.muls == 0 {
+            return Ok(());
+        }
+
+        let field_chip = FieldChip::<Scalar>::construct(config);
+        let mut x = field_chip.load_private(layouter.namespace(|| "load a"), self.circuit.a)?;
+        for _ in 0..self.muls {
+            x = field_chip.mul(layouter.namespace(|| "x * x"), x.clone(), x)?;
+        }
+        Ok(())
+    }
+}
+
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name).map_or(default, |value| value.parse().unwrap_or_else(|_| panic!("invalid {}", name)))
+}
+
+/// The directory criterion writes its reports to, found the way criterion
+/// finds it.
+fn criterion_home() -> PathBuf {
+    env::var_os("CRITERION_HOME").map(PathBuf::from).unwrap_or_else(|| {
+        env::var_os("CARGO_TARGET_DIR")
+            .map_or_else(|| PathBuf::from("target"), PathBuf::from)
+            .join("criterion")
+    })
+}
+
+/// The mean criterion estimated for `operation/k`, in nanoseconds, or `None`
+/// if it was not benchmarked since `start`, e.g. because of a filter.
+fn mean_ns(operation: &str, k: u32, start: SystemTime) -> Option<f64> {
+    let path = criterion_home().join(operation).join(k.to_string()).join("new").join("estimates.json");
+    if fs::metadata(&path).and_then(|metadata| metadata.modified()).ok()? < start {
+        return None;
+    }
+    let estimates: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    estimates["mean"]["point_estimate"].as_f64()
+}
+
+fn main() {
+    let mut criterion = Criterion::default()
+        .sample_size(10)
+        .warm_up_time(Duration::from_millis(500))
+        .measurement_time(Duration::from_secs(2))
+        .configure_from_args();
+    let ks = env_or("BENCH_K_MIN", 4)..=env_or("BENCH_K_MAX", 18);
+    let csv = env_or("BENCH_CSV", "bench.csv".to_string());
+    let start = SystemTime::now();
+
+    for k in ks.clone() {
+        let params: ParamsKZG<Bls12> = ParamsKZG::setup(k, StdRng::from_seed([0u8; 32]));
+        let circuit = PaddedCircuit::new(k);
+        let pk = keygen(&params, &circuit);
+        let vk = pk.get_vk().clone();
+        let create_proof = || {
+            prove::<ProverGWC<_>, _>(&params, &pk, &[PaddedCircuit::new(k)], &[&[]], StdRng::from_seed([1u8; 32]))
+        };
+        let proof = create_proof();
+        let pvk = PreparedVerifyingKey::new(pk.get_vk());
+
+        let id = BenchmarkId::from_parameter(k);
+        criterion.benchmark_group("keygen_vk").bench_function(id.clone(), |bencher| {
+            bencher.iter(|| keygen_vk(&params, &circuit).expect("keygen_vk should not fail"))
+        });
+        criterion.benchmark_group("keygen_pk").bench_function(id.clone(), |bencher| {
+            bencher.iter(|| keygen_pk(&params, vk.clone(), &circuit).expect("keygen_pk should not fail"))
+        });
+        criterion
+            .benchmark_group("create_proof")
+            .bench_function(id.clone(), |bencher| bencher.iter(create_proof));
+        criterion.benchmark_group("verify_proof").bench_function(id.clone(), |bencher| {
+            bencher.iter(|| {
+                verify_proof::<_, VerifierGWC<_>, _, _, _>(
+                    &params,
+                    pk.get_vk(),
+                    SingleStrategy::new(&params),
+                    &[&[]],
+                    &mut Blake2bRead::<_, _, Challenge255<_>>::init(proof.as_slice()),
+                )
+                .expect("Verification failed")
+            })
+        });
+        criterion.benchmark_group("explicit_verify").bench_function(id, |bencher| {
+            bencher.iter(|| {
+                let (accumulator, _) = explicit_verify(&params, &pvk, &proof, &[&[]]);
+                assert!(accumulator.check());
+            })
+        });
+    }
+    criterion.final_summary();
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&csv).expect("cannot open the CSV file");
+    if file.metadata().map(|metadata| metadata.len() == 0).unwrap_or(true) {
+        writeln!(file, "timestamp,operation,k,mean_ns").unwrap();
+    }
+    let timestamp = start.duration_since(UNIX_EPOCH).unwrap().as_secs();
+    for k in ks {
+        for operation in OPERATIONS {
+            if let Some(mean) = mean_ns(operation, k, start) {
+                writeln!(file, "{},{},{},{:.0}", timestamp, operation, k, mean).unwrap();
+            }
+        }
+    }
+    println!("Appended the means to {}", csv);
+}
@@ -0,0 +1,1208 @@
+use std::io::{self, Read, Write};
+use std::time::Instant;
+
+use halo2_proofs::arithmetic::powers;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bls12_381::{Bls12, G1Affine, G1Projective, Scalar};
+use halo2_proofs::halo2curves::ff::{Field, PrimeField};
+use halo2_proofs::halo2curves::group::prime::PrimeCurveAffine;
+use halo2_proofs::halo2curves::group::{Curve, Group, GroupEncoding};
+use halo2_proofs::plonk::{
+    create_proof, verify_proof, Any, Circuit, Column, ColumnType, ConstraintSystem, Error, Expression, Instance,
+    ProvingKey, VerifyingKey,
+};
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverGWC, VerifierGWC};
+use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    Blake2bRead, Challenge255, EncodedChallenge, Transcript, TranscriptRead, TranscriptReadBuffer, TranscriptWrite,
+    TranscriptWriterBuffer,
+};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+use crate::foreign_field::{self, ForeignFieldInstructions};
+use crate::layout::Layout;
+use crate::msm::{point_instance, AccumulatorCircuit, G1Point, MsmChip, MsmConfig, MsmInstructions};
+use crate::poseidon::{PoseidonChip, PoseidonConfig, PoseidonInstructions, PoseidonParams};
+use crate::prepared::PreparedVerifyingKey;
+use crate::{explicit_verify_transcript, keygen, prove, MyCircuit, NumericInstructions, Number};
+
+/// A challenge of `PoseidonTranscript`, which is already a scalar.
+#[derive(Clone, Copy, Debug)]
+pub struct PoseidonChallenge(Scalar);
+
+impl EncodedChallenge<G1Affine> for PoseidonChallenge {
+    type Input = Scalar;
+
+    fn new(challenge_input: &Scalar) -> Self {
+        PoseidonChallenge(*challenge_input)
+    }
+
+    fn get_scalar(&self) -> Scalar {
+        self.0
+    }
+}
+
+/// A transcript that is cheap to replay in a circuit over the BLS12-381 scalar
+/// field. Absorbed scalars are buffered as they are and points as the limbs of
+/// their coordinates (see `point_instance`). A squeeze hashes the previous
+/// challenge together with the buffer, using the constant length Poseidon hash.
+///
+/// The proof itself uses the same encoding as `Blake2bWrite`, i.e. compressed
+/// points and little-endian scalars.
+pub struct PoseidonTranscript<S> {
+    stream: S,
+    state: Scalar,
+    buffer: Vec<Scalar>,
+    params: PoseidonParams<Scalar>,
+}
+
+impl<S> PoseidonTranscript<S> {
+    pub fn new(stream: S) -> Self {
+        PoseidonTranscript {
+            stream,
+            state: Scalar::ZERO,
+            buffer: Vec::new(),
+            params: PoseidonParams::new(),
+        }
+    }
+}
+
+/// The limbs a point is absorbed as. The identity, which has no affine
+/// coordinates, is absorbed as zeros.
+fn point_encoding(point: &G1Affine) -> Vec<Scalar> {
+    if bool::from(point.is_identity()) {
+        vec![Scalar::ZERO; 2 * foreign_field::NUM_LIMBS]
+    } else {
+        point_instance(point)
+    }
+}
+
+impl<S> Transcript<G1Affine, PoseidonChallenge> for PoseidonTranscript<S> {
+    fn squeeze_challenge(&mut self) -> PoseidonChallenge {
+        let mut inputs = vec![self.state];
+        inputs.append(&mut self.buffer);
+        self.state = self.params.hash(&inputs);
+        PoseidonChallenge::new(&self.state)
+    }
+
+    fn common_point(&mut self, point: G1Affine) -> io::Result<()> {
+        self.buffer.extend(point_encoding(&point));
+        Ok(())
+    }
+
+    fn common_scalar(&mut self, scalar: Scalar) -> io::Result<()> {
+        self.buffer.push(scalar);
+        Ok(())
+    }
+}
+
+impl<R: Read> TranscriptRead<G1Affine, PoseidonChallenge> for PoseidonTranscript<R> {
+    fn read_point(&mut self) -> io::Result<G1Affine> {
+        let mut compressed = <G1Affine as GroupEncoding>::Repr::default();
+        self.stream.read_exact(compressed.as_mut())?;
+        let point: G1Affine = Option::from(G1Affine::from_bytes(&compressed))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof"))?;
+        self.common_point(point)?;
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<Scalar> {
+        let mut data = <Scalar as PrimeField>::Repr::default();
+        self.stream.read_exact(data.as_mut())?;
+        let scalar: Scalar = Option::from(Scalar::from_repr(data))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "invalid field element encoding in proof"))?;
+        self.common_scalar(scalar)?;
+        Ok(scalar)
+    }
+}
+
+impl<R: Read> TranscriptReadBuffer<R, G1Affine, PoseidonChallenge> for PoseidonTranscript<R> {
+    fn init(reader: R) -> Self {
+        Self::new(reader)
+    }
+}
+
+impl<W: Write> TranscriptWrite<G1Affine, PoseidonChallenge> for PoseidonTranscript<W> {
+    fn write_point(&mut self, point: G1Affine) -> io::Result<()> {
+        self.common_point(point)?;
+        self.stream.write_all(point.to_bytes().as_ref())
+    }
+
+    fn write_scalar(&mut self, scalar: Scalar) -> io::Result<()> {
+        self.common_scalar(scalar)?;
+        self.stream.write_all(scalar.to_repr().as_ref())
+    }
+}
+
+impl<W: Write> TranscriptWriterBuffer<W, G1Affine, PoseidonChallenge> for PoseidonTranscript<W> {
+    fn init(writer: W) -> Self {
+        Self::new(writer)
+    }
+
+    fn finalize(self) -> W {
+        self.stream
+    }
+}
+
+/// The number of phases the advice columns of `cs` are committed to in.
+fn num_phases(cs: &ConstraintSystem<Scalar>) -> u8 {
+    cs.advice_column_phase().iter().copied().max().map_or(1, |phase| phase + 1)
+}
+
+fn read_points<R: Read>(transcript: &mut PoseidonTranscript<R>, n: usize) -> io::Result<Vec<Value<G1Affine>>> {
+    (0..n).map(|_| transcript.read_point().map(Value::known)).collect()
+}
+
+fn read_scalars<R: Read>(transcript: &mut PoseidonTranscript<R>, n: usize) -> io::Result<Vec<Value<Scalar>>> {
+    (0..n).map(|_| transcript.read_scalar().map(Value::known)).collect()
+}
+
+/// A proof created with `PoseidonTranscript`, split into the points and scalars
+/// that `explicit_verify` reads. How many there are of each follows from the
+/// constraint system of the circuit, as in `Layout`.
+#[derive(Clone, Debug)]
+pub struct InnerProof {
+    /// By column index, although they are read phase by phase.
+    pub advice_commitments: Vec<Value<G1Affine>>,
+    pub permutation_products: Vec<Value<G1Affine>>,
+    pub shuffle_products: Vec<Value<G1Affine>>,
+    pub vanishing_rand: Value<G1Affine>,
+    pub vanishing_split: Vec<Value<G1Affine>>,
+    pub advice_evals: Vec<Value<Scalar>>,
+    pub fixed_evals: Vec<Value<Scalar>>,
+    pub random_eval: Value<Scalar>,
+    pub permutation_common_evals: Vec<Value<Scalar>>,
+    /// `z(x)`, `z(omega x)` and `z(omega^last x)` of every product, except for
+    /// `z(omega^last x)` of the last one.
+    pub permutation_product_evals: Vec<Value<Scalar>>,
+    /// `z(x)` and `z(omega x)` of every shuffle product.
+    pub shuffle_evals: Vec<Value<Scalar>>,
+    pub w: Vec<Value<G1Affine>>,
+}
+
+impl InnerProof {
+    /// Reads a proof of a circuit with constraint system `cs`. Like
+    /// `explicit_verify`, the aggregator has no lookups, and the inner proofs
+    /// have no instance columns.
+    pub fn read(cs: &ConstraintSystem<Scalar>, proof: &[u8]) -> io::Result<Self> {
+        assert!(cs.lookups().is_empty(), "lookups are not supported");
+        assert_eq!(cs.num_instance_columns(), 0, "instance columns are not supported");
+        let layout = Layout::new(cs, 1);
+        let mut transcript = PoseidonTranscript::new(proof);
+
+        let advice_column_phase = cs.advice_column_phase();
+        let mut advice_commitments = vec![Value::unknown(); cs.num_advice_columns()];
+        for current_phase in 0..num_phases(cs) {
+            for (phase, commitment) in advice_column_phase.iter().zip(advice_commitments.iter_mut()) {
+                if *phase == current_phase {
+                    *commitment = Value::known(transcript.read_point()?);
+                }
+            }
+        }
+        let permutation_products = read_points(&mut transcript, layout.permutation_product_commitments)?;
+        let shuffle_products = read_points(&mut transcript, layout.shuffle_product_commitments)?;
+        let vanishing_rand = Value::known(transcript.read_point()?);
+        let vanishing_split = read_points(&mut transcript, layout.quotient_pieces)?;
+
+        Ok(InnerProof {
+            advice_commitments,
+            permutation_products,
+            shuffle_products,
+            vanishing_rand,
+            vanishing_split,
+            advice_evals: read_scalars(&mut transcript, layout.advice_evals)?,
+            fixed_evals: read_scalars(&mut transcript, layout.fixed_evals)?,
+            random_eval: Value::known(transcript.read_scalar()?),
+            permutation_common_evals: read_scalars(&mut transcript, layout.permutation_common_evals)?,
+            permutation_product_evals: read_scalars(&mut transcript, layout.permutation_product_evals)?,
+            shuffle_evals: read_scalars(&mut transcript, layout.shuffle_evals)?,
+            w: read_points(&mut transcript, layout.openings(false))?,
+        })
+    }
+
+    /// The same shape, without the values.
+    fn unknown(&self) -> Self {
+        let points = |points: &[Value<G1Affine>]| vec![Value::unknown(); points.len()];
+        let scalars = |scalars: &[Value<Scalar>]| vec![Value::unknown(); scalars.len()];
+        InnerProof {
+            advice_commitments: points(&self.advice_commitments),
+            permutation_products: points(&self.permutation_products),
+            shuffle_products: points(&self.shuffle_products),
+            vanishing_rand: Value::unknown(),
+            vanishing_split: points(&self.vanishing_split),
+            advice_evals: scalars(&self.advice_evals),
+            fixed_evals: scalars(&self.fixed_evals),
+            random_eval: Value::unknown(),
+            permutation_common_evals: scalars(&self.permutation_common_evals),
+            permutation_product_evals: scalars(&self.permutation_product_evals),
+            shuffle_evals: scalars(&self.shuffle_evals),
+            w: points(&self.w),
+        }
+    }
+}
+
+/// Proves `circuit` with `PoseidonTranscript`, which makes a proof the
+/// aggregator can verify.
+pub fn prove_inner<C: Circuit<Scalar>>(
+    params: &ParamsKZG<Bls12>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    rng: impl RngCore,
+) -> Vec<u8> {
+    let mut transcript = PoseidonTranscript::new(vec![]);
+    create_proof::<KZGCommitmentScheme<Bls12>, ProverGWC<_>, _, _, _, _>(params, pk, &[circuit], &[&[]], rng, &mut transcript)
+        .expect("Proof generation failed");
+    transcript.finalize()
+}
+
+/// Combines the accumulators of `proofs`, created with `PoseidonTranscript`, the
+/// way `AggregatorCircuit` does: the accumulator of proof `k` is scaled by
+/// `r^k`, where `r` hashes the last challenge `u` of every proof.
+pub fn accumulate(
+    params: &ParamsKZG<Bls12>,
+    vks: &[&VerifyingKey<G1Affine>],
+    proofs: &[Vec<u8>],
+) -> (G1Affine, G1Affine) {
+    let inputs = vks
+        .iter()
+        .zip(proofs.iter())
+        .map(|(vk, proof)| {
+            let mut transcript = PoseidonTranscript::new(proof.as_slice());
+            explicit_verify_transcript(params, &PreparedVerifyingKey::new(vk), &mut transcript, &[&[]]).1
+        })
+        .collect::<Vec<_>>();
+    let r = PoseidonParams::new().hash(&inputs.iter().map(|input| input.u).collect::<Vec<_>>());
+
+    let mut left = G1Projective::identity();
+    let mut right = G1Projective::identity();
+    for (input, power_of_r) in inputs.iter().zip(powers(r)) {
+        let (left_k, right_k) = input.accumulate();
+        left += left_k * power_of_r;
+        right += right_k * power_of_r;
+    }
+    (left.to_affine(), right.to_affine())
+}
+
+/// The position of the query of `column_index` at `at` in `queries`, which is
+/// also the position of its evaluation in the proof.
+fn query_index<C: ColumnType>(queries: &[(Column<C>, Rotation)], column_index: usize, at: Rotation) -> usize {
+    queries
+        .iter()
+        .position(|(column, rotation)| column.index() == column_index && *rotation == at)
+        .expect("query not found")
+}
+
+/// The evaluations that the expressions of the inner circuit refer to.
+struct Evals<'a> {
+    cs: &'a ConstraintSystem<Scalar>,
+    advice: &'a [Number<Scalar>],
+    fixed: &'a [Number<Scalar>],
+    challenges: &'a [Number<Scalar>],
+}
+
+impl Evals<'_> {
+    /// An expression, with the evaluations in place of the queries, as
+    /// `evaluate` in `explicit_verify`.
+    fn evaluate(
+        &self,
+        chip: &MsmChip,
+        mut layouter: impl Layouter<Scalar>,
+        expression: &Expression<Scalar>,
+    ) -> Result<Number<Scalar>, Error> {
+        let foreign = chip.foreign();
+        let (zero, one) = (Scalar::ZERO, Scalar::ONE);
+        Ok(match expression {
+            Expression::Constant(constant) => chip.field().load_constant(layouter, *constant)?,
+            Expression::Selector(_) => panic!("selectors are compressed into fixed columns"),
+            Expression::Fixed(query) => {
+                self.fixed[query_index(self.cs.fixed_queries(), query.column_index(), query.rotation())].clone()
+            }
+            Expression::Advice(query) => {
+                self.advice[query_index(self.cs.advice_queries(), query.column_index(), query.rotation())].clone()
+            }
+            Expression::Instance(_) => panic!("instance columns are not supported"),
+            Expression::Challenge(challenge) => self.challenges[challenge.index()].clone(),
+            Expression::Negated(a) => {
+                let a = self.evaluate(chip, layouter.namespace(|| "a"), a)?;
+                foreign.linear_combination(layouter.namespace(|| "-a"), &[], &[(&a, -one)], zero)?
+            }
+            Expression::Sum(a, b) => {
+                let a = self.evaluate(chip, layouter.namespace(|| "a"), a)?;
+                let b = self.evaluate(chip, layouter.namespace(|| "b"), b)?;
+                foreign.linear_combination(layouter.namespace(|| "a + b"), &[], &[(&a, one), (&b, one)], zero)?
+            }
+            Expression::Product(a, b) => {
+                let a = self.evaluate(chip, layouter.namespace(|| "a"), a)?;
+                let b = self.evaluate(chip, layouter.namespace(|| "b"), b)?;
+                chip.field().mul(layouter.namespace(|| "a * b"), a, b)?
+            }
+            Expression::Scaled(a, scalar) => {
+                let a = self.evaluate(chip, layouter.namespace(|| "a"), a)?;
+                foreign.linear_combination(layouter.namespace(|| "a * scalar"), &[], &[(&a, *scalar)], zero)?
+            }
+        })
+    }
+
+    /// Several expressions compressed into one with powers of theta.
+    fn compress(
+        &self,
+        chip: &MsmChip,
+        mut layouter: impl Layouter<Scalar>,
+        expressions: &[Expression<Scalar>],
+        theta: &Number<Scalar>,
+    ) -> Result<Number<Scalar>, Error> {
+        let mut compressed: Option<Number<Scalar>> = None;
+        for expression in expressions {
+            let eval = self.evaluate(chip, layouter.namespace(|| "expression"), expression)?;
+            compressed = Some(match compressed {
+                None => eval,
+                Some(compressed) => chip.foreign().linear_combination(
+                    layouter.namespace(|| "acc * theta + eval"),
+                    &[(&compressed, theta)],
+                    &[(&eval, Scalar::ONE)],
+                    Scalar::ZERO,
+                )?,
+            });
+        }
+        Ok(compressed.expect("an argument without expressions"))
+    }
+}
+
+/// A query of the multiopen argument. The commitment is a linear combination of
+/// points, so that the split `h` commitment never has to be computed on its own.
+struct Query {
+    rotation: i32,
+    commitment: Vec<(Option<Number<Scalar>>, G1Point)>,
+    eval: Number<Scalar>,
+}
+
+/// The in-circuit counterpart of `PoseidonTranscript`. Without
+/// `subgroup_checks` the points it reads are only checked to be on the curve,
+/// which is only meant for tests of the replay.
+struct TranscriptChip<'a> {
+    chip: &'a MsmChip,
+    poseidon: &'a PoseidonChip<Scalar>,
+    subgroup_checks: bool,
+    state: Number<Scalar>,
+    buffer: Vec<Number<Scalar>>,
+}
+
+impl<'a> TranscriptChip<'a> {
+    fn new(
+        chip: &'a MsmChip,
+        poseidon: &'a PoseidonChip<Scalar>,
+        subgroup_checks: bool,
+        layouter: impl Layouter<Scalar>,
+    ) -> Result<Self, Error> {
+        let state = chip.field().load_constant(layouter, Scalar::ZERO)?;
+        Ok(TranscriptChip {
+            chip,
+            poseidon,
+            subgroup_checks,
+            state,
+            buffer: Vec::new(),
+        })
+    }
+
+    fn common_scalar(&mut self, scalar: &Number<Scalar>) {
+        self.buffer.push(scalar.clone());
+    }
+
+    /// Absorbs a point that is already assigned, whose limbs must be canonical
+    /// to match the native transcript.
+    fn common_point(&mut self, point: &G1Point) {
+        self.buffer.extend(point.x.limbs.iter().chain(point.y.limbs.iter()).cloned());
+    }
+
+    fn read_point(&mut self, mut layouter: impl Layouter<Scalar>, point: Value<G1Affine>) -> Result<G1Point, Error> {
+        let foreign = self.chip.foreign();
+        let point = if self.subgroup_checks {
+            self.chip.witness_point(layouter.namespace(|| "witness"), point)?
+        } else {
+            self.chip.witness_on_curve(layouter.namespace(|| "witness"), point)?
+        };
+        foreign.assert_canonical(layouter.namespace(|| "canonical x"), &point.x)?;
+        foreign.assert_canonical(layouter.namespace(|| "canonical y"), &point.y)?;
+        self.common_point(&point);
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self, layouter: impl Layouter<Scalar>, scalar: Value<Scalar>) -> Result<Number<Scalar>, Error> {
+        let scalar = self.chip.field().load_private(layouter, scalar)?;
+        self.common_scalar(&scalar);
+        Ok(scalar)
+    }
+
+    fn read_points(&mut self, mut layouter: impl Layouter<Scalar>, points: &[Value<G1Affine>]) -> Result<Vec<G1Point>, Error> {
+        points
+            .iter()
+            .map(|point| self.read_point(layouter.namespace(|| "read point"), *point))
+            .collect()
+    }
+
+    fn read_scalars(&mut self, mut layouter: impl Layouter<Scalar>, scalars: &[Value<Scalar>]) -> Result<Vec<Number<Scalar>>, Error> {
+        scalars
+            .iter()
+            .map(|scalar| self.read_scalar(layouter.namespace(|| "read scalar"), *scalar))
+            .collect()
+    }
+
+    fn squeeze(&mut self, layouter: impl Layouter<Scalar>) -> Result<Number<Scalar>, Error> {
+        let mut inputs = vec![self.state.clone()];
+        inputs.append(&mut self.buffer);
+        self.state = self.poseidon.hash(layouter, &inputs)?;
+        Ok(self.state.clone())
+    }
+}
+
+/// The accumulator of one proof before it is combined with the others. The
+/// `-g0` term is kept apart as `sum s_i * e_i`.
+struct Accumulation {
+    left: Vec<(Number<Scalar>, G1Point)>,
+    right: Vec<(Number<Scalar>, G1Point)>,
+    evals: Vec<(Number<Scalar>, Number<Scalar>)>,
+    u: Number<Scalar>,
+}
+
+/// Verifies proofs created with `PoseidonTranscript`, replaying
+/// `explicit_verify` in the circuit, and exposes the combined KZG accumulator
+/// `(left, right)` as the limbs of `left.x`, `left.y`, `right.x` and `right.y`
+/// (see `AccumulatorCircuit::instance`). The pairing check
+/// `e(left, [s] g2) = e(right, g2)` is left to whoever consumes the instance,
+/// e.g. a contract or the next layer of aggregation.
+///
+/// Every proof has its own verifying key, and the phases, gates, permutation
+/// sets, shuffles and number of quotient pieces are taken from its constraint
+/// system, like `explicit_verify` does. The inner circuits may have no lookups
+/// and no instance columns (see `InnerProof::read`). The verifying keys and
+/// `g0` are fixed at keygen time.
+///
+/// Like `AccumulatorCircuit`, this is far too large for the `MockProver`: every
+/// commitment of a proof is checked to be in the subgroup and adds a term to
+/// MSMs of 255 bit scalars. `run` proves it for real, and the tests run the
+/// replay without those on the `MockProver`.
+pub struct AggregatorCircuit<'a> {
+    pub vks: Vec<&'a VerifyingKey<G1Affine>>,
+    pub proofs: Vec<InnerProof>,
+    pub g0: G1Affine,
+}
+
+impl<'a> AggregatorCircuit<'a> {
+    pub fn new(params: &ParamsKZG<Bls12>, vks: Vec<&'a VerifyingKey<G1Affine>>, proofs: &[Vec<u8>]) -> Self {
+        assert_eq!(vks.len(), proofs.len());
+        let proofs = vks
+            .iter()
+            .zip(proofs.iter())
+            .map(|(vk, proof)| InnerProof::read(&vk.cs, proof).expect("proof with the layout of its key"))
+            .collect();
+        AggregatorCircuit {
+            vks,
+            proofs,
+            g0: params.g[0],
+        }
+    }
+
+    /// Returns `inverse` with `a * inverse = 1`.
+    fn invert(chip: &MsmChip, mut layouter: impl Layouter<Scalar>, a: &Number<Scalar>) -> Result<Number<Scalar>, Error> {
+        let inverse = a.0.value().map(|a| a.invert().unwrap_or(Scalar::ZERO));
+        let inverse = chip.field().load_private(layouter.namespace(|| "load inverse"), inverse)?;
+        let one = chip
+            .foreign()
+            .linear_combination(layouter.namespace(|| "a * a^-1 - 1"), &[(a, &inverse)], &[], -Scalar::ONE)?;
+        Self::assert_zero(layouter.namespace(|| "a * a^-1 = 1"), &one)?;
+        Ok(inverse)
+    }
+
+    fn assert_zero(mut layouter: impl Layouter<Scalar>, a: &Number<Scalar>) -> Result<(), Error> {
+        let cell = a.0.cell();
+        layouter.assign_region(|| "assert zero", |mut region| region.constrain_constant(cell, Scalar::ZERO))
+    }
+
+    /// The steps of `explicit_verify` for one proof, up to the accumulator.
+    /// `subgroup_checks` is only turned off by the tests, see `TranscriptChip`.
+    fn verify(
+        chip: &MsmChip,
+        poseidon: &PoseidonChip<Scalar>,
+        mut layouter: impl Layouter<Scalar>,
+        vk: &VerifyingKey<G1Affine>,
+        proof: &InnerProof,
+        subgroup_checks: bool,
+    ) -> Result<Accumulation, Error> {
+        let field = chip.field();
+        let foreign = chip.foreign();
+        let cs = &vk.cs;
+        let domain = vk.get_domain();
+        let (zero, one) = (Scalar::ZERO, Scalar::ONE);
+
+        let mut transcript = TranscriptChip::new(chip, poseidon, subgroup_checks, layouter.namespace(|| "transcript"))?;
+        let repr = field.load_constant(layouter.namespace(|| "vk"), vk.transcript_repr())?;
+        transcript.common_scalar(&repr);
+
+        // The advice commitments phase by phase, each phase followed by the
+        // challenges that are usable after it.
+        let advice_column_phase = cs.advice_column_phase();
+        let challenge_phase = cs.challenge_phase();
+        let mut advice = vec![None; cs.num_advice_columns()];
+        let mut challenges = vec![None; cs.num_challenges()];
+        for current_phase in 0..num_phases(cs) {
+            for (column, phase) in advice_column_phase.iter().enumerate() {
+                if *phase == current_phase {
+                    let commitment = proof.advice_commitments[column];
+                    advice[column] = Some(transcript.read_point(layouter.namespace(|| "advice commitment"), commitment)?);
+                }
+            }
+            for (index, phase) in challenge_phase.iter().enumerate() {
+                if *phase == current_phase {
+                    challenges[index] = Some(transcript.squeeze(layouter.namespace(|| "challenge"))?);
+                }
+            }
+        }
+        let advice = advice.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+        let challenges = challenges.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+
+        let theta = transcript.squeeze(layouter.namespace(|| "theta"))?;
+        let beta = transcript.squeeze(layouter.namespace(|| "beta"))?;
+        let gamma = transcript.squeeze(layouter.namespace(|| "gamma"))?;
+        let products = transcript.read_points(layouter.namespace(|| "permutation products"), &proof.permutation_products)?;
+        let shuffle_products = transcript.read_points(layouter.namespace(|| "shuffle products"), &proof.shuffle_products)?;
+        let vanishing_rand = transcript.read_point(layouter.namespace(|| "vanishing rand"), proof.vanishing_rand)?;
+        let y = transcript.squeeze(layouter.namespace(|| "y"))?;
+        let vanishing_split = transcript.read_points(layouter.namespace(|| "vanishing split"), &proof.vanishing_split)?;
+        let x = transcript.squeeze(layouter.namespace(|| "x"))?;
+
+        let advice_evals = transcript.read_scalars(layouter.namespace(|| "advice evals"), &proof.advice_evals)?;
+        let fixed_evals = transcript.read_scalars(layouter.namespace(|| "fixed evals"), &proof.fixed_evals)?;
+        let random_eval = transcript.read_scalar(layouter.namespace(|| "random eval"), proof.random_eval)?;
+        let common_evals =
+            transcript.read_scalars(layouter.namespace(|| "permutation common evals"), &proof.permutation_common_evals)?;
+        let product_evals =
+            transcript.read_scalars(layouter.namespace(|| "permutation product evals"), &proof.permutation_product_evals)?;
+        let shuffle_evals = transcript.read_scalars(layouter.namespace(|| "shuffle evals"), &proof.shuffle_evals)?;
+
+        // (commitment, z(x), z(omega x), z(omega^last x)) of every permutation
+        // set, where the last one has no z(omega^last x).
+        let num_sets = products.len();
+        let mut product_evals = product_evals.into_iter();
+        let sets = products
+            .into_iter()
+            .enumerate()
+            .map(|(i, product)| {
+                let eval = product_evals.next().unwrap();
+                let next_eval = product_evals.next().unwrap();
+                let last_eval = (i + 1 < num_sets).then(|| product_evals.next().unwrap());
+                (product, eval, next_eval, last_eval)
+            })
+            .collect::<Vec<_>>();
+        // (commitment, z(x), z(omega x)) of every shuffle.
+        let shuffles = shuffle_products
+            .into_iter()
+            .zip(shuffle_evals.chunks(2))
+            .map(|(product, evals)| (product, evals[0].clone(), evals[1].clone()))
+            .collect::<Vec<_>>();
+
+        // x^n
+        let mut xn = x.clone();
+        for _ in 0..domain.k() {
+            xn = field.mul(layouter.namespace(|| "square"), xn.clone(), xn)?;
+        }
+
+        // l_i(x) = omega^i (x^n - 1) / (n (x - omega^i)), as in `l_i_range`.
+        let blinding_factors = cs.blinding_factors();
+        let n_inv = Scalar::from(1u64 << domain.k()).invert().unwrap();
+        let common = foreign.linear_combination(layouter.namespace(|| "(x^n - 1) / n"), &[], &[(&xn, n_inv)], -n_inv)?;
+        let mut l_evals = Vec::with_capacity(blinding_factors + 2);
+        for i in -((blinding_factors + 1) as i32)..=0 {
+            let mut layouter = layouter.namespace(|| format!("l_{}", i));
+            let omega_i = domain.rotate_omega(one, Rotation(i));
+            let d = foreign.linear_combination(layouter.namespace(|| "x - omega^i"), &[], &[(&x, one)], -omega_i)?;
+            let d_inv = Self::invert(chip, layouter.namespace(|| "1 / (x - omega^i)"), &d)?;
+            let l = field.mul(layouter.namespace(|| "common / (x - omega^i)"), d_inv, common.clone())?;
+            l_evals.push(foreign.linear_combination(layouter.namespace(|| "l_i"), &[], &[(&l, omega_i)], zero)?);
+        }
+        let l_last = &l_evals[0];
+        let l_blind = foreign.linear_combination(
+            layouter.namespace(|| "l_blind"),
+            &[],
+            &l_evals[1..1 + blinding_factors].iter().map(|l| (l, one)).collect::<Vec<_>>(),
+            zero,
+        )?;
+        let l_0 = &l_evals[1 + blinding_factors];
+        let active = foreign.linear_combination(
+            layouter.namespace(|| "1 - (l_last + l_blind)"),
+            &[],
+            &[(l_last, -one), (&l_blind, -one)],
+            one,
+        )?;
+
+        // The constraints, in the same order as in `explicit_verify`, starting
+        // with the gates.
+        let evals = Evals {
+            cs,
+            advice: &advice_evals,
+            fixed: &fixed_evals,
+            challenges: &challenges,
+        };
+        let mut expressions = Vec::new();
+        for poly in cs.gates().iter().flat_map(|gate| gate.polynomials()) {
+            expressions.push(evals.evaluate(chip, layouter.namespace(|| "gate"), poly)?);
+        }
+        // l_0(X) * (1 - z_0(X))
+        if let Some((_, z, _, _)) = sets.first() {
+            let t = foreign.linear_combination(layouter.namespace(|| "1 - z_0"), &[], &[(z, -one)], one)?;
+            expressions.push(field.mul(layouter.namespace(|| "CP1"), l_0.clone(), t)?);
+        }
+        // l_last(X) * (z_l(X)^2 - z_l(X))
+        if let Some((_, z, _, _)) = sets.last() {
+            let t = foreign.linear_combination(layouter.namespace(|| "z_l^2 - z_l"), &[(z, z)], &[(z, -one)], zero)?;
+            expressions.push(field.mul(layouter.namespace(|| "CP2"), l_last.clone(), t)?);
+        }
+        // l_0(X) * (z_i(X) - z_{i-1}(\omega^(last) X))
+        for (set, previous) in sets.iter().skip(1).zip(sets.iter()) {
+            let t = foreign.linear_combination(
+                layouter.namespace(|| "z_i - z_{i-1}(omega^last x)"),
+                &[],
+                &[(&set.1, one), (previous.3.as_ref().unwrap(), -one)],
+                zero,
+            )?;
+            expressions.push(field.mul(layouter.namespace(|| "CP3"), l_0.clone(), t)?);
+        }
+        // (1 - (l_last(X) + l_blind(X))) * (
+        //   z_i(\omega X) \prod (p(X) + \beta s_i(X) + \gamma)
+        // - z_i(X) \prod (p(X) + \delta^i \beta X + \gamma)
+        // )
+        // for every set, over the chunk of columns it covers.
+        let chunk_len = cs.degree() - 2;
+        let columns = cs
+            .permutation()
+            .get_columns()
+            .iter()
+            .map(|column| match column.column_type() {
+                Any::Advice(_) => advice_evals[query_index(cs.advice_queries(), column.index(), Rotation::cur())].clone(),
+                Any::Fixed => fixed_evals[query_index(cs.fixed_queries(), column.index(), Rotation::cur())].clone(),
+                Any::Instance => panic!("instance columns are not supported"),
+            })
+            .collect::<Vec<_>>();
+        let beta_x = field.mul(layouter.namespace(|| "beta x"), beta.clone(), x.clone())?;
+        for (chunk_index, ((set, chunk), sigmas)) in
+            sets.iter().zip(columns.chunks(chunk_len)).zip(common_evals.chunks(chunk_len)).enumerate()
+        {
+            let mut layouter = layouter.namespace(|| format!("permutation set {}", chunk_index));
+            let mut left = set.2.clone();
+            for (column, sigma) in chunk.iter().zip(sigmas.iter()) {
+                let t = foreign.linear_combination(
+                    layouter.namespace(|| "p + beta s + gamma"),
+                    &[(&beta, sigma)],
+                    &[(column, one), (&gamma, one)],
+                    zero,
+                )?;
+                left = field.mul(layouter.namespace(|| "left"), left, t)?;
+            }
+            let mut right = set.1.clone();
+            for (i, column) in chunk.iter().enumerate() {
+                let delta = Scalar::DELTA.pow_vartime([(chunk_index * chunk_len + i) as u64]);
+                let t = foreign.linear_combination(
+                    layouter.namespace(|| "p + delta^i beta x + gamma"),
+                    &[],
+                    &[(column, one), (&beta_x, delta), (&gamma, one)],
+                    zero,
+                )?;
+                right = field.mul(layouter.namespace(|| "right"), right, t)?;
+            }
+            let t = foreign.linear_combination(layouter.namespace(|| "left - right"), &[], &[(&left, one), (&right, -one)], zero)?;
+            expressions.push(field.mul(layouter.namespace(|| "active rows"), t, active.clone())?);
+        }
+        // For every shuffle, with a(X) and s(X) the compressed input and shuffle
+        // expressions:
+        // l_0(X) * (1 - z(X))
+        // l_last(X) * (z(X)^2 - z(X))
+        // (1 - (l_last(X) + l_blind(X))) * (z(\omega X) (s(X) + \gamma) - z(X) (a(X) + \gamma))
+        for (i, (argument, (_, z, z_next))) in cs.shuffles().iter().zip(shuffles.iter()).enumerate() {
+            let mut layouter = layouter.namespace(|| format!("shuffle {}", i));
+            let input = evals.compress(chip, layouter.namespace(|| "input"), argument.input_expressions(), &theta)?;
+            let shuffle = evals.compress(chip, layouter.namespace(|| "shuffle"), argument.shuffle_expressions(), &theta)?;
+            let t = foreign.linear_combination(layouter.namespace(|| "1 - z"), &[], &[(z, -one)], one)?;
+            expressions.push(field.mul(layouter.namespace(|| "first row"), l_0.clone(), t)?);
+            let t = foreign.linear_combination(layouter.namespace(|| "z^2 - z"), &[(z, z)], &[(z, -one)], zero)?;
+            expressions.push(field.mul(layouter.namespace(|| "last row"), l_last.clone(), t)?);
+            let t = foreign.linear_combination(layouter.namespace(|| "s + gamma"), &[], &[(&shuffle, one), (&gamma, one)], zero)?;
+            let left = field.mul(layouter.namespace(|| "left"), z_next.clone(), t)?;
+            let t = foreign.linear_combination(layouter.namespace(|| "a + gamma"), &[], &[(&input, one), (&gamma, one)], zero)?;
+            let right = field.mul(layouter.namespace(|| "right"), z.clone(), t)?;
+            let t = foreign.linear_combination(layouter.namespace(|| "left - right"), &[], &[(&left, one), (&right, -one)], zero)?;
+            expressions.push(field.mul(layouter.namespace(|| "active rows"), t, active.clone())?);
+        }
+
+        // h(x) = (sum y^i expression_i) / (x^n - 1), witnessed and checked by
+        // multiplying back.
+        let (first, rest) = expressions.split_first().expect("a circuit without constraints");
+        let mut h = first.clone();
+        for expression in rest {
+            h = foreign.linear_combination(layouter.namespace(|| "h * y + e"), &[(&h, &y)], &[(expression, one)], zero)?;
+        }
+        let xn_minus_one = foreign.linear_combination(layouter.namespace(|| "x^n - 1"), &[], &[(&xn, one)], -one)?;
+        let h_eval = h.0.value().zip(xn_minus_one.0.value()).map(|(h, d)| *h * d.invert().unwrap_or(zero));
+        let h_eval = field.load_private(layouter.namespace(|| "load h(x)"), h_eval)?;
+        let check = foreign.linear_combination(
+            layouter.namespace(|| "h(x) (x^n - 1) - h"),
+            &[(&h_eval, &xn_minus_one)],
+            &[(&h, -one)],
+            zero,
+        )?;
+        Self::assert_zero(layouter.namespace(|| "h(x) (x^n - 1) = h"), &check)?;
+
+        // h_0 + x^n h_1 + x^2n h_2 + ..., left to the MSM.
+        let mut h_commitment = vec![(None, vanishing_split[0].clone())];
+        let mut power_of_xn = xn.clone();
+        for (i, piece) in vanishing_split.iter().enumerate().skip(1) {
+            if i > 1 {
+                power_of_xn = field.mul(layouter.namespace(|| "next power of x^n"), power_of_xn, xn.clone())?;
+            }
+            h_commitment.push((Some(power_of_xn.clone()), piece.clone()));
+        }
+
+        // The queries, in the same order as in `explicit_verify`.
+        let last = -((blinding_factors + 1) as i32);
+        let single = |p: &G1Point| vec![(None, p.clone())];
+        let mut queries = Vec::new();
+        for (index, &(column, at)) in cs.advice_queries().iter().enumerate() {
+            queries.push(Query {
+                rotation: at.0,
+                commitment: single(&advice[column.index()]),
+                eval: advice_evals[index].clone(),
+            });
+        }
+        for (product, eval, next_eval, _) in &sets {
+            for (rotation, eval) in [(0, eval), (1, next_eval)] {
+                queries.push(Query {
+                    rotation,
+                    commitment: single(product),
+                    eval: eval.clone(),
+                });
+            }
+        }
+        for (product, _, _, last_eval) in sets.iter().rev().skip(1) {
+            queries.push(Query {
+                rotation: last,
+                commitment: single(product),
+                eval: last_eval.clone().unwrap(),
+            });
+        }
+        for (product, eval, next_eval) in &shuffles {
+            for (rotation, eval) in [(0, eval), (1, next_eval)] {
+                queries.push(Query {
+                    rotation,
+                    commitment: single(product),
+                    eval: eval.clone(),
+                });
+            }
+        }
+        for (index, &(column, at)) in cs.fixed_queries().iter().enumerate() {
+            let commitment =
+                chip.constant_point(layouter.namespace(|| "fixed commitment"), vk.fixed_commitments()[column.index()])?;
+            queries.push(Query {
+                rotation: at.0,
+                commitment: single(&commitment),
+                eval: fixed_evals[index].clone(),
+            });
+        }
+        for (commitment, eval) in vk.permutation().commitments().iter().zip(common_evals.iter()) {
+            let commitment = chip.constant_point(layouter.namespace(|| "permutation commitment"), *commitment)?;
+            queries.push(Query {
+                rotation: 0,
+                commitment: single(&commitment),
+                eval: eval.clone(),
+            });
+        }
+        queries.push(Query {
+            rotation: 0,
+            commitment: h_commitment,
+            eval: h_eval,
+        });
+        queries.push(Query {
+            rotation: 0,
+            commitment: single(&vanishing_rand),
+            eval: random_eval,
+        });
+
+        let v = transcript.squeeze(layouter.namespace(|| "v"))?;
+
+        // Distinct rotations give distinct points, except with negligible
+        // probability, so grouping by rotation matches `point_query_map`.
+        let mut groups: Vec<(i32, Vec<Query>)> = Vec::new();
+        for query in queries {
+            match groups.iter_mut().find(|(rotation, _)| *rotation == query.rotation) {
+                Some((_, group)) => group.push(query),
+                None => groups.push((query.rotation, vec![query])),
+            }
+        }
+        assert_eq!(groups.len(), proof.w.len());
+
+        let w = transcript.read_points(layouter.namespace(|| "w"), &proof.w)?;
+        let u = transcript.squeeze(layouter.namespace(|| "u"))?;
+
+        // The same accumulation as `AccumulatorCircuit`.
+        let mut accumulation = Accumulation {
+            left: Vec::new(),
+            right: Vec::new(),
+            evals: Vec::new(),
+            u: u.clone(),
+        };
+        let mut power_of_u = field.load_constant(layouter.namespace(|| "load 1"), one)?;
+        for (i, ((rotation, queries), w)) in groups.into_iter().zip(w).enumerate() {
+            let mut layouter = layouter.namespace(|| format!("point {}", i));
+            let omega = domain.rotate_omega(one, Rotation(rotation));
+            let z = foreign.linear_combination(layouter.namespace(|| "z"), &[], &[(&x, omega)], zero)?;
+
+            let mut power_of_v = field.load_constant(layouter.namespace(|| "load 1"), one)?;
+            for query in queries {
+                let scalar = field.mul(layouter.namespace(|| "u^i v^j"), power_of_u.clone(), power_of_v.clone())?;
+                for (factor, point) in query.commitment {
+                    let scalar = match factor {
+                        Some(factor) => field.mul(layouter.namespace(|| "u^i v^j x^n"), scalar.clone(), factor)?,
+                        None => scalar.clone(),
+                    };
+                    accumulation.right.push((scalar, point));
+                }
+                accumulation.evals.push((scalar, query.eval));
+                power_of_v = field.mul(layouter.namespace(|| "next v^j"), power_of_v, v.clone())?;
+            }
+
+            let power_of_u_z = field.mul(layouter.namespace(|| "u^i z"), power_of_u.clone(), z)?;
+            accumulation.left.push((power_of_u.clone(), w.clone()));
+            accumulation.right.push((power_of_u_z, w));
+            power_of_u = field.mul(layouter.namespace(|| "next u^i"), power_of_u, u.clone())?;
+        }
+
+        Ok(accumulation)
+    }
+}
+
+impl<'a> Circuit<Scalar> for AggregatorCircuit<'a> {
+    type Config = (MsmConfig, PoseidonConfig<Scalar>, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        AggregatorCircuit {
+            vks: self.vks.clone(),
+            proofs: self.proofs.iter().map(InnerProof::unknown).collect(),
+            g0: self.g0,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+        let modulus = meta.fixed_column();
+        let round_constants = [meta.fixed_column(), meta.fixed_column(), meta.fixed_column()];
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        // Both chips only use selector gated gates, so they can share the advice
+        // columns.
+        let msm_config = MsmChip::configure(meta, advice, constant, modulus);
+        let poseidon_config = PoseidonChip::configure(meta, advice, round_constants);
+
+        (msm_config, poseidon_config, instance)
+    }
+
+    fn synthesize(
+        &self,
+        (msm_config, poseidon_config, instance): Self::Config,
+        mut layouter: impl Layouter<Scalar>,
+    ) -> Result<(), Error> {
+        let chip = MsmChip::construct(msm_config);
+        let poseidon = PoseidonChip::construct(poseidon_config);
+        let field = chip.field();
+        let foreign = chip.foreign();
+        chip.load_table(layouter.namespace(|| "load table"))?;
+
+        let mut accumulations = Vec::with_capacity(self.proofs.len());
+        for (k, (vk, proof)) in self.vks.iter().zip(self.proofs.iter()).enumerate() {
+            let layouter = layouter.namespace(|| format!("proof {}", k));
+            accumulations.push(Self::verify(&chip, &poseidon, layouter, vk, proof, true)?);
+        }
+        let us = accumulations.iter().map(|accumulation| accumulation.u.clone()).collect::<Vec<_>>();
+        let r = poseidon.hash(layouter.namespace(|| "r"), &us)?;
+
+        // Scale the accumulator of proof k by r^k, where the first one is left as is.
+        let mut left = Vec::new();
+        let mut right = Vec::new();
+        let mut evals = Vec::new();
+        let mut power_of_r: Option<Number<Scalar>> = None;
+        for (k, accumulation) in accumulations.into_iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("combine proof {}", k));
+            let eval = foreign.sum_of_products(
+                layouter.namespace(|| "sum u^i v^j e_ij"),
+                &accumulation.evals.iter().map(|(scalar, eval)| (scalar, eval)).collect::<Vec<_>>(),
+            )?;
+            match &power_of_r {
+                None => {
+                    left.extend(accumulation.left);
+                    right.extend(accumulation.right);
+                    evals.push(eval);
+                }
+                Some(power_of_r) => {
+                    for (scalar, point) in accumulation.left {
+                        left.push((field.mul(layouter.namespace(|| "r^k s"), scalar, power_of_r.clone())?, point));
+                    }
+                    for (scalar, point) in accumulation.right {
+                        right.push((field.mul(layouter.namespace(|| "r^k s"), scalar, power_of_r.clone())?, point));
+                    }
+                    evals.push(field.mul(layouter.namespace(|| "r^k e"), eval, power_of_r.clone())?);
+                }
+            }
+            power_of_r = Some(match power_of_r {
+                None => r.clone(),
+                Some(power_of_r) => field.mul(layouter.namespace(|| "next r^k"), power_of_r, r.clone())?,
+            });
+        }
+
+        let eval_multi = foreign.linear_combination(
+            layouter.namespace(|| "eval_multi"),
+            &[],
+            &evals.iter().map(|eval| (eval, Scalar::ONE)).collect::<Vec<_>>(),
+            Scalar::ZERO,
+        )?;
+        let minus_g0 = chip.constant_point(layouter.namespace(|| "-g0"), -self.g0)?;
+        right.push((eval_multi, minus_g0));
+
+        let num_bits = Scalar::NUM_BITS as usize;
+        let left = chip.msm(layouter.namespace(|| "left"), &left, num_bits)?;
+        let right = chip.msm(layouter.namespace(|| "right"), &right, num_bits)?;
+
+        chip.constrain_instance(layouter.namespace(|| "expose left"), &left, instance, 0)?;
+        chip.constrain_instance(layouter.namespace(|| "expose right"), &right, instance, 2 * foreign_field::NUM_LIMBS)
+    }
+}
+
+/// Proves `AggregatorCircuit` over `n` proofs of `MyCircuit` with halo2's
+/// prover at `k`, and checks that halo2's verifier accepts it with the limbs of
+/// `accumulate` as instance, and rejects it with the two points swapped. This
+/// is the only run that synthesizes the aggregator, which takes minutes and
+/// tens of gigabytes, so it is not part of the default run.
+pub fn run(n: usize, k: u32) {
+    let inner_params: ParamsKZG<Bls12> = ParamsKZG::setup(4, StdRng::from_seed([0u8; 32]));
+    let constant = Scalar::from(7);
+    let inner_pk = keygen(&inner_params, &MyCircuit { constant, ..Default::default() });
+
+    let mut rng = StdRng::from_seed([1u8; 32]);
+    let inner_proofs = (0..n)
+        .map(|i| {
+            let (a, b) = (Scalar::from(i as u64 + 2), Scalar::from(3));
+            let circuit = MyCircuit {
+                constant,
+                a: Value::known(a),
+                b: Value::known(b),
+                c: Value::known(constant * a.square() * b.square()),
+            };
+            prove_inner(&inner_params, &inner_pk, circuit, &mut rng)
+        })
+        .collect::<Vec<_>>();
+    let vks = vec![inner_pk.get_vk(); n];
+    let (left, right) = accumulate(&inner_params, &vks, &inner_proofs);
+    let aggregator = AggregatorCircuit::new(&inner_params, vks, &inner_proofs);
+
+    let start = Instant::now();
+    let params: ParamsKZG<Bls12> = ParamsKZG::setup(k, StdRng::from_seed([2u8; 32]));
+    let pk = keygen(&params, &aggregator);
+    let instance = AccumulatorCircuit::instance(&(left, right));
+    let proof = prove::<ProverGWC<_>, _>(&params, &pk, &[aggregator], &[&[&instance]], StdRng::from_seed([3u8; 32]));
+    println!("Aggregated {} proofs at k = {} in {:?}", n, k, start.elapsed());
+
+    let verify = |instance: &[Scalar]| {
+        verify_proof::<_, VerifierGWC<_>, _, _, _>(
+            &params,
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            &[&[instance]],
+            &mut Blake2bRead::<_, _, Challenge255<_>>::init(proof.as_slice()),
+        )
+        .is_ok()
+    };
+    assert!(verify(&instance));
+    assert!(!verify(&AccumulatorCircuit::instance(&(right, left))));
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::poly::commitment::MSM;
+    use halo2_proofs::poly::kzg::msm::DualMSM;
+
+    use super::*;
+    use crate::chain::ChainCircuit;
+    use crate::multiset::MultisetCircuit;
+    use crate::my_circuit;
+    use crate::shuffle::ShuffleCircuit;
+
+    /// Whether the accumulator `(left, right)` passes the pairing check.
+    fn check(params: &ParamsKZG<Bls12>, (left, right): (G1Affine, G1Affine)) -> bool {
+        let mut accumulator = DualMSM::new(params);
+        accumulator.left.append_term(Scalar::ONE, left.into());
+        accumulator.right.append_term(Scalar::ONE, right.into());
+        accumulator.check()
+    }
+
+    /// `AggregatorCircuit::verify` for a single proof, without the subgroup
+    /// checks and the MSMs that make the aggregator too large for the
+    /// `MockProver`. It exposes the last challenge `u` and the combined
+    /// evaluation `sum u^i v^j e_ij`, which depend on every challenge and on
+    /// the h(x) that the circuit computes from the gates and arguments.
+    struct ReplayCircuit<'a> {
+        vk: &'a VerifyingKey<G1Affine>,
+        proof: InnerProof,
+    }
+
+    impl<'a> Circuit<Scalar> for ReplayCircuit<'a> {
+        type Config = (MsmConfig, PoseidonConfig<Scalar>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+        type Params = ();
+
+        fn without_witnesses(&self) -> Self {
+            ReplayCircuit {
+                vk: self.vk,
+                proof: self.proof.unknown(),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+            AggregatorCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            (msm_config, poseidon_config, instance): Self::Config,
+            mut layouter: impl Layouter<Scalar>,
+        ) -> Result<(), Error> {
+            let chip = MsmChip::construct(msm_config);
+            let poseidon = PoseidonChip::construct(poseidon_config);
+            chip.load_table(layouter.namespace(|| "load table"))?;
+
+            let accumulation =
+                AggregatorCircuit::verify(&chip, &poseidon, layouter.namespace(|| "verify"), self.vk, &self.proof, false)?;
+            let eval = chip.foreign().sum_of_products(
+                layouter.namespace(|| "sum u^i v^j e_ij"),
+                &accumulation.evals.iter().map(|(scalar, eval)| (scalar, eval)).collect::<Vec<_>>(),
+            )?;
+            layouter.constrain_instance(accumulation.u.0.cell(), instance, 0)?;
+            layouter.constrain_instance(eval.0.cell(), instance, 1)
+        }
+    }
+
+    /// The instance of `ReplayCircuit`, from the native verifier.
+    fn replay_instance(params: &ParamsKZG<Bls12>, vk: &VerifyingKey<G1Affine>, proof: &[u8]) -> Vec<Scalar> {
+        let mut transcript = PoseidonTranscript::new(proof);
+        let (_, input) = explicit_verify_transcript(params, &PreparedVerifyingKey::new(vk), &mut transcript, &[&[]]);
+        let eval: Scalar = input
+            .points
+            .iter()
+            .zip(powers(input.u))
+            .flat_map(|((_, queries), power_of_u)| {
+                queries.iter().zip(powers(input.v)).map(move |((_, eval), power_of_v)| power_of_u * power_of_v * eval)
+            })
+            .sum();
+        vec![input.u, eval]
+    }
+
+    /// Replays a proof of each of `circuits`, which share their layout, and
+    /// checks that the first one does not give the instance of the second.
+    fn assert_replay<C: Circuit<Scalar>>(circuits: [C; 2]) {
+        let params: ParamsKZG<Bls12> = ParamsKZG::setup(5, StdRng::from_seed([0u8; 32]));
+        let pk = keygen(&params, &circuits[0]);
+        let mut rng = StdRng::from_seed([1u8; 32]);
+        let proofs = circuits
+            .into_iter()
+            .map(|circuit| prove_inner(&params, &pk, circuit, &mut rng))
+            .collect::<Vec<_>>();
+        let instances = proofs
+            .iter()
+            .map(|proof| replay_instance(&params, pk.get_vk(), proof))
+            .collect::<Vec<_>>();
+
+        let replay = ReplayCircuit {
+            vk: pk.get_vk(),
+            proof: InnerProof::read(&pk.get_vk().cs, &proofs[0]).unwrap(),
+        };
+        MockProver::run(17, &replay, vec![instances[0].clone()]).unwrap().assert_satisfied();
+        let prover = MockProver::run(17, &replay, vec![instances[1].clone()]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn replay() {
+        // The in-circuit verifier follows the layout of every key: one phase
+        // and two H pieces for `MyCircuit`, a second phase and a challenge for
+        // `MultisetCircuit`, a shuffle product for `ShuffleCircuit`, and more
+        // columns and permutation sets for a `ChainCircuit`. Most of the rows
+        // go to the curve checks of the points and the Poseidon hashes.
+        assert_replay([my_circuit::<Scalar>(2, 3), my_circuit(5, 7)]);
+
+        let values = |values: &[u64]| values.iter().map(|v| Value::known(Scalar::from(*v))).collect::<Vec<_>>();
+        assert_replay([
+            MultisetCircuit {
+                a: values(&[1, 2, 3, 4]),
+                b: values(&[3, 1, 4, 2]),
+            },
+            MultisetCircuit {
+                a: values(&[5, 6, 7, 8]),
+                b: values(&[8, 7, 6, 5]),
+            },
+        ]);
+        assert_replay([
+            ShuffleCircuit {
+                a: values(&[5, 8, 1, 3]),
+                b: values(&[1, 3, 5, 8]),
+            },
+            ShuffleCircuit {
+                a: values(&[2, 4, 6, 8]),
+                b: values(&[8, 6, 4, 2]),
+            },
+        ]);
+
+        let chain = |a: u64| ChainCircuit {
+            n_muls: 6,
+            n_advice: 3,
+            n_fixed: 2,
+            with_lookup: false,
+            a: Value::known(Scalar::from(a)),
+        };
+        assert_replay([chain(2), chain(3)]);
+    }
+
+    #[test]
+    fn native_accumulator() {
+        // halo2's verifier accepts proofs with the Poseidon transcript just as
+        // well. The combined accumulator must pass the pairing check, and must
+        // not after changing the first advice evaluation of one proof.
+        let params: ParamsKZG<Bls12> = ParamsKZG::setup(4, StdRng::from_seed([0u8; 32]));
+        let pk = keygen(&params, &my_circuit::<Scalar>(2, 3));
+        let mut rng = StdRng::from_seed([1u8; 32]);
+        let proofs = [(2, 3), (5, 7)]
+            .iter()
+            .map(|&(a, b)| prove_inner(&params, &pk, my_circuit(a, b), &mut rng))
+            .collect::<Vec<_>>();
+        for proof in &proofs {
+            verify_proof::<_, VerifierGWC<_>, _, _, _>(
+                &params,
+                pk.get_vk(),
+                SingleStrategy::new(&params),
+                &[&[]],
+                &mut PoseidonTranscript::new(proof.as_slice()),
+            )
+            .expect("Verification failed");
+        }
+        let vks = vec![pk.get_vk(); proofs.len()];
+        assert_eq!(AggregatorCircuit::new(&params, vks.clone(), &proofs).proofs.len(), 2);
+        assert!(check(&params, accumulate(&params, &vks, &proofs)));
+
+        // The first advice evaluation follows the commitments, of 48 bytes each.
+        let offset = Layout::new(&pk.get_vk().cs, 1).commitments() * 48;
+        let mut tampered = proofs;
+        tampered[1][offset] ^= 1;
+        assert!(!check(&params, accumulate(&params, &vks, &tampered)));
+    }
+}
@@ -0,0 +1,133 @@
+use std::time::{Duration, Instant};
+
+use halo2_proofs::circuit::Value;
+use halo2_proofs::halo2curves::bls12_381::{Bls12, G1Affine, Scalar};
+use halo2_proofs::halo2curves::ff::Field;
+use halo2_proofs::plonk::ProvingKey;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::poly::kzg::msm::DualMSM;
+use halo2_proofs::poly::kzg::multiopen::ProverGWC;
+use rand::rngs::{OsRng, StdRng};
+use rand::SeedableRng;
+
+use crate::prepared::PreparedVerifyingKey;
+use crate::{explicit_verify, keygen, prove, MyCircuit};
+
+/// Collects the KZG accumulators of several proofs, so they are all checked with
+/// a single pairing check instead of one per proof.
+pub struct BatchVerifier<'params> {
+    accumulator: DualMSM<'params, Bls12>,
+}
+
+impl<'params> BatchVerifier<'params> {
+    pub fn new(params: &'params ParamsKZG<Bls12>) -> Self {
+        BatchVerifier {
+            accumulator: DualMSM::new(params),
+        }
+    }
+
+    /// Adds the accumulator of one proof. The accumulators collected so far are
+    /// first scaled by a fresh random scalar, so that a prover cannot make two
+    /// invalid accumulators cancel each other out.
+    pub fn add(&mut self, accumulator: DualMSM<'params, Bls12>) {
+        self.accumulator.scale(Scalar::random(OsRng));
+        self.accumulator.add_msm(accumulator);
+    }
+
+    /// The single pairing check, `e(left, [s]_2) = e(right, [1]_2)`.
+    pub fn finalize(self) -> bool {
+        self.accumulator.check()
+    }
+}
+
+/// Proves `n` instances of `MyCircuit`, each with different private inputs.
+fn create_proofs(params: &ParamsKZG<Bls12>, n: usize) -> (ProvingKey<G1Affine>, Vec<Vec<u8>>) {
+    let constant = Scalar::from(7);
+    let circuit = |a: Scalar, b: Scalar| MyCircuit {
+        constant,
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(constant * a.square() * b.square()),
+    };
+
+    let empty = MyCircuit {
+        constant,
+        ..Default::default()
+    };
+    let pk = keygen(params, &empty);
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let proofs = (0..n)
+        .map(|i| {
+            let circuit = circuit(Scalar::from(i as u64 + 2), Scalar::from(3));
+            prove::<ProverGWC<_>, _>(params, &pk, &[circuit], &[&[]], &mut rng)
+        })
+        .collect();
+
+    (pk, proofs)
+}
+
+/// Times the explicit verifier on `n` proofs of `MyCircuit` for every `n` in
+/// `ns`: once with a pairing check per proof, and once with a `BatchVerifier`.
+pub fn bench(ns: &[usize]) {
+    let k = 4;
+    let params: ParamsKZG<Bls12> = ParamsKZG::setup(k, StdRng::from_seed([0u8; 32]));
+    let (pk, proofs) = create_proofs(&params, ns.iter().copied().max().unwrap_or(0));
+    let pvk = PreparedVerifyingKey::new(pk.get_vk());
+
+    println!("{:>6} {:>14} {:>14} {:>10}", "n", "single (ms)", "batched (ms)", "speedup");
+    for &n in ns {
+        let start = Instant::now();
+        for proof in &proofs[..n] {
+            let (accumulator, _) = explicit_verify(&params, &pvk, proof, &[&[]]);
+            assert!(accumulator.check());
+        }
+        let single = start.elapsed();
+
+        let start = Instant::now();
+        let mut batch = BatchVerifier::new(&params);
+        for proof in &proofs[..n] {
+            batch.add(explicit_verify(&params, &pvk, proof, &[&[]]).0);
+        }
+        assert!(batch.finalize());
+        let batched = start.elapsed();
+
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        println!(
+            "{:>6} {:>14.2} {:>14.2} {:>9.2}x",
+            n,
+            ms(single),
+            ms(batched),
+            ms(single) / ms(batched)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::poly::commitment::MSM;
+
+    use super::*;
+
+    #[test]
+    fn batch() {
+        // Two proofs need only one pairing check in a batch, while a batch with
+        // one tampered accumulator must be rejected.
+        let params: ParamsKZG<Bls12> = ParamsKZG::setup(4, StdRng::from_seed([0u8; 32]));
+        let (pk, proofs) = create_proofs(&params, 2);
+        let pvk = PreparedVerifyingKey::new(pk.get_vk());
+
+        let mut batch = BatchVerifier::new(&params);
+        for proof in &proofs {
+            batch.add(explicit_verify(&params, &pvk, proof, &[&[]]).0);
+        }
+        assert!(batch.finalize());
+
+        let mut tampered = explicit_verify(&params, &pvk, &proofs[1], &[&[]]).0;
+        tampered.right.append_term(Scalar::ONE, params.g[0].into());
+        let mut batch = BatchVerifier::new(&params);
+        batch.add(explicit_verify(&params, &pvk, &proofs[0], &[&[]]).0);
+        batch.add(tampered);
+        assert!(!batch.finalize());
+    }
+}
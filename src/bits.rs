@@ -0,0 +1,389 @@
+use halo2_proofs::circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{
+    Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Selector,
+};
+use halo2_proofs::poly::Rotation;
+
+use crate::{FieldChip, FieldConfig, NumericInstructions, Number};
+
+pub trait BitInstructions<F: PrimeField>: NumericInstructions<F> {
+    /// Constrains `a` to be either 0 or 1.
+    fn assert_bool(&self, layouter: impl Layouter<F>, a: Self::Num) -> Result<(), Error>;
+
+    /// Decomposes `a` into `n` boolean cells, least significant bit first.
+    ///
+    /// When `n` equals `F::NUM_BITS` the decomposition is also constrained to be
+    /// canonical, i.e. the bits read as an integer are smaller than the modulus.
+    fn to_bits(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        n: usize,
+    ) -> Result<Vec<Self::Num>, Error>;
+
+    /// Returns `sum bits[i] * 2^i`, constraining every bit to be boolean. As with
+    /// `to_bits`, a full width input must be canonical.
+    fn from_bits(&self, layouter: impl Layouter<F>, bits: &[Self::Num]) -> Result<Self::Num, Error>;
+
+    /// Decomposes `a` like `to_bits`, into as many bits as `max` has, and
+    /// constrains them to read as an integer no larger than `max`. This range
+    /// checks `a` against a bound that is not a power of two, e.g. a scalar
+    /// against the order of a curve.
+    fn to_bits_at_most(&self, layouter: impl Layouter<F>, a: Self::Num, max: F) -> Result<Vec<Self::Num>, Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct BitConfig {
+    /// `advice[0]` holds the running sum, `advice[1]` the bits and `advice[2]`
+    /// the flag that tracks whether the bits so far equal those of `p - 1`.
+    advice: [Column<Advice>; 3],
+
+    /// The bits of `p - 1`, most significant first, used for the canonicity check,
+    /// or of the bound of `to_bits_at_most`.
+    modulus: Column<Fixed>,
+
+    s_bool: Selector,
+    s_decompose: Selector,
+    s_canonical: Selector,
+}
+
+impl<F: PrimeField> FieldChip<F> {
+    /// Adds the gates of `BitInstructions` to `config`. The running sum goes in
+    /// the first advice column of `config`, the bits in the second and the flag
+    /// of the canonicity check in `flag`.
+    ///
+    /// This is not part of `FieldChip::configure`, so that the layout of
+    /// `MyCircuit` (which the explicit verifier in `main` relies on) does not
+    /// change.
+    pub fn configure_bits(
+        meta: &mut ConstraintSystem<F>,
+        config: FieldConfig,
+        flag: Column<Advice>,
+        modulus: Column<Fixed>,
+    ) -> FieldConfig {
+        let advice = [config.advice[0], config.advice[1], flag];
+        for column in &advice {
+            meta.enable_equality(*column);
+        }
+        let s_bool = meta.selector();
+        let s_decompose = meta.selector();
+        let s_canonical = meta.selector();
+
+        let bool_constraint = |bit: Expression<F>| {
+            bit.clone() * (Expression::Constant(F::ONE) - bit)
+        };
+
+        meta.create_gate("bool", |meta| {
+            let a = meta.query_advice(advice[1], Rotation::cur());
+            let s_bool = meta.query_selector(s_bool);
+
+            vec![s_bool * bool_constraint(a)]
+        });
+
+        // The bits are consumed most significant first, just like the limbs of the
+        // range check chip:
+        //
+        // | a0   | a1    | a2   | modulus | s_decompose | s_canonical |
+        // |------|-------|------|---------|-------------|-------------|
+        // | 0    |       | 1    |         | 1           | 1           |
+        // | acc1 | bit_1 | eq_1 | c_1     | 1           | 1           |
+        // | ...  | ...   | ...  | ...     | ...         | ...         |
+        // | accn | bit_n | eq_n | c_n     | 0           | 0           |
+        //
+        // As in the `mul` gate, each row constrains the next one via `Rotation::next()`.
+        meta.create_gate("bit decompose", |meta| {
+            let acc = meta.query_advice(advice[0], Rotation::cur());
+            let acc_next = meta.query_advice(advice[0], Rotation::next());
+            let bit_next = meta.query_advice(advice[1], Rotation::next());
+            let s_decompose = meta.query_selector(s_decompose);
+
+            vec![
+                s_decompose.clone() * bool_constraint(bit_next.clone()),
+                s_decompose * (acc_next - (acc * F::from(2) + bit_next)),
+            ]
+        });
+
+        // eq_i is 1 while bits 1..=i equal the corresponding bits of `p - 1`. While
+        // that is the case, a bit may only be set where `p - 1` has a set bit, so
+        // the decomposition can never exceed `p - 1`.
+        meta.create_gate("canonical", |meta| {
+            let eq = meta.query_advice(advice[2], Rotation::cur());
+            let eq_next = meta.query_advice(advice[2], Rotation::next());
+            let bit_next = meta.query_advice(advice[1], Rotation::next());
+            let c_next = meta.query_fixed(modulus, Rotation::next());
+            let s_canonical = meta.query_selector(s_canonical);
+
+            let one = Expression::Constant(F::ONE);
+            let xor = bit_next.clone() + c_next.clone()
+                - bit_next.clone() * c_next.clone() * F::from(2);
+
+            vec![
+                s_canonical.clone() * (eq_next - eq.clone() * (one.clone() - xor)),
+                s_canonical * eq * (one - c_next) * bit_next,
+            ]
+        });
+
+        FieldConfig {
+            bits: Some(BitConfig {
+                advice,
+                modulus,
+                s_bool,
+                s_decompose,
+                s_canonical,
+            }),
+            ..config
+        }
+    }
+
+    fn bit_config(&self) -> &BitConfig {
+        self.config
+            .bits
+            .as_ref()
+            .expect("the bit instructions need FieldChip::configure_bits")
+    }
+
+    /// Assigns the running sum for `bits` (least significant first) into `region`
+    /// and returns the bit cells together with the recomposed value. If `copies`
+    /// is given, every bit is copy constrained to the corresponding cell. If
+    /// `max` is given, the bits must read as an integer no larger than it; a
+    /// full width decomposition always gets `p - 1`.
+    fn assign_running_sum(
+        &self,
+        region: &mut Region<'_, F>,
+        bits: &[Value<F>],
+        copies: Option<&[Number<F>]>,
+        max: Option<F>,
+    ) -> Result<(Vec<Number<F>>, AssignedCell<F, F>), Error> {
+        let config = self.bit_config();
+        let n = bits.len();
+        assert!(n <= F::NUM_BITS as usize, "cannot decompose into more than NUM_BITS bits");
+        let max = max.or((n == F::NUM_BITS as usize).then(|| -F::ONE));
+        let canonical = max.is_some();
+        let modulus_bits = le_bits(&max.unwrap_or(F::ZERO), n);
+
+        let mut acc =
+            region.assign_advice_from_constant(|| "initial accumulator", config.advice[0], 0, F::ZERO)?;
+        let mut eq = if canonical {
+            Some(region.assign_advice_from_constant(|| "initial eq", config.advice[2], 0, F::ONE)?)
+        } else {
+            None
+        };
+
+        let mut assigned_bits = Vec::with_capacity(n);
+        for (i, index) in (0..n).rev().enumerate() {
+            let offset = i + 1;
+            config.s_decompose.enable(region, i)?;
+
+            let bit = match copies {
+                Some(copies) => copies[index].0.copy_advice(|| "bit", region, config.advice[1], offset)?,
+                None => region.assign_advice(|| "bit", config.advice[1], offset, || bits[index])?,
+            };
+            let value = acc.value().copied() * Value::known(F::from(2)) + bit.value();
+            acc = region.assign_advice(|| "accumulator", config.advice[0], offset, || value)?;
+
+            if let Some(prev) = eq.take() {
+                config.s_canonical.enable(region, i)?;
+                let c = if modulus_bits[index] { F::ONE } else { F::ZERO };
+                region.assign_fixed(|| "modulus bit", config.modulus, offset, || Value::known(c))?;
+
+                let value = prev.value().copied().zip(bit.value().copied()).map(|(prev, bit)| {
+                    if bit == c {
+                        prev
+                    } else {
+                        F::ZERO
+                    }
+                });
+                eq = Some(region.assign_advice(|| "eq", config.advice[2], offset, || value)?);
+            }
+
+            assigned_bits.push(Number(bit));
+        }
+        assigned_bits.reverse();
+
+        Ok((assigned_bits, acc))
+    }
+
+    /// `to_bits`, with the bound `max` of `assign_running_sum`.
+    fn decompose(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Number<F>,
+        n: usize,
+        max: Option<F>,
+    ) -> Result<Vec<Number<F>>, Error> {
+        layouter.assign_region(
+            || "to bits",
+            |mut region| {
+                let bits = a
+                    .0
+                    .value()
+                    .map(|a| {
+                        le_bits(a, n)
+                            .into_iter()
+                            .map(|bit| if bit { F::ONE } else { F::ZERO })
+                            .collect::<Vec<_>>()
+                    })
+                    .transpose_vec(n);
+
+                let (bits, acc) = self.assign_running_sum(&mut region, &bits, None, max)?;
+                region.constrain_equal(acc.cell(), a.0.cell())?;
+
+                Ok(bits)
+            },
+        )
+    }
+}
+
+/// Returns the lowest `n` bits of the little-endian representation of `value`.
+pub fn le_bits<F: PrimeField>(value: &F, n: usize) -> Vec<bool> {
+    let repr = value.to_repr();
+    (0..n)
+        .map(|i| {
+            repr.as_ref()
+                .get(i / 8)
+                .map(|byte| (byte >> (i % 8)) & 1 == 1)
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+impl<F: PrimeField> BitInstructions<F> for FieldChip<F> {
+    fn assert_bool(&self, mut layouter: impl Layouter<F>, a: Self::Num) -> Result<(), Error> {
+        let config = self.bit_config();
+
+        layouter.assign_region(
+            || "assert bool",
+            |mut region| {
+                config.s_bool.enable(&mut region, 0)?;
+                a.0.copy_advice(|| "bit", &mut region, config.advice[1], 0)?;
+                Ok(())
+            },
+        )
+    }
+
+    fn to_bits(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        n: usize,
+    ) -> Result<Vec<Self::Num>, Error> {
+        self.decompose(layouter, a, n, None)
+    }
+
+    fn from_bits(&self, mut layouter: impl Layouter<F>, bits: &[Self::Num]) -> Result<Self::Num, Error> {
+        layouter.assign_region(
+            || "from bits",
+            |mut region| {
+                let values = bits.iter().map(|bit| bit.0.value().copied()).collect::<Vec<_>>();
+                let (_, acc) = self.assign_running_sum(&mut region, &values, Some(bits), None)?;
+
+                Ok(Number(acc))
+            },
+        )
+    }
+
+    fn to_bits_at_most(&self, layouter: impl Layouter<F>, a: Self::Num, max: F) -> Result<Vec<Self::Num>, Error> {
+        let n = le_bits(&max, F::NUM_BITS as usize)
+            .iter()
+            .rposition(|bit| *bit)
+            .map_or(0, |i| i + 1);
+        self.decompose(layouter, a, n, Some(max))
+    }
+}
+
+/// Loads `bits` as private inputs, recomposes them with `from_bits` and then
+/// decomposes the result again with `to_bits`, checking we get the same bits.
+pub struct BitsCircuit<F: PrimeField> {
+    pub bits: Vec<Value<F>>,
+}
+
+impl<F: PrimeField> Circuit<F> for BitsCircuit<F> {
+    type Config = FieldConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            bits: vec![Value::unknown(); self.bits.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+        let modulus = meta.fixed_column();
+
+        let config = FieldChip::configure(meta, [advice[0], advice[1]], constant);
+        FieldChip::configure_bits(meta, config, advice[2], modulus)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<F>::construct(config);
+
+        let bits = self
+            .bits
+            .iter()
+            .map(|bit| field_chip.load_private(layouter.namespace(|| "load bit"), *bit))
+            .collect::<Result<Vec<_>, _>>()?;
+        field_chip.assert_bool(layouter.namespace(|| "assert bool"), bits[0].clone())?;
+
+        let value = field_chip.from_bits(layouter.namespace(|| "from bits"), &bits)?;
+        let decomposed = field_chip.to_bits(layouter.namespace(|| "to bits"), value, bits.len())?;
+
+        layouter.assign_region(
+            || "compare bits",
+            |mut region| {
+                for (a, b) in bits.iter().zip(decomposed.iter()) {
+                    region.constrain_equal(a.0.cell(), b.0.cell())?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+    use halo2_proofs::halo2curves::ff::Field;
+
+    use super::*;
+
+    fn to_values(bits: &[bool]) -> Vec<Value<Scalar>> {
+        bits.iter()
+            .map(|bit| Value::known(if *bit { Scalar::ONE } else { Scalar::ZERO }))
+            .collect()
+    }
+
+    #[test]
+    fn canonical_bits() {
+        // The canonical 255 bit decomposition of p - 1 passes, the bits of p
+        // itself (which would decompose to 0 as well) do not.
+        let p_minus_one = le_bits(&-Scalar::ONE, Scalar::NUM_BITS as usize);
+        let mut p = p_minus_one.clone();
+        for bit in p.iter_mut() {
+            *bit = !*bit;
+            if *bit {
+                break;
+            }
+        }
+        let prover = MockProver::run(10, &BitsCircuit { bits: to_values(&p_minus_one) }, vec![]).unwrap();
+        prover.assert_satisfied();
+        let prover = MockProver::run(10, &BitsCircuit { bits: to_values(&p) }, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn not_boolean() {
+        let mut not_bool = to_values(&le_bits(&Scalar::from(0xbeef), 16));
+        not_bool[0] = Value::known(Scalar::from(2));
+        let prover = MockProver::run(10, &BitsCircuit { bits: not_bool }, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use std::cell::Cell;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{Circuit, Column, ConstraintSystem, Error, Instance};
+
+use crate::spread::{SpreadChip, SpreadConfig, Word, WordInstructions};
+
+pub const IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+pub const SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// The columns and message words mixed by the eight `G` calls of a round.
+const G_INDICES: [[usize; 4]; 8] = [
+    [0, 4, 8, 12],
+    [1, 5, 9, 13],
+    [2, 6, 10, 14],
+    [3, 7, 11, 15],
+    [0, 5, 10, 15],
+    [1, 6, 11, 12],
+    [2, 7, 8, 13],
+    [3, 4, 9, 14],
+];
+
+/// Output length in bytes. We only support unkeyed Blake2b-256.
+pub const OUT_LEN: usize = 32;
+
+/// Splits `message` into 128 byte blocks, padding the last one with zeros, and
+/// returns every block with its byte counter and whether it is the last one.
+pub fn blocks(message: &[u8]) -> Vec<(Vec<u8>, u128, bool)> {
+    let n_blocks = std::cmp::max(1, (message.len() + 127) / 128);
+    (0..n_blocks)
+        .map(|i| {
+            let mut block = message[128 * i..std::cmp::min(message.len(), 128 * (i + 1))].to_vec();
+            block.resize(128, 0);
+            let last = i == n_blocks - 1;
+            let counter = if last { message.len() } else { 128 * (i + 1) };
+            (block, counter as u128, last)
+        })
+        .collect()
+}
+
+/// The initial state, with the parameter block for an unkeyed hash of
+/// `OUT_LEN` bytes.
+pub fn initial_state() -> [u64; 8] {
+    let mut h = IV;
+    h[0] ^= 0x0101_0000 ^ OUT_LEN as u64;
+    h
+}
+
+/// The native compression function.
+pub fn compress(h: &mut [u64; 8], block: &[u8], counter: u128, last: bool) {
+    let mut m = [0u64; 16];
+    for (i, chunk) in block.chunks(8).enumerate() {
+        m[i] = u64::from_le_bytes(chunk.try_into().unwrap());
+    }
+
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&IV);
+    v[12] ^= counter as u64;
+    v[13] ^= (counter >> 64) as u64;
+    if last {
+        v[14] = !v[14];
+    }
+
+    for sigma in SIGMA.iter().cycle().take(12) {
+        for (j, [a, b, c, d]) in G_INDICES.iter().enumerate() {
+            let (x, y) = (m[sigma[2 * j]], m[sigma[2 * j + 1]]);
+            v[*a] = v[*a].wrapping_add(v[*b]).wrapping_add(x);
+            v[*d] = (v[*d] ^ v[*a]).rotate_right(32);
+            v[*c] = v[*c].wrapping_add(v[*d]);
+            v[*b] = (v[*b] ^ v[*c]).rotate_right(24);
+            v[*a] = v[*a].wrapping_add(v[*b]).wrapping_add(y);
+            v[*d] = (v[*d] ^ v[*a]).rotate_right(16);
+            v[*c] = v[*c].wrapping_add(v[*d]);
+            v[*b] = (v[*b] ^ v[*c]).rotate_right(63);
+        }
+    }
+
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+/// Native Blake2b-256, returning the digest as four little-endian words.
+pub fn blake2b(message: &[u8]) -> [u64; 4] {
+    let mut h = initial_state();
+    for (block, counter, last) in blocks(message) {
+        compress(&mut h, &block, counter, last);
+    }
+    [h[0], h[1], h[2], h[3]]
+}
+
+/// The compression function in the circuit, mirroring `compress`.
+pub fn compress_in_circuit<F: PrimeField>(
+    chip: &SpreadChip<F>,
+    mut layouter: impl Layouter<F>,
+    h: &[Word<F>; 8],
+    m: &[Word<F>; 16],
+    counter: u128,
+    last: bool,
+) -> Result<[Word<F>; 8], Error> {
+    let mut v = h.to_vec();
+    for (i, iv) in IV.iter().enumerate() {
+        // The counter and the finalization flag are known at keygen time, so they
+        // are folded into the constants. The counter never exceeds 64 bits here.
+        let mut iv = *iv;
+        if i == 4 {
+            iv ^= counter as u64;
+        }
+        if i == 6 && last {
+            iv = !iv;
+        }
+        v.push(chip.constant_word(layouter.namespace(|| "iv"), iv, 8)?);
+    }
+    assert_eq!(counter >> 64, 0);
+
+    for (round, sigma) in SIGMA.iter().cycle().take(12).enumerate() {
+        let mut layouter = layouter.namespace(|| format!("round {}", round));
+        for (j, [a, b, c, d]) in G_INDICES.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("G {}", j));
+            let (x, y) = (&m[sigma[2 * j]], &m[sigma[2 * j + 1]]);
+
+            v[*a] = chip.add(layouter.namespace(|| "a + b + x"), &[&v[*a], &v[*b], x])?;
+            let t = chip.xor(layouter.namespace(|| "d ^ a"), &[&v[*d], &v[*a]])?;
+            v[*d] = chip.rotr(layouter.namespace(|| "rotr 32"), &t, 32)?;
+            v[*c] = chip.add(layouter.namespace(|| "c + d"), &[&v[*c], &v[*d]])?;
+            let t = chip.xor(layouter.namespace(|| "b ^ c"), &[&v[*b], &v[*c]])?;
+            v[*b] = chip.rotr(layouter.namespace(|| "rotr 24"), &t, 24)?;
+            v[*a] = chip.add(layouter.namespace(|| "a + b + y"), &[&v[*a], &v[*b], y])?;
+            let t = chip.xor(layouter.namespace(|| "d ^ a"), &[&v[*d], &v[*a]])?;
+            v[*d] = chip.rotr(layouter.namespace(|| "rotr 16"), &t, 16)?;
+            v[*c] = chip.add(layouter.namespace(|| "c + d"), &[&v[*c], &v[*d]])?;
+            let t = chip.xor(layouter.namespace(|| "b ^ c"), &[&v[*b], &v[*c]])?;
+            v[*b] = chip.rotr(layouter.namespace(|| "rotr 63"), &t, 63)?;
+        }
+    }
+
+    let mut out = Vec::with_capacity(8);
+    for i in 0..8 {
+        out.push(chip.xor(layouter.namespace(|| "feed forward"), &[&h[i], &v[i], &v[i + 8]])?);
+    }
+    Ok(out.try_into().unwrap_or_else(|_| unreachable!()))
+}
+
+/// Proves knowledge of a message of a fixed length whose Blake2b-256 digest is
+/// the first four rows of the instance column, one little-endian word per row.
+pub struct Blake2bCircuit {
+    pub message: Vec<Value<u8>>,
+    /// Rows used by the spread chip in the last synthesis, excluding the table.
+    pub rows: Cell<usize>,
+}
+
+impl Blake2bCircuit {
+    pub fn new(message: Vec<Value<u8>>) -> Self {
+        Blake2bCircuit {
+            message,
+            rows: Cell::new(0),
+        }
+    }
+}
+
+impl<F: PrimeField> Circuit<F> for Blake2bCircuit {
+    type Config = (SpreadConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::new(vec![Value::unknown(); self.message.len()])
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (SpreadChip::configure(meta, advice, constant), instance)
+    }
+
+    fn synthesize(
+        &self,
+        (spread_config, instance): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let chip = SpreadChip::<F>::construct(spread_config);
+        chip.load_table(layouter.namespace(|| "load table"))?;
+
+        let len = self.message.len();
+        let mut h = Vec::with_capacity(8);
+        for word in initial_state() {
+            h.push(chip.constant_word(layouter.namespace(|| "initial state"), word, 8)?);
+        }
+        let mut h: [Word<F>; 8] = h.try_into().unwrap_or_else(|_| unreachable!());
+
+        for (b, (_, counter, last)) in blocks(&vec![0u8; len]).into_iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("block {}", b));
+
+            let mut words = Vec::with_capacity(16);
+            for j in 0..16 {
+                // Little-endian, and the zero padding is constrained by loading
+                // those words (or the padded bytes of a word) as constants.
+                let positions = (0..8).map(|k| 128 * b + 8 * j + k).collect::<Vec<_>>();
+                let word = if positions[0] >= len {
+                    chip.constant_word(layouter.namespace(|| "padding word"), 0, 8)?
+                } else {
+                    let value = positions.iter().rev().fold(Value::known(0u64), |acc, position| {
+                        let byte = self.message.get(*position).copied().unwrap_or(Value::known(0));
+                        acc.zip(byte).map(|(acc, byte)| (acc << 8) | byte as u64)
+                    });
+                    let word = chip.load_word(layouter.namespace(|| "message word"), value, 8)?;
+                    for (k, position) in positions.iter().enumerate() {
+                        if *position >= len {
+                            let cell = word.bytes[k].0.cell();
+                            layouter.assign_region(
+                                || "padding",
+                                |mut region| region.constrain_constant(cell, F::ZERO),
+                            )?;
+                        }
+                    }
+                    word
+                };
+                words.push(word);
+            }
+            let words: [Word<F>; 16] = words.try_into().unwrap_or_else(|_| unreachable!());
+
+            h = compress_in_circuit(&chip, layouter.namespace(|| "compress"), &h, &words, counter, last)?;
+        }
+
+        for (row, word) in h.iter().take(OUT_LEN / 8).enumerate() {
+            layouter.constrain_instance(word.dense.0.cell(), instance, row)?;
+        }
+        self.rows.set(chip.rows());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+    use halo2_proofs::halo2curves::ff::Field;
+
+    use super::*;
+
+    #[test]
+    fn abc() {
+        // The digest bytes bddd813c...c068d52319 of RFC 7693, read as
+        // little-endian words.
+        let digest = blake2b(b"abc");
+        assert_eq!(
+            digest,
+            [0x7239_4263_3c81_ddbd, 0x9b57_98ee_3fef_7131, 0x423e_cbb1_3b4e_9694, 0x1923_d568_c0c8_6272]
+        );
+        let message = b"abc".iter().map(|byte| Value::known(*byte)).collect::<Vec<_>>();
+        let instance = digest.iter().map(|word| Scalar::from(*word)).collect::<Vec<_>>();
+        let prover = MockProver::run(16, &Blake2bCircuit::new(message.clone()), vec![instance.clone()]).unwrap();
+        prover.assert_satisfied();
+        let mut wrong = instance;
+        wrong[0] += Scalar::ONE;
+        let prover = MockProver::run(16, &Blake2bCircuit::new(message), vec![wrong]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+use crate::range_check::{RangeCheckChip, RangeCheckConfig, RangeCheckInstructions, LIMB_BITS, NUM_LIMBS};
+use crate::{FieldChip, FieldConfig, NumericInstructions};
+
+#[derive(Clone, Debug)]
+pub struct ChainConfig {
+    /// One `FieldChip` for every pair of neighbouring advice columns.
+    field: Vec<FieldConfig>,
+    range: Option<RangeCheckConfig>,
+}
+
+/// A chain of `n_muls` multiplications x_{i+1} = x_i * a, starting from x_0 = a,
+/// for studying how the proof size, the prover and the explicit verifier scale
+/// with the number of multiplications, advice columns, fixed columns and
+/// lookups. The multiplications take turns on the `FieldChip`s of the
+/// `n_advice - 1` pairs of neighbouring advice columns, each with its own mul
+/// gate and with the `n_fixed` fixed columns in turn as constant column. With
+/// `with_lookup` every product is range checked to 16 bits by `RangeCheckChip`.
+/// `explicit_verify` rejects circuits with lookups, so those chains can only be
+/// verified with halo2's own verifier.
+///
+/// The columns and the lookup reach `configure_with_params` through
+/// `ChainParams`, since they change the constraint system.
+pub struct ChainCircuit<F: PrimeField> {
+    pub n_muls: usize,
+    pub n_advice: usize,
+    pub n_fixed: usize,
+    pub with_lookup: bool,
+    pub a: Value<F>,
+}
+
+/// The part of a `ChainCircuit` that shapes its constraint system.
+#[derive(Clone, Copy, Debug)]
+pub struct ChainParams {
+    pub n_advice: usize,
+    pub n_fixed: usize,
+    pub with_lookup: bool,
+}
+
+impl Default for ChainParams {
+    /// The columns of `MyCircuit`: two advice and one fixed.
+    fn default() -> Self {
+        ChainParams {
+            n_advice: 2,
+            n_fixed: 1,
+            with_lookup: false,
+        }
+    }
+}
+
+impl<F: PrimeField> Circuit<F> for ChainCircuit<F> {
+    type Config = ChainConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ChainParams;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            a: Value::unknown(),
+            ..*self
+        }
+    }
+
+    fn params(&self) -> Self::Params {
+        ChainParams {
+            n_advice: self.n_advice,
+            n_fixed: self.n_fixed,
+            with_lookup: self.with_lookup,
+        }
+    }
+
+    fn configure_with_params(meta: &mut ConstraintSystem<F>, params: Self::Params) -> Self::Config {
+        let ChainParams { n_advice, n_fixed, with_lookup } = params;
+        assert!(n_advice >= 2, "FieldChip needs two advice columns");
+        assert!(n_fixed >= 1, "FieldChip needs a constant column");
+        let advice = (0..n_advice).map(|_| meta.advice_column()).collect::<Vec<_>>();
+        let fixed = (0..n_fixed).map(|_| meta.fixed_column()).collect::<Vec<_>>();
+
+        let field = (0..n_advice - 1)
+            .map(|i| FieldChip::configure(meta, [advice[i], advice[i + 1]], fixed[i % n_fixed]))
+            .collect();
+        // Fixed columns that no chip got can still hold constants, so they are
+        // part of the permutation argument as well.
+        for column in fixed.iter().skip(n_advice - 1) {
+            meta.enable_constant(*column);
+        }
+        let range = with_lookup.then(|| RangeCheckChip::configure(meta, [advice[0], advice[1]], LIMB_BITS));
+
+        ChainConfig { field, range }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        Self::configure_with_params(meta, ChainParams::default())
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let field_chips = config.field.into_iter().map(FieldChip::<F>::construct).collect::<Vec<_>>();
+        let range_chip = config.range.map(RangeCheckChip::<F>::construct);
+        if let Some(range_chip) = &range_chip {
+            range_chip.load_table(layouter.namespace(|| "load table"))?;
+        }
+
+        let a = field_chips[0].load_private(layouter.namespace(|| "load a"), self.a)?;
+        let mut x = a.clone();
+        for i in 0..self.n_muls {
+            let field_chip = &field_chips[i % field_chips.len()];
+            x = field_chip.mul(layouter.namespace(|| "x * a"), x, a.clone())?;
+            if let Some(range_chip) = &range_chip {
+                let low = x.0.value().map(|x| {
+                    let repr = x.to_repr();
+                    F::from(u64::from(u16::from_le_bytes([repr.as_ref()[0], repr.as_ref()[1]])))
+                });
+                let low = field_chip.load_private(layouter.namespace(|| "load low bits of x"), low)?;
+                range_chip.range_check(layouter.namespace(|| "range check low bits of x"), low, NUM_LIMBS)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+
+    use super::*;
+    use crate::{explicit_verify, prove_and_verify, PreparedVerifyingKey};
+
+    #[test]
+    fn explicit_verify_more_columns() {
+        // Three advice and two fixed columns, which the explicit verifier
+        // handles like any other layout.
+        let chain = || ChainCircuit {
+            n_muls: 6,
+            n_advice: 3,
+            n_fixed: 2,
+            with_lookup: false,
+            a: Value::known(Scalar::from(2)),
+        };
+        MockProver::run(5, &chain(), vec![]).unwrap().assert_satisfied();
+        let (params, pk, proof) = prove_and_verify(5, chain(), &[]);
+        let (accumulator, _) = explicit_verify(&params, &PreparedVerifyingKey::new(pk.get_vk()), &proof, &[&[]]);
+        assert!(accumulator.check());
+    }
+
+    #[test]
+    fn with_lookup() {
+        // The products outgrow 16 bits after 15 doublings, but only their low
+        // bits are range checked. Only halo2's verifier takes these, as the
+        // explicit one has no lookups.
+        let chain = ChainCircuit {
+            n_muls: 40,
+            n_advice: 2,
+            n_fixed: 1,
+            with_lookup: true,
+            a: Value::known(Scalar::from(3)),
+        };
+        MockProver::run(9, &chain, vec![]).unwrap().assert_satisfied();
+        prove_and_verify(9, chain, &[]);
+    }
+}
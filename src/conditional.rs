@@ -0,0 +1,286 @@
+use halo2_proofs::circuit::{Layouter, Region, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::Field;
+use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Selector};
+use halo2_proofs::poly::Rotation;
+
+use crate::{FieldChip, FieldConfig, NumericInstructions, Number};
+
+pub trait ConditionalInstructions<F: Field>: NumericInstructions<F> {
+    /// Returns 1 if `a = 0` and 0 otherwise.
+    fn is_zero(&self, layouter: impl Layouter<F>, a: Self::Num) -> Result<Self::Num, Error>;
+
+    /// Returns 1 if `a = b` and 0 otherwise.
+    fn is_equal(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns `a` if `cond = 1` and `b` if `cond = 0`. `cond` is constrained to
+    /// be boolean.
+    fn select(
+        &self,
+        layouter: impl Layouter<F>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+
+    /// Returns `(b, a)` if `cond = 1` and `(a, b)` if `cond = 0`.
+    fn cond_swap(
+        &self,
+        layouter: impl Layouter<F>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<(Self::Num, Self::Num), Error>;
+}
+
+#[derive(Clone, Debug)]
+pub struct ConditionalConfig {
+    advice: [Column<Advice>; 3],
+    s_is_equal: Selector,
+    s_select: Selector,
+}
+
+impl<F: Field> FieldChip<F> {
+    /// Adds the gates of `ConditionalInstructions` to `config`, which turn
+    /// comparisons into assigned booleans, and booleans back into a choice
+    /// between two values. They use the advice columns of `config` and `third`.
+    ///
+    /// As with `FieldChip::configure_bits`, this is not part of
+    /// `FieldChip::configure`, so that the layout of `MyCircuit` does not change.
+    pub fn configure_conditional(
+        meta: &mut ConstraintSystem<F>,
+        config: FieldConfig,
+        third: Column<Advice>,
+    ) -> FieldConfig {
+        let advice = [config.advice[0], config.advice[1], third];
+        for column in &advice {
+            meta.enable_equality(*column);
+        }
+        let s_is_equal = meta.selector();
+        let s_select = meta.selector();
+
+        // | a0  | a1 | a2  | s_is_equal |
+        // |-----|----|-----|------------|
+        // | a   | b  | inv | s_is_equal |
+        // | out |    |     |            |
+        //
+        // With `out = 1 - (a - b) * inv` and `(a - b) * out = 0`: if a != b the
+        // second constraint forces out = 0, and if a = b the first forces out = 1.
+        // The prover picks inv = 1 / (a - b), or anything when a = b.
+        meta.create_gate("is equal", |meta| {
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let inv = meta.query_advice(advice[2], Rotation::cur());
+            let out = meta.query_advice(advice[0], Rotation::next());
+            let s_is_equal = meta.query_selector(s_is_equal);
+
+            let diff = a - b;
+            let one = Expression::Constant(F::ONE);
+            vec![
+                s_is_equal.clone() * (out.clone() - (one - diff.clone() * inv)),
+                s_is_equal * diff * out,
+            ]
+        });
+
+        // | a0   | a1 | a2 | s_select |
+        // |------|----|----|----------|
+        // | cond | a  | b  | s_select |
+        // | out  |    |    |          |
+        meta.create_gate("select", |meta| {
+            let cond = meta.query_advice(advice[0], Rotation::cur());
+            let a = meta.query_advice(advice[1], Rotation::cur());
+            let b = meta.query_advice(advice[2], Rotation::cur());
+            let out = meta.query_advice(advice[0], Rotation::next());
+            let s_select = meta.query_selector(s_select);
+
+            let one = Expression::Constant(F::ONE);
+            vec![
+                s_select.clone() * cond.clone() * (one - cond.clone()),
+                s_select * (out - (b.clone() + cond * (a - b))),
+            ]
+        });
+
+        FieldConfig {
+            conditional: Some(ConditionalConfig {
+                advice,
+                s_is_equal,
+                s_select,
+            }),
+            ..config
+        }
+    }
+
+    fn conditional_config(&self) -> &ConditionalConfig {
+        self.config
+            .conditional
+            .as_ref()
+            .expect("the conditional instructions need FieldChip::configure_conditional")
+    }
+}
+
+impl<F: Field> ConditionalInstructions<F> for FieldChip<F> {
+    fn is_zero(&self, mut layouter: impl Layouter<F>, a: Self::Num) -> Result<Self::Num, Error> {
+        let config = self.conditional_config();
+
+        let zero = layouter.assign_region(
+            || "load zero",
+            |mut region| {
+                region
+                    .assign_advice_from_constant(|| "zero", config.advice[1], 0, F::ZERO)
+                    .map(Number)
+            },
+        )?;
+
+        self.is_equal(layouter, a, zero)
+    }
+
+    fn is_equal(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        let config = self.conditional_config();
+
+        layouter.assign_region(
+            || "is equal",
+            |mut region: Region<'_, F>| {
+                config.s_is_equal.enable(&mut region, 0)?;
+
+                a.0.copy_advice(|| "a", &mut region, config.advice[0], 0)?;
+                b.0.copy_advice(|| "b", &mut region, config.advice[1], 0)?;
+
+                let diff = a.0.value().copied() - b.0.value();
+                let inv = diff.map(|diff| diff.invert().unwrap_or(F::ZERO));
+                region.assign_advice(|| "inv", config.advice[2], 0, || inv)?;
+
+                let out = diff.map(|diff| if diff.is_zero_vartime() { F::ONE } else { F::ZERO });
+                region
+                    .assign_advice(|| "a == b", config.advice[0], 1, || out)
+                    .map(Number)
+            },
+        )
+    }
+
+    fn select(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        let config = self.conditional_config();
+
+        layouter.assign_region(
+            || "select",
+            |mut region: Region<'_, F>| {
+                config.s_select.enable(&mut region, 0)?;
+
+                cond.0.copy_advice(|| "cond", &mut region, config.advice[0], 0)?;
+                a.0.copy_advice(|| "a", &mut region, config.advice[1], 0)?;
+                b.0.copy_advice(|| "b", &mut region, config.advice[2], 0)?;
+
+                let value = cond
+                    .0
+                    .value()
+                    .zip(a.0.value().zip(b.0.value()))
+                    .map(|(cond, (a, b))| if cond.is_zero_vartime() { *b } else { *a });
+                region
+                    .assign_advice(|| "cond ? a : b", config.advice[0], 1, || value)
+                    .map(Number)
+            },
+        )
+    }
+
+    fn cond_swap(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cond: Self::Num,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<(Self::Num, Self::Num), Error> {
+        let left = self.select(layouter.namespace(|| "left"), cond.clone(), b.clone(), a.clone())?;
+        let right = self.select(layouter.namespace(|| "right"), cond, a, b)?;
+
+        Ok((left, right))
+    }
+}
+
+/// Proves `out = if a == b { a } else { a == 0 }`, which uses the conditional
+/// instructions.
+#[derive(Default)]
+pub struct ConditionalCircuit<F: Field> {
+    pub a: Value<F>,
+    pub b: Value<F>,
+    pub out: Value<F>,
+}
+
+impl<F: Field> Circuit<F> for ConditionalCircuit<F> {
+    type Config = FieldConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+
+        let config = FieldChip::configure(meta, [advice[0], advice[1]], constant);
+        FieldChip::configure_conditional(meta, config, advice[2])
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<F>::construct(config);
+
+        let a = field_chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = field_chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+        let out = field_chip.load_private(layouter.namespace(|| "load out"), self.out)?;
+
+        let a_is_zero = field_chip.is_zero(layouter.namespace(|| "a == 0"), a.clone())?;
+        let a_eq_b = field_chip.is_equal(layouter.namespace(|| "a == b"), a.clone(), b)?;
+        let result = field_chip.select(
+            layouter.namespace(|| "a == b ? a : a == 0"),
+            a_eq_b,
+            a,
+            a_is_zero,
+        )?;
+
+        layouter.assign_region(
+            || "Assert equality",
+            |mut region| region.constrain_equal(result.0.cell(), out.0.cell()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+
+    use super::*;
+
+    #[test]
+    fn conditional() {
+        // out = if a == b { a } else { a == 0 }
+        let conditional = |a: u64, b: u64, out: u64| ConditionalCircuit {
+            a: Value::known(Scalar::from(a)),
+            b: Value::known(Scalar::from(b)),
+            out: Value::known(Scalar::from(out)),
+        };
+        for (a, b, out, ok) in [(3, 3, 3, true), (3, 4, 0, true), (0, 4, 1, true), (3, 4, 3, false)] {
+            let prover = MockProver::run(5, &conditional(a, b, out), vec![]).unwrap();
+            assert_eq!(prover.verify().is_ok(), ok);
+        }
+    }
+}
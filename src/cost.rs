@@ -0,0 +1,330 @@
+use std::cell::RefCell;
+use std::io;
+use std::ops::AddAssign;
+use std::time::Instant;
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2_proofs::halo2curves::bls12_381::{Bls12, G1Affine, Scalar};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{verify_proof, Circuit, ProvingKey};
+use halo2_proofs::poly::commitment::{Verifier, MSM};
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::msm::DualMSM;
+use halo2_proofs::poly::kzg::multiopen::{ProverGWC, ProverSHPLONK, VerifierGWC, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::{GuardKZG, SingleStrategy};
+use halo2_proofs::transcript::{
+    Blake2bRead, Challenge255, EncodedChallenge, Transcript, TranscriptRead, TranscriptReadBuffer,
+};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::{explicit_verify_transcript, keygen, prove};
+use crate::layout::Layout;
+use crate::prepared::PreparedVerifyingKey;
+
+/// The work done by (a phase of) the verifier.
+#[derive(Clone, Debug, Default)]
+pub struct Counts {
+    /// The number of terms of every multi-scalar multiplication.
+    pub msms: Vec<usize>,
+    pub field_adds: usize,
+    pub field_muls: usize,
+    pub inversions: usize,
+    /// The inversions a batch inversion did with multiplications instead.
+    pub inversions_saved: usize,
+    pub points_read: usize,
+    pub scalars_read: usize,
+    pub squeezes: usize,
+    pub blake2b_blocks: usize,
+    pub pairings: usize,
+}
+
+impl AddAssign<&Counts> for Counts {
+    fn add_assign(&mut self, other: &Counts) {
+        self.msms.extend(&other.msms);
+        self.field_adds += other.field_adds;
+        self.field_muls += other.field_muls;
+        self.inversions += other.inversions;
+        self.inversions_saved += other.inversions_saved;
+        self.points_read += other.points_read;
+        self.scalars_read += other.scalars_read;
+        self.squeezes += other.squeezes;
+        self.blake2b_blocks += other.blake2b_blocks;
+        self.pairings += other.pairings;
+    }
+}
+
+/// The counts of a measured run, per phase in the order they started.
+#[derive(Clone, Debug, Default)]
+pub struct Cost {
+    pub phases: Vec<(&'static str, Counts)>,
+}
+
+impl Cost {
+    pub fn total(&self) -> Counts {
+        let mut total = Counts::default();
+        for (_, counts) in &self.phases {
+            total += counts;
+        }
+        total
+    }
+}
+
+thread_local! {
+    static COST: RefCell<Option<Cost>> = RefCell::new(None);
+}
+
+/// Runs `f`, counting everything the verifier records meanwhile.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Cost) {
+    COST.with(|cost| *cost.borrow_mut() = Some(Cost::default()));
+    let result = f();
+    let cost = COST.with(|cost| cost.borrow_mut().take()).unwrap_or_default();
+    (result, cost)
+}
+
+/// Starts a new phase, if we are measuring.
+pub fn phase(name: &'static str) {
+    COST.with(|cost| {
+        if let Some(cost) = cost.borrow_mut().as_mut() {
+            cost.phases.push((name, Counts::default()));
+        }
+    });
+}
+
+/// Adds to the counts of the current phase, if we are measuring. Without a phase
+/// the counts go to an unnamed one.
+pub fn record(f: impl FnOnce(&mut Counts)) {
+    COST.with(|cost| {
+        if let Some(cost) = cost.borrow_mut().as_mut() {
+            if cost.phases.is_empty() {
+                cost.phases.push(("", Counts::default()));
+            }
+            f(&mut cost.phases.last_mut().unwrap().1);
+        }
+    });
+}
+
+/// Wraps a transcript to count what it reads and squeezes, and the Blake2b
+/// blocks halo2's `Blake2bRead` compresses for it. `Blake2bRead` absorbs a
+/// prefix byte and the coordinates of a point, a prefix byte and the scalar, and
+/// a prefix byte before every squeeze. Its running state compresses a block once
+/// it has a full one and more input arrives, and a squeeze finalizes a copy of
+/// that state, which compresses the last one.
+pub struct CountingTranscript<T> {
+    inner: T,
+    absorbed: usize,
+}
+
+impl<T> CountingTranscript<T> {
+    pub fn new(inner: T) -> Self {
+        CountingTranscript { inner, absorbed: 0 }
+    }
+
+    fn compressed(absorbed: usize) -> usize {
+        absorbed.saturating_sub(1) / 128
+    }
+
+    fn absorb(&mut self, bytes: usize) {
+        let before = Self::compressed(self.absorbed);
+        self.absorbed += bytes;
+        let blocks = Self::compressed(self.absorbed) - before;
+        record(|counts| counts.blake2b_blocks += blocks);
+    }
+
+    fn point_bytes<C: CurveAffine>() -> usize {
+        1 + 2 * <C::Base as PrimeField>::Repr::default().as_ref().len()
+    }
+
+    fn scalar_bytes<C: CurveAffine>() -> usize {
+        1 + <C::Scalar as PrimeField>::Repr::default().as_ref().len()
+    }
+}
+
+impl<C: CurveAffine, E: EncodedChallenge<C>, T: Transcript<C, E>> Transcript<C, E> for CountingTranscript<T> {
+    fn squeeze_challenge(&mut self) -> E {
+        self.absorb(1);
+        record(|counts| {
+            counts.squeezes += 1;
+            counts.blake2b_blocks += 1;
+        });
+        self.inner.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.absorb(Self::point_bytes::<C>());
+        self.inner.common_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.absorb(Self::scalar_bytes::<C>());
+        self.inner.common_scalar(scalar)
+    }
+}
+
+impl<C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>> TranscriptRead<C, E> for CountingTranscript<T> {
+    fn read_point(&mut self) -> io::Result<C> {
+        let point = self.inner.read_point()?;
+        self.absorb(Self::point_bytes::<C>());
+        record(|counts| counts.points_read += 1);
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let scalar = self.inner.read_scalar()?;
+        self.absorb(Self::scalar_bytes::<C>());
+        record(|counts| counts.scalars_read += 1);
+        Ok(scalar)
+    }
+}
+
+/// The cost of `explicit_verify` on `proof`, with the values of the instance
+/// columns `instances`, including the pairing check of the accumulator it returns.
+pub fn explicit_verify_cost(
+    params: &ParamsKZG<Bls12>,
+    pk: &ProvingKey<G1Affine>,
+    proof: &[u8],
+    instances: &[&[Scalar]],
+) -> Cost {
+    // Preparing the key is done once for all proofs, so it is not measured.
+    let pvk = PreparedVerifyingKey::new(pk.get_vk());
+    let (accepted, cost) = measure(|| {
+        phase("transcript");
+        let mut transcript = CountingTranscript::new(Blake2bRead::<_, _, Challenge255<_>>::init(proof));
+        let (accumulator, _) = explicit_verify_transcript(params, &pvk, &mut transcript, &[instances]);
+
+        // `DualMSM::check` evaluates both sides, each one MSM of the terms the
+        // verifier appended, and computes e(left, [s]_2) * e(-right, [1]_2) with
+        // a single final exponentiation.
+        phase("pairing");
+        record(|counts| {
+            counts.msms.push(accumulator.left.bases().len());
+            counts.msms.push(accumulator.right.bases().len());
+            counts.pairings += 2;
+        });
+        accumulator.check()
+    });
+    assert!(accepted);
+    cost
+}
+
+/// The cost of halo2's own verifier with the multiopen argument `V`. Only the
+/// transcript is counted, as the verifier itself is not instrumented.
+fn transcript_cost<'params, V>(
+    params: &'params ParamsKZG<Bls12>,
+    pk: &ProvingKey<G1Affine>,
+    proof: &[u8],
+    instances: &[&[Scalar]],
+) -> Cost
+where
+    V: Verifier<
+        'params,
+        KZGCommitmentScheme<Bls12>,
+        MSMAccumulator = DualMSM<'params, Bls12>,
+        Guard = GuardKZG<'params, Bls12>,
+    >,
+{
+    let (result, cost) = measure(|| {
+        phase("transcript");
+        verify_proof::<_, V, _, _, _>(
+            params,
+            pk.get_vk(),
+            SingleStrategy::new(params),
+            &[instances],
+            &mut CountingTranscript::new(Blake2bRead::<_, _, Challenge255<_>>::init(proof)),
+        )
+    });
+    result.expect("Verification failed");
+    cost
+}
+
+fn print_counts(name: &str, counts: &Counts) {
+    println!(
+        "{:<12} {:>12} {:>8} {:>8} {:>5} {:>5} {:>7} {:>8} {:>8} {:>7} {:>5}",
+        name,
+        format!("{:?}", counts.msms),
+        counts.field_adds,
+        counts.field_muls,
+        counts.inversions,
+        counts.inversions_saved,
+        counts.points_read,
+        counts.scalars_read,
+        counts.squeezes,
+        counts.blake2b_blocks,
+        counts.pairings,
+    );
+}
+
+/// Prints how long proving the circuit built by `circuit` takes, and what
+/// verifying its proof costs: per phase of the explicit verifier, which uses
+/// GWC, and only the transcript reads of halo2's verifier with GWC and with
+/// SHPLONK.
+/// `instances` has the values of its instance columns.
+pub fn report<C: Circuit<Scalar>>(name: &str, k: u32, instances: &[&[Scalar]], circuit: impl Fn() -> C) {
+    let params: ParamsKZG<Bls12> = ParamsKZG::setup(k, StdRng::from_seed([0u8; 32]));
+    let pk = keygen(&params, &circuit());
+    let layout = Layout::new(&pk.get_vk().cs, 1);
+    layout.print::<G1Affine>(&format!("{} (k = {})", name, k));
+
+    let start = Instant::now();
+    let gwc_proof = prove::<ProverGWC<_>, _>(&params, &pk, &[circuit()], &[instances], StdRng::from_seed([0u8; 32]));
+    println!("  {:<32} {:>7.2} ms with GWC", "prover", start.elapsed().as_secs_f64() * 1000.0);
+    let shplonk_proof =
+        prove::<ProverSHPLONK<_>, _>(&params, &pk, &[circuit()], &[instances], StdRng::from_seed([0u8; 32]));
+    assert_eq!(gwc_proof.len(), layout.proof_bytes::<G1Affine>(false));
+    assert_eq!(shplonk_proof.len(), layout.proof_bytes::<G1Affine>(true));
+
+    println!(
+        "{:<12} {:>12} {:>8} {:>8} {:>5} {:>5} {:>7} {:>8} {:>8} {:>7} {:>5}",
+        "phase", "msms", "f add", "f mul", "inv", "saved", "points", "scalars", "squeeze", "blake2b", "pair"
+    );
+    let cost = explicit_verify_cost(&params, &pk, &gwc_proof, instances);
+    for (phase, counts) in &cost.phases {
+        print_counts(phase, counts);
+    }
+    print_counts("total", &cost.total());
+
+    // halo2's verifier is not instrumented, so GWC and SHPLONK are only
+    // compared by what their transcripts read, not by the work they do.
+    println!("halo2's verifier, transcript reads only:");
+
+    for (scheme, proof, cost) in [
+        ("gwc", &gwc_proof, transcript_cost::<VerifierGWC<_>>(&params, &pk, &gwc_proof, instances)),
+        ("shplonk", &shplonk_proof, transcript_cost::<VerifierSHPLONK<_>>(&params, &pk, &shplonk_proof, instances)),
+    ] {
+        let total = cost.total();
+        println!(
+            "  {:<7} proof {:>5} bytes, {} points, {} scalars, {} squeezes, {} blake2b blocks",
+            scheme,
+            proof.len(),
+            total.points_read,
+            total.scalars_read,
+            total.squeezes,
+            total.blake2b_blocks
+        );
+    }
+    println!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{my_circuit, prove_and_verify};
+
+    #[test]
+    fn explicit_verify_of_my_circuit() {
+        // Three points give a left MSM of three terms, and the 18 queries, with
+        // both pieces of H, the three opening proofs and g0 a right one of 23.
+        // The pairing check is a single product of two pairings.
+        let (params, pk, proof) = prove_and_verify(4, my_circuit(2, 3), &[]);
+        let total = explicit_verify_cost(&params, &pk, &proof, &[]).total();
+        assert_eq!(total.msms, vec![3, 23]);
+        assert_eq!(total.pairings, 2);
+        assert_eq!(total.points_read, 2 + 3 + 1 + 2 + 3);
+        assert_eq!(total.squeezes, 7);
+        // With the prepared key, x^n is computed by squaring, and the Lagrange
+        // denominators of the last row, the five blinding rows and the first
+        // row share one inversion with x^n - 1.
+        assert_eq!(total.inversions, 1);
+        assert_eq!(total.inversions_saved, 7);
+    }
+}
@@ -0,0 +1,156 @@
+use halo2_proofs::halo2curves::bls12_381::{Bls12, Scalar};
+use halo2_proofs::halo2curves::ff::Field;
+use halo2_proofs::plonk::{keygen_vk, Circuit, ConstraintSystem, Expression};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// Why h(X) has the number of pieces it has. Every constraint of degree d is
+/// divided by X^n - 1, which leaves a quotient of degree (d - 1) * n, so the
+/// largest degree of the circuit decides the number of pieces of n coefficients
+/// the prover commits to, and the extended domain h(X) is computed on.
+#[derive(Clone, Debug)]
+pub struct Degrees {
+    /// Every gate constraint, permutation, lookup and shuffle argument with
+    /// the degree it needs.
+    pub items: Vec<(String, usize)>,
+    /// The degree of the circuit, at least that of every item.
+    pub degree: usize,
+    /// d - 1, the number of pieces of h(X).
+    pub quotient_degree: usize,
+    /// The smallest k' with 2^k' >= (d - 1) * n.
+    pub extended_k: u32,
+    pub k: u32,
+}
+
+/// The largest degree of `expressions`, but at least 1.
+fn max_degree<F: Field>(expressions: &[Expression<F>]) -> usize {
+    expressions.iter().map(Expression::degree).max().unwrap_or(1).max(1)
+}
+
+impl Degrees {
+    /// The degrees of `cs`, which should come from the verifying key, as the key
+    /// generation turns the selectors into fixed columns.
+    pub fn new<F: Field>(cs: &ConstraintSystem<F>, k: u32) -> Self {
+        let mut items = Vec::new();
+        for gate in cs.gates() {
+            for (i, polynomial) in gate.polynomials().iter().enumerate() {
+                let name = match gate.constraint_name(i) {
+                    "" => format!("gate \"{}\" constraint {}", gate.name(), i),
+                    constraint => format!("gate \"{}\" constraint \"{}\"", gate.name(), constraint),
+                };
+                items.push((name, polynomial.degree()));
+            }
+        }
+
+        // z(omega X) (p(X) + beta s(X) + gamma) - z(X) (p(X) + delta beta X + gamma)
+        // with (1 - (l_last(X) + l_blind(X))) has degree 3 for a single column,
+        // and the columns are chunked to fit the degree of the circuit.
+        let columns = cs.permutation().get_columns().len();
+        if columns > 0 {
+            items.push((format!("permutation of {} columns", columns), 3));
+        }
+
+        // The lookup constraint multiplies z(X) with the compressed input and
+        // table, and the active rows.
+        for lookup in cs.lookups() {
+            let input = max_degree(lookup.input_expressions());
+            let table = max_degree(lookup.table_expressions());
+            items.push((format!("lookup \"{}\"", lookup.name()), (2 + input + table).max(4)));
+        }
+
+        // The shuffle constraint multiplies z(X) with either compressed side,
+        // and the active rows.
+        for shuffle in cs.shuffles() {
+            let input = max_degree(shuffle.input_expressions());
+            let shuffled = max_degree(shuffle.shuffle_expressions());
+            items.push((format!("shuffle \"{}\"", shuffle.name()), 2 + input.max(shuffled)));
+        }
+
+        let degree = cs.degree();
+        let quotient_degree = degree - 1;
+        let mut extended_k = k;
+        while (1u64 << extended_k) < (1u64 << k) * quotient_degree as u64 {
+            extended_k += 1;
+        }
+
+        Degrees {
+            items,
+            degree,
+            quotient_degree,
+            extended_k,
+            k,
+        }
+    }
+
+    /// The permutation argument gets a product for every chunk of d - 2 columns.
+    pub fn permutation_chunk_len(&self) -> usize {
+        self.degree - 2
+    }
+
+    pub fn print(&self, name: &str) {
+        println!("{} (k = {})", name, self.k);
+        for (item, degree) in &self.items {
+            let marker = if *degree == self.degree { "  <- degree of the circuit" } else { "" };
+            println!("  {:<48} {:>3}{}", item, degree, marker);
+        }
+        if self.items.iter().all(|(_, degree)| *degree < self.degree) {
+            println!("  {:<48} {:>3}", "minimum degree", self.degree);
+        }
+        println!(
+            "  degree {} gives a quotient of degree {} * n in {} H pieces, computed on 2^{} = {} rows, with {} column(s) per permutation product",
+            self.degree,
+            self.quotient_degree,
+            self.quotient_degree,
+            self.extended_k,
+            1u64 << self.extended_k,
+            self.permutation_chunk_len()
+        );
+    }
+}
+
+/// Prints the degrees of `circuit`, after generating its verifying key.
+pub fn report<C: Circuit<Scalar>>(name: &str, k: u32, circuit: &C) {
+    let params: ParamsKZG<Bls12> = ParamsKZG::setup(k, StdRng::from_seed([0u8; 32]));
+    let vk = keygen_vk(&params, circuit).expect("keygen_vk should not fail");
+    let degrees = Degrees::new(&vk.cs, k);
+    assert_eq!(degrees.quotient_degree, vk.get_domain().get_quotient_poly_degree());
+    assert_eq!(degrees.extended_k, vk.get_domain().extended_k());
+    degrees.print(name);
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::circuit::Value;
+
+    use super::*;
+    use crate::my_circuit;
+    use crate::range_check::RangeCheckCircuit;
+
+    /// The degrees of `circuit`, checked against the domain of its verifying key.
+    fn degrees<C: Circuit<Scalar>>(k: u32, circuit: &C) -> Degrees {
+        let params: ParamsKZG<Bls12> = ParamsKZG::setup(k, StdRng::from_seed([0u8; 32]));
+        let vk = keygen_vk(&params, circuit).expect("keygen_vk should not fail");
+        let degrees = Degrees::new(&vk.cs, k);
+        assert_eq!(degrees.quotient_degree, vk.get_domain().get_quotient_poly_degree());
+        assert_eq!(degrees.extended_k, vk.get_domain().extended_k());
+        degrees
+    }
+
+    #[test]
+    fn degree_of_my_circuit() {
+        // H is split in two pieces because the mul gate and the permutation
+        // argument both have degree 3, which leaves a quotient of degree 2n.
+        let degrees = degrees(4, &my_circuit(2, 3));
+        assert_eq!(degrees.degree, 3);
+        assert_eq!(degrees.quotient_degree, 2);
+    }
+
+    #[test]
+    fn degree_of_lookup() {
+        // The selector times the limb and the table column make the lookup of
+        // degree 5.
+        let degrees = degrees(6, &RangeCheckCircuit { value: Value::known(Scalar::from(0xbeef)) });
+        assert_eq!(degrees.degree, 5);
+    }
+}
@@ -0,0 +1,611 @@
+use halo2_proofs::circuit::{Chip, Layouter, Region, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{
+    Advice, Circuit, Column, ConstraintSystem, Error, Expression, Fixed, Instance, Selector,
+};
+use halo2_proofs::poly::Rotation;
+
+use crate::bits::{le_bits, BitInstructions};
+use crate::conditional::ConditionalInstructions;
+use crate::{FieldChip, FieldConfig, NumericInstructions, Number};
+
+/// A twisted Edwards curve `a * x^2 + y^2 = 1 + d * x^2 * y^2` over `F`, whose
+/// points are `cofactor` times a prime order subgroup of order `order`.
+///
+/// With `a` a square and `d` a non-square (Jubjub) the addition law is
+/// complete, so the chip never has to deal with exceptional cases (doubling,
+/// identity, ...). With both non-squares (Bandersnatch) its denominators only
+/// vanish when `p + q` or `p - q` is a point at infinity, which has order 2 or
+/// 4, so the law is still complete on the prime order subgroup. Doubling has
+/// no exceptions either way, as `d` is a non-square.
+#[derive(Clone, Copy, Debug)]
+pub struct EdwardsCurve<F: PrimeField> {
+    pub a: F,
+    pub d: F,
+    /// A power of two.
+    pub cofactor: u64,
+    /// Smaller than the modulus of `F`, so it fits in a field element.
+    pub order: F,
+}
+
+/// An affine point on an `EdwardsCurve`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EdwardsPoint<F: PrimeField> {
+    pub x: F,
+    pub y: F,
+}
+
+impl<F: PrimeField> EdwardsCurve<F> {
+    /// Jubjub, which is defined over the BLS12-381 scalar field:
+    /// `-x^2 + y^2 = 1 - (10240 / 10241) * x^2 * y^2`.
+    pub fn jubjub() -> Self {
+        EdwardsCurve {
+            a: -F::ONE,
+            d: -(F::from(10240) * F::from(10241).invert().unwrap()),
+            cofactor: 8,
+            order: F::from_str_vartime(
+                "6554484396890773809930967563523245729705921265872317281365359162392183254199",
+            )
+            .unwrap(),
+        }
+    }
+
+    /// Bandersnatch, also defined over the BLS12-381 scalar field, whose
+    /// endomorphism makes it faster outside circuits:
+    /// `-5 * x^2 + y^2 = 1 + d * x^2 * y^2`, with
+    /// `d = 138827208126141220649022263972958607803 / 171449701953573178309673572579671231137`.
+    pub fn bandersnatch() -> Self {
+        EdwardsCurve {
+            a: -F::from(5),
+            d: F::from_u128(138827208126141220649022263972958607803)
+                * F::from_u128(171449701953573178309673572579671231137).invert().unwrap(),
+            cofactor: 4,
+            order: F::from_str_vartime(
+                "13108968793781547619861935127046491459309155893440570251786403306729687672801",
+            )
+            .unwrap(),
+        }
+    }
+
+    pub fn identity(&self) -> EdwardsPoint<F> {
+        EdwardsPoint { x: F::ZERO, y: F::ONE }
+    }
+
+    pub fn is_on_curve(&self, p: &EdwardsPoint<F>) -> bool {
+        let x2 = p.x.square();
+        let y2 = p.y.square();
+        self.a * x2 + y2 == F::ONE + self.d * x2 * y2
+    }
+
+    pub fn add(&self, p: &EdwardsPoint<F>, q: &EdwardsPoint<F>) -> EdwardsPoint<F> {
+        let t = self.d * p.x * q.x * p.y * q.y;
+        EdwardsPoint {
+            x: (p.x * q.y + p.y * q.x) * (F::ONE + t).invert().unwrap(),
+            y: (p.y * q.y - self.a * p.x * q.x) * (F::ONE - t).invert().unwrap(),
+        }
+    }
+
+    pub fn double(&self, p: &EdwardsPoint<F>) -> EdwardsPoint<F> {
+        self.add(p, p)
+    }
+
+    pub fn is_in_subgroup(&self, p: &EdwardsPoint<F>) -> bool {
+        self.is_on_curve(p) && self.mul(p, &self.order) == self.identity()
+    }
+
+    /// Returns `[1 / cofactor mod order] p`, which for `p` in the prime order
+    /// subgroup is the point of the subgroup that `cofactor` times gives `p`.
+    pub fn divide_by_cofactor(&self, p: &EdwardsPoint<F>) -> EdwardsPoint<F> {
+        // 1 / cofactor mod order is (j * order + 1) / cofactor, for the j that
+        // makes it an integer. With j < cofactor the numerator is smaller than
+        // cofactor * order, which is below the modulus of F for both curves, so
+        // the division in F is the one of the integers.
+        assert!(self.cofactor.is_power_of_two());
+        let order_low = self.order.to_repr().as_ref()[0] as u64 % self.cofactor;
+        let j = (0..self.cofactor)
+            .find(|j| (j * order_low + 1) % self.cofactor == 0)
+            .unwrap();
+        let inverse = (F::from(j) * self.order + F::ONE) * F::from(self.cofactor).invert().unwrap();
+        self.mul(p, &inverse)
+    }
+
+    /// Computes `[k] p`, reading `k` as an integer smaller than the field modulus.
+    pub fn mul(&self, p: &EdwardsPoint<F>, k: &F) -> EdwardsPoint<F> {
+        le_bits(k, F::NUM_BITS as usize)
+            .iter()
+            .rev()
+            .fold(self.identity(), |acc, bit| {
+                let acc = self.double(&acc);
+                if *bit {
+                    self.add(&acc, p)
+                } else {
+                    acc
+                }
+            })
+    }
+
+    /// The point with the smallest `y >= 11` that lies on the curve, multiplied
+    /// by the cofactor so it generates the prime order subgroup. For Jubjub this
+    /// starts from the same `y = 11` point as the `jubjub` crate's full generator.
+    pub fn generator(&self) -> EdwardsPoint<F> {
+        let mut y = F::from(11);
+        loop {
+            // x^2 = (y^2 - 1) / (d * y^2 - a)
+            let y2 = y.square();
+            let x2 = (y2 - F::ONE) * (self.d * y2 - self.a).invert().unwrap();
+            if let Some(x) = Option::<F>::from(x2.sqrt()) {
+                let p = self.mul(&EdwardsPoint { x, y }, &F::from(self.cofactor));
+                if p != self.identity() {
+                    return p;
+                }
+            }
+            y += F::ONE;
+        }
+    }
+}
+
+/// A point assigned in the circuit.
+#[derive(Clone)]
+pub struct EccPoint<F: PrimeField> {
+    pub x: Number<F>,
+    pub y: Number<F>,
+}
+
+pub trait EccInstructions<F: PrimeField>: Chip<F> {
+    /// Variable representing a curve point.
+    type Point;
+    /// Variable representing a scalar.
+    type Num;
+
+    /// Loads a point of the prime order subgroup as a private input. A point
+    /// outside of it cannot be loaded: the constraints only hold for a
+    /// different point, of the subgroup.
+    fn witness_point(
+        &self,
+        layouter: impl Layouter<F>,
+        p: Value<EdwardsPoint<F>>,
+    ) -> Result<Self::Point, Error>;
+
+    /// Loads a point as a fixed constant.
+    fn constant_point(&self, layouter: impl Layouter<F>, p: EdwardsPoint<F>) -> Result<Self::Point, Error>;
+
+    /// Returns `p + q`.
+    fn add(&self, layouter: impl Layouter<F>, p: &Self::Point, q: &Self::Point) -> Result<Self::Point, Error>;
+
+    /// Returns `[2] p`.
+    fn double(&self, layouter: impl Layouter<F>, p: &Self::Point) -> Result<Self::Point, Error>;
+
+    /// Returns `[k] base` for a base point known at keygen time.
+    fn mul_fixed(
+        &self,
+        layouter: impl Layouter<F>,
+        base: EdwardsPoint<F>,
+        k: Self::Num,
+    ) -> Result<Self::Point, Error>;
+
+    /// Returns `[k] p` for a point assigned in the circuit.
+    fn mul(&self, layouter: impl Layouter<F>, p: &Self::Point, k: Self::Num) -> Result<Self::Point, Error>;
+}
+
+/// An ECC chip for a twisted Edwards curve embedded in the circuit field.
+///
+/// Scalars are decomposed into canonical bits and points are chosen with the
+/// bit and conditional instructions of `FieldChip`, so this chip only adds the
+/// curve gates.
+pub struct EccChip<F: PrimeField> {
+    config: EccConfig<F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EccConfig<F: PrimeField> {
+    advice: [Column<Advice>; 4],
+    s_add: Selector,
+    s_on_curve: Selector,
+    curve: EdwardsCurve<F>,
+    field: FieldConfig,
+}
+
+impl<F: PrimeField> EccChip<F> {
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
+        Self { config }
+    }
+
+    /// Configures the chip for `curve`. `field` must use the first two advice
+    /// columns, and gets the bit and conditional instructions on the first three.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 4],
+        field: FieldConfig,
+        modulus: Column<Fixed>,
+        curve: EdwardsCurve<F>,
+    ) -> <Self as Chip<F>>::Config {
+        for column in &advice {
+            meta.enable_equality(*column);
+        }
+        let s_add = meta.selector();
+        let s_on_curve = meta.selector();
+
+        // | a0 | a1 | a2 | a3 | s_add |
+        // |----|----|----|----|-------|
+        // | x1 | y1 | x2 | y2 | 1     |
+        // | x3 | y3 |    |    |       |
+        //
+        // x3 * (1 + d * x1 * x2 * y1 * y2) = x1 * y2 + y1 * x2
+        // y3 * (1 - d * x1 * x2 * y1 * y2) = y1 * y2 - a * x1 * x2
+        meta.create_gate("edwards add", |meta| {
+            let x1 = meta.query_advice(advice[0], Rotation::cur());
+            let y1 = meta.query_advice(advice[1], Rotation::cur());
+            let x2 = meta.query_advice(advice[2], Rotation::cur());
+            let y2 = meta.query_advice(advice[3], Rotation::cur());
+            let x3 = meta.query_advice(advice[0], Rotation::next());
+            let y3 = meta.query_advice(advice[1], Rotation::next());
+            let s_add = meta.query_selector(s_add);
+
+            let one = Expression::Constant(F::ONE);
+            let t = x1.clone() * x2.clone() * y1.clone() * y2.clone() * curve.d;
+            vec![
+                s_add.clone()
+                    * (x3 * (one.clone() + t.clone()) - (x1.clone() * y2.clone() + y1.clone() * x2.clone())),
+                s_add * (y3 * (one - t) - (y1 * y2 - x1 * x2 * curve.a)),
+            ]
+        });
+
+        meta.create_gate("on curve", |meta| {
+            let x = meta.query_advice(advice[0], Rotation::cur());
+            let y = meta.query_advice(advice[1], Rotation::cur());
+            let s_on_curve = meta.query_selector(s_on_curve);
+
+            let x2 = x.clone() * x;
+            let y2 = y.clone() * y;
+            vec![
+                s_on_curve
+                    * (x2.clone() * curve.a + y2.clone()
+                        - Expression::Constant(F::ONE)
+                        - x2 * y2 * curve.d),
+            ]
+        });
+
+        assert_eq!(field.advice, [advice[0], advice[1]]);
+        let field = FieldChip::configure_bits(meta, field, advice[2], modulus);
+        let field = FieldChip::configure_conditional(meta, field, advice[2]);
+
+        EccConfig {
+            advice,
+            s_add,
+            s_on_curve,
+            curve,
+            field,
+        }
+    }
+
+    pub fn curve(&self) -> &EdwardsCurve<F> {
+        &self.config.curve
+    }
+
+    /// The `FieldChip` of the chip, with the bit and conditional instructions.
+    pub fn field_chip(&self) -> FieldChip<F> {
+        FieldChip::construct(self.config.field.clone())
+    }
+
+    fn select(
+        &self,
+        mut layouter: impl Layouter<F>,
+        cond: &Number<F>,
+        p: &EccPoint<F>,
+        q: &EccPoint<F>,
+    ) -> Result<EccPoint<F>, Error> {
+        let field = self.field_chip();
+        Ok(EccPoint {
+            x: field.select(layouter.namespace(|| "x"), cond.clone(), p.x.clone(), q.x.clone())?,
+            y: field.select(layouter.namespace(|| "y"), cond.clone(), p.y.clone(), q.y.clone())?,
+        })
+    }
+
+    fn scalar_bits(&self, layouter: impl Layouter<F>, k: Number<F>) -> Result<Vec<Number<F>>, Error> {
+        self.field_chip().to_bits(layouter, k, F::NUM_BITS as usize)
+    }
+}
+
+impl<F: PrimeField> Chip<F> for EccChip<F> {
+    type Config = EccConfig<F>;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: PrimeField> EccInstructions<F> for EccChip<F> {
+    type Point = EccPoint<F>;
+    type Num = Number<F>;
+
+    fn witness_point(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: Value<EdwardsPoint<F>>,
+    ) -> Result<Self::Point, Error> {
+        let config = self.config();
+        let curve = config.curve;
+
+        // The points of the prime order subgroup are the multiples of the
+        // cofactor: [cofactor] q kills any small order component of q. So we
+        // witness q = p / cofactor, constrain it to be on the curve, and double
+        // it back to p.
+        let q = layouter.assign_region(
+            || "witness point / cofactor",
+            |mut region| {
+                config.s_on_curve.enable(&mut region, 0)?;
+                let q = p.map(|p| curve.divide_by_cofactor(&p));
+                let x = region.assign_advice(|| "x", config.advice[0], 0, || q.map(|q| q.x))?;
+                let y = region.assign_advice(|| "y", config.advice[1], 0, || q.map(|q| q.y))?;
+                Ok(EccPoint {
+                    x: Number(x),
+                    y: Number(y),
+                })
+            },
+        )?;
+
+        (0..curve.cofactor.trailing_zeros()).try_fold(q, |point, i| {
+            self.double(layouter.namespace(|| format!("double {}", i)), &point)
+        })
+    }
+
+    fn constant_point(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: EdwardsPoint<F>,
+    ) -> Result<Self::Point, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "constant point",
+            |mut region| {
+                let x = region.assign_advice_from_constant(|| "x", config.advice[0], 0, p.x)?;
+                let y = region.assign_advice_from_constant(|| "y", config.advice[1], 0, p.y)?;
+                Ok(EccPoint {
+                    x: Number(x),
+                    y: Number(y),
+                })
+            },
+        )
+    }
+
+    fn add(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &Self::Point,
+        q: &Self::Point,
+    ) -> Result<Self::Point, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "add",
+            |mut region: Region<'_, F>| {
+                config.s_add.enable(&mut region, 0)?;
+
+                p.x.0.copy_advice(|| "x1", &mut region, config.advice[0], 0)?;
+                p.y.0.copy_advice(|| "y1", &mut region, config.advice[1], 0)?;
+                q.x.0.copy_advice(|| "x2", &mut region, config.advice[2], 0)?;
+                q.y.0.copy_advice(|| "y2", &mut region, config.advice[3], 0)?;
+
+                let r = p
+                    .x
+                    .0
+                    .value()
+                    .zip(p.y.0.value())
+                    .zip(q.x.0.value().zip(q.y.0.value()))
+                    .map(|((px, py), (qx, qy))| {
+                        config.curve.add(
+                            &EdwardsPoint { x: *px, y: *py },
+                            &EdwardsPoint { x: *qx, y: *qy },
+                        )
+                    });
+                let x = region.assign_advice(|| "x3", config.advice[0], 1, || r.map(|r| r.x))?;
+                let y = region.assign_advice(|| "y3", config.advice[1], 1, || r.map(|r| r.y))?;
+                Ok(EccPoint {
+                    x: Number(x),
+                    y: Number(y),
+                })
+            },
+        )
+    }
+
+    fn double(&self, layouter: impl Layouter<F>, p: &Self::Point) -> Result<Self::Point, Error> {
+        // The addition law is complete, so doubling is just adding p to itself.
+        self.add(layouter, p, p)
+    }
+
+    fn mul_fixed(
+        &self,
+        mut layouter: impl Layouter<F>,
+        base: EdwardsPoint<F>,
+        k: Self::Num,
+    ) -> Result<Self::Point, Error> {
+        let curve = *self.curve();
+        let bits = self.scalar_bits(layouter.namespace(|| "scalar bits"), k)?;
+        let identity = self.constant_point(layouter.namespace(|| "identity"), curve.identity())?;
+
+        // Since the base is known, so are all of its doublings [2^i] base, and we
+        // only need to add the ones selected by the bits of k.
+        let mut acc = identity.clone();
+        let mut power = base;
+        for (i, bit) in bits.iter().enumerate() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            let power_point = self.constant_point(layouter.namespace(|| "[2^i] base"), power)?;
+            let term = self.select(layouter.namespace(|| "select term"), bit, &power_point, &identity)?;
+            acc = self.add(layouter.namespace(|| "accumulate"), &acc, &term)?;
+            power = curve.double(&power);
+        }
+
+        Ok(acc)
+    }
+
+    fn mul(
+        &self,
+        mut layouter: impl Layouter<F>,
+        p: &Self::Point,
+        k: Self::Num,
+    ) -> Result<Self::Point, Error> {
+        let curve = *self.curve();
+        let bits = self.scalar_bits(layouter.namespace(|| "scalar bits"), k)?;
+        let identity = self.constant_point(layouter.namespace(|| "identity"), curve.identity())?;
+
+        // Double and add, from the most significant bit down. As for `mul_fixed`,
+        // completeness means we can start from the identity.
+        let mut acc = identity;
+        for (i, bit) in bits.iter().enumerate().rev() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            acc = self.double(layouter.namespace(|| "double"), &acc)?;
+            let sum = self.add(layouter.namespace(|| "add"), &acc, p)?;
+            acc = self.select(layouter.namespace(|| "select"), bit, &sum, &acc)?;
+        }
+
+        Ok(acc)
+    }
+}
+
+/// Proves `[k] G = A` and `[k] P = B` on `curve` for a private scalar `k` and
+/// point `P`, where `G` is the generator and `A` and `B` are public. This
+/// exercises both the fixed and the variable base multiplication. The curve
+/// reaches `configure_with_params` as the parameters of the circuit.
+#[derive(Default)]
+pub struct EccCircuit<F: PrimeField> {
+    pub curve: EdwardsCurve<F>,
+    pub k: Value<F>,
+    pub p: Value<EdwardsPoint<F>>,
+}
+
+impl<F: PrimeField> Default for EdwardsCurve<F> {
+    fn default() -> Self {
+        Self::jubjub()
+    }
+}
+
+impl<F: PrimeField> Circuit<F> for EccCircuit<F> {
+    type Config = (FieldConfig, EccConfig<F>, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = EdwardsCurve<F>;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            curve: self.curve,
+            ..Self::default()
+        }
+    }
+
+    fn params(&self) -> Self::Params {
+        self.curve
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        Self::configure_with_params(meta, EdwardsCurve::default())
+    }
+
+    fn configure_with_params(meta: &mut ConstraintSystem<F>, curve: Self::Params) -> Self::Config {
+        let advice = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let constant = meta.fixed_column();
+        let modulus = meta.fixed_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        let field_config = FieldChip::configure(meta, [advice[0], advice[1]], constant);
+        let ecc_config = EccChip::configure(meta, advice, field_config.clone(), modulus, curve);
+
+        (field_config, ecc_config, instance)
+    }
+
+    fn synthesize(
+        &self,
+        (field_config, ecc_config, instance): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<F>::construct(field_config);
+        let ecc_chip = EccChip::<F>::construct(ecc_config);
+        let generator = ecc_chip.curve().generator();
+
+        let k = field_chip.load_private(layouter.namespace(|| "load k"), self.k)?;
+        let p = ecc_chip.witness_point(layouter.namespace(|| "load P"), self.p)?;
+
+        let a = ecc_chip.mul_fixed(layouter.namespace(|| "[k] G"), generator, k.clone())?;
+        let b = ecc_chip.mul(layouter.namespace(|| "[k] P"), &p, k)?;
+
+        for (row, coordinate) in [a.x, a.y, b.x, b.y].iter().enumerate() {
+            layouter.constrain_instance(coordinate.0.cell(), instance, row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+    use halo2_proofs::halo2curves::ff::Field;
+
+    use super::*;
+
+    #[test]
+    fn bandersnatch_generator() {
+        // The generator of the arkworks implementation of Bandersnatch, which
+        // checks our d and subgroup order.
+        let bandersnatch = EdwardsCurve::<Scalar>::bandersnatch();
+        let ark_generator = EdwardsPoint {
+            x: Scalar::from_str_vartime("18886178867200960497001835917649091219057080094937609519140440539760939937304").unwrap(),
+            y: Scalar::from_str_vartime("19188667384257783945677642223292697773471335439753913231509108946878080696678").unwrap(),
+        };
+        assert!(bandersnatch.is_in_subgroup(&ark_generator));
+    }
+
+    #[test]
+    fn mul() {
+        // Fixed and variable base multiplication on Jubjub and on Bandersnatch,
+        // checked against the native curve arithmetic.
+        for curve in [EdwardsCurve::<Scalar>::jubjub(), EdwardsCurve::bandersnatch()] {
+            let generator = curve.generator();
+            assert!(curve.is_in_subgroup(&generator));
+            let k = Scalar::from(0x1234_5678);
+            let p = curve.mul(&generator, &Scalar::from(5));
+            let a = curve.mul(&generator, &k);
+            let b = curve.mul(&p, &k);
+            let ecc_circuit = EccCircuit {
+                curve,
+                k: Value::known(k),
+                p: Value::known(p),
+            };
+            let prover = MockProver::run(13, &ecc_circuit, vec![vec![a.x, a.y, b.x, b.y]]).unwrap();
+            prover.assert_satisfied();
+            let prover = MockProver::run(13, &ecc_circuit, vec![vec![b.x, b.y, a.x, a.y]]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+
+    #[test]
+    fn point_outside_subgroup() {
+        // A point of Jubjub with a component of order 2 cannot be loaded: the
+        // circuit multiplies the point of the subgroup instead. `k` is odd, so
+        // that [k] P keeps the component.
+        let jubjub = EdwardsCurve::<Scalar>::jubjub();
+        let order_two = EdwardsPoint { x: Scalar::ZERO, y: -Scalar::ONE };
+        let p = jubjub.add(&jubjub.mul(&jubjub.generator(), &Scalar::from(5)), &order_two);
+        assert!(jubjub.is_on_curve(&p) && !jubjub.is_in_subgroup(&p));
+        let k = Scalar::from(0x1234_5679);
+        let a = jubjub.mul(&jubjub.generator(), &k);
+        let b = jubjub.mul(&p, &k);
+        let ecc_circuit = EccCircuit {
+            curve: jubjub,
+            k: Value::known(k),
+            p: Value::known(p),
+        };
+        let prover = MockProver::run(13, &ecc_circuit, vec![vec![a.x, a.y, b.x, b.y]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
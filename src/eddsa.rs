@@ -0,0 +1,312 @@
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{Circuit, Column, ConstraintSystem, Error, Instance};
+
+use crate::ecc::{EccChip, EccConfig, EccInstructions, EdwardsCurve, EdwardsPoint};
+use crate::poseidon::{PoseidonChip, PoseidonConfig, PoseidonInstructions, PoseidonParams};
+use crate::bits::BitInstructions;
+use crate::conditional::ConditionalInstructions;
+use crate::{FieldChip, NumericInstructions};
+
+/// Order of the prime order subgroup of Jubjub, as little-endian limbs.
+const JUBJUB_ORDER: [u64; 4] = [
+    0xd097_0e5e_d6f7_2cb7,
+    0xa668_2093_ccc8_1082,
+    0x0667_3b01_0134_3b00,
+    0x0e7d_b4ea_6533_afa9,
+];
+
+fn to_limbs<F: PrimeField>(f: &F) -> [u64; 4] {
+    let repr = f.to_repr();
+    let mut limbs = [0u64; 4];
+    for (i, byte) in repr.as_ref().iter().enumerate().take(32) {
+        limbs[i / 8] |= (*byte as u64) << (8 * (i % 8));
+    }
+    limbs
+}
+
+fn from_limbs<F: PrimeField>(limbs: &[u64; 4]) -> F {
+    let mut repr = F::Repr::default();
+    for (i, byte) in repr.as_mut().iter_mut().enumerate().take(32) {
+        *byte = (limbs[i / 8] >> (8 * (i % 8))) as u8;
+    }
+    F::from_repr(repr).unwrap()
+}
+
+/// Returns `a + b mod JUBJUB_ORDER` for `a, b < JUBJUB_ORDER`. The order has 252
+/// bits, so the sum cannot overflow 256 bits.
+fn add_mod(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    let mut sum = [0u64; 4];
+    let mut carry = 0u128;
+    for i in 0..4 {
+        let s = a[i] as u128 + b[i] as u128 + carry;
+        sum[i] = s as u64;
+        carry = s >> 64;
+    }
+
+    let mut reduced = [0u64; 4];
+    let mut borrow = 0i128;
+    for i in 0..4 {
+        let d = sum[i] as i128 - JUBJUB_ORDER[i] as i128 - borrow;
+        reduced[i] = d as u64;
+        borrow = (d < 0) as i128;
+    }
+    if borrow == 0 {
+        reduced
+    } else {
+        sum
+    }
+}
+
+/// Returns `a * b mod JUBJUB_ORDER` by double and add over the bits of `a`. Only
+/// `b` has to be reduced, `a` can be any 256 bit value.
+fn mul_mod(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+    (0..256).rev().fold([0u64; 4], |acc, i| {
+        let acc = add_mod(&acc, &acc);
+        if (a[i / 64] >> (i % 64)) & 1 == 1 {
+            add_mod(&acc, b)
+        } else {
+            acc
+        }
+    })
+}
+
+/// Whether `a < JUBJUB_ORDER`.
+fn is_reduced(a: &[u64; 4]) -> bool {
+    a.iter().rev().lt(JUBJUB_ORDER.iter().rev())
+}
+
+/// Reduces a field element modulo the Jubjub subgroup order.
+fn reduce<F: PrimeField>(f: &F) -> [u64; 4] {
+    mul_mod(&to_limbs(f), &[1, 0, 0, 0])
+}
+
+/// The challenge `c = H(R, A, m)` binding the signature to the key and message.
+pub fn challenge<F: PrimeField>(
+    params: &PoseidonParams<F>,
+    r: &EdwardsPoint<F>,
+    public_key: &EdwardsPoint<F>,
+    message: F,
+) -> F {
+    params.hash(&[r.x, r.y, public_key.x, public_key.y, message])
+}
+
+/// Returns the public key `A = [a] G` for the secret key `a`.
+pub fn public_key<F: PrimeField>(curve: &EdwardsCurve<F>, secret: F) -> EdwardsPoint<F> {
+    curve.mul(&curve.generator(), &secret)
+}
+
+/// Signs `message` with the secret key `a`, returning `(R, s)` such that
+/// `[s] G = R + [c] A`. The nonce is derived deterministically as `H(a, m)`.
+pub fn sign<F: PrimeField>(
+    curve: &EdwardsCurve<F>,
+    params: &PoseidonParams<F>,
+    secret: F,
+    message: F,
+) -> (EdwardsPoint<F>, F) {
+    let generator = curve.generator();
+    let nonce = from_limbs::<F>(&reduce(&params.hash(&[secret, message])));
+    let r = curve.mul(&generator, &nonce);
+    let c = challenge(params, &r, &public_key(curve, secret), message);
+
+    // s = nonce + c * a mod l, so the in-circuit multiplication by the unreduced
+    // c still matches since [c] A = [c mod l] A.
+    let s = add_mod(&to_limbs(&nonce), &mul_mod(&reduce(&c), &reduce(&secret)));
+    (r, from_limbs(&s))
+}
+
+/// Native verification, performing the same checks as `SignatureCircuit`.
+pub fn verify<F: PrimeField>(
+    curve: &EdwardsCurve<F>,
+    params: &PoseidonParams<F>,
+    public_key: &EdwardsPoint<F>,
+    message: F,
+    (r, s): &(EdwardsPoint<F>, F),
+) -> bool {
+    let is_valid_point = |p: &EdwardsPoint<F>| curve.is_in_subgroup(p) && *p != curve.identity();
+    if !is_valid_point(public_key) || !is_valid_point(r) || !is_reduced(&to_limbs(s)) {
+        return false;
+    }
+    let c = challenge(params, r, public_key, message);
+    let lhs = curve.mul(&curve.generator(), s);
+    let rhs = curve.add(r, &curve.mul(public_key, &c));
+    lhs == rhs
+}
+
+/// Verifies an EdDSA signature `(R, s)` over Jubjub with a Poseidon challenge.
+///
+/// The instance column holds `A.x`, `A.y` and the message digest `m`, in that
+/// order. The signature itself is private.
+///
+/// `A` and `R` are loaded into the prime order subgroup, where the identity is
+/// the only point of small order, and both must differ from it. `s` must be
+/// smaller than the order, so that a signature has a single encoding.
+#[derive(Default)]
+pub struct SignatureCircuit<F: PrimeField> {
+    pub public_key: Value<EdwardsPoint<F>>,
+    pub message: Value<F>,
+    pub r: Value<EdwardsPoint<F>>,
+    pub s: Value<F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SignatureConfig<F: PrimeField> {
+    ecc: EccConfig<F>,
+    poseidon: PoseidonConfig<F>,
+    instance: Column<Instance>,
+}
+
+impl<F: PrimeField> Circuit<F> for SignatureCircuit<F> {
+    type Config = SignatureConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+            meta.advice_column(),
+        ];
+        let round_constants = [meta.fixed_column(), meta.fixed_column(), meta.fixed_column()];
+        let constant = meta.fixed_column();
+        let modulus = meta.fixed_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        let field = FieldChip::configure(meta, [advice[0], advice[1]], constant);
+        SignatureConfig {
+            ecc: EccChip::configure(meta, advice, field, modulus, EdwardsCurve::jubjub()),
+            poseidon: PoseidonChip::configure(meta, [advice[0], advice[1], advice[2]], round_constants),
+            instance,
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let ecc_chip = EccChip::<F>::construct(config.ecc);
+        // The field chip of the ECC chip also has the bit and conditional
+        // instructions, for the checks of A, R and s.
+        let field_chip = ecc_chip.field_chip();
+        let poseidon_chip = PoseidonChip::<F>::construct(config.poseidon);
+        let generator = ecc_chip.curve().generator();
+
+        let public_key = ecc_chip.witness_point(layouter.namespace(|| "load A"), self.public_key)?;
+        let message = field_chip.load_private(layouter.namespace(|| "load m"), self.message)?;
+        let r = ecc_chip.witness_point(layouter.namespace(|| "load R"), self.r)?;
+        let s = field_chip.load_private(layouter.namespace(|| "load s"), self.s)?;
+
+        // In the subgroup x = 0 only holds for the identity.
+        for (name, point) in [("A", &public_key), ("R", &r)] {
+            let is_identity =
+                field_chip.is_zero(layouter.namespace(|| format!("{}.x == 0", name)), point.x.clone())?;
+            layouter.assign_region(
+                || format!("{} is not the identity", name),
+                |mut region| region.constrain_constant(is_identity.0.cell(), F::ZERO),
+            )?;
+        }
+        let order = ecc_chip.curve().order;
+        field_chip.to_bits_at_most(layouter.namespace(|| "s < order"), s.clone(), order - F::ONE)?;
+
+        layouter.constrain_instance(public_key.x.0.cell(), config.instance, 0)?;
+        layouter.constrain_instance(public_key.y.0.cell(), config.instance, 1)?;
+        layouter.constrain_instance(message.0.cell(), config.instance, 2)?;
+
+        let c = poseidon_chip.hash(
+            layouter.namespace(|| "challenge"),
+            &[r.x.clone(), r.y.clone(), public_key.x.clone(), public_key.y.clone(), message],
+        )?;
+
+        // [s] G = R + [c] A
+        let lhs = ecc_chip.mul_fixed(layouter.namespace(|| "[s] G"), generator, s)?;
+        let c_a = ecc_chip.mul(layouter.namespace(|| "[c] A"), &public_key, c)?;
+        let rhs = ecc_chip.add(layouter.namespace(|| "R + [c] A"), &r, &c_a)?;
+
+        layouter.assign_region(
+            || "Assert equality",
+            |mut region| {
+                region.constrain_equal(lhs.x.0.cell(), rhs.x.0.cell())?;
+                region.constrain_equal(lhs.y.0.cell(), rhs.y.0.cell())
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+    use halo2_proofs::halo2curves::ff::Field;
+
+    use super::*;
+
+    const SECRET: u64 = 0xdead_beef;
+
+    #[test]
+    fn signature() {
+        // The public key and message digest are the instances.
+        let (jubjub, poseidon) = (EdwardsCurve::<Scalar>::jubjub(), PoseidonParams::<Scalar>::new());
+        let public_key = public_key(&jubjub, Scalar::from(SECRET));
+        let message = poseidon.hash(&[Scalar::from(1), Scalar::from(2)]);
+        let signature = sign(&jubjub, &poseidon, Scalar::from(SECRET), message);
+        assert!(verify(&jubjub, &poseidon, &public_key, message, &signature));
+        let signature_circuit = SignatureCircuit {
+            public_key: Value::known(public_key),
+            message: Value::known(message),
+            r: Value::known(signature.0),
+            s: Value::known(signature.1),
+        };
+        let instance = vec![public_key.x, public_key.y, message];
+        let prover = MockProver::run(13, &signature_circuit, vec![instance.clone()]).unwrap();
+        prover.assert_satisfied();
+        let forged = SignatureCircuit {
+            s: Value::known(signature.1 + Scalar::ONE),
+            ..signature_circuit
+        };
+        let prover = MockProver::run(13, &forged, vec![instance]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn unreduced_s() {
+        // s + order satisfies [s] G = R + [c] A as well, but is not the
+        // canonical encoding of s.
+        let (jubjub, poseidon) = (EdwardsCurve::<Scalar>::jubjub(), PoseidonParams::<Scalar>::new());
+        let public_key = public_key(&jubjub, Scalar::from(SECRET));
+        let message = poseidon.hash(&[Scalar::from(1), Scalar::from(2)]);
+        let signature = sign(&jubjub, &poseidon, Scalar::from(SECRET), message);
+        let unreduced = (signature.0, signature.1 + jubjub.order);
+        assert!(!verify(&jubjub, &poseidon, &public_key, message, &unreduced));
+        let forged = SignatureCircuit {
+            public_key: Value::known(public_key),
+            message: Value::known(message),
+            r: Value::known(unreduced.0),
+            s: Value::known(unreduced.1),
+        };
+        let prover = MockProver::run(13, &forged, vec![vec![public_key.x, public_key.y, message]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn identity_public_key() {
+        // With the identity as public key, any R = [s] G would verify every
+        // message.
+        let (jubjub, poseidon) = (EdwardsCurve::<Scalar>::jubjub(), PoseidonParams::<Scalar>::new());
+        let message = poseidon.hash(&[Scalar::from(1), Scalar::from(2)]);
+        let s = Scalar::from(5);
+        let weak = (jubjub.mul(&jubjub.generator(), &s), s);
+        let identity = jubjub.identity();
+        assert!(!verify(&jubjub, &poseidon, &identity, message, &weak));
+        let forged = SignatureCircuit {
+            public_key: Value::known(identity),
+            message: Value::known(message),
+            r: Value::known(weak.0),
+            s: Value::known(weak.1),
+        };
+        let prover = MockProver::run(13, &forged, vec![vec![identity.x, identity.y, message]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,1055 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+#[cfg(feature = "evm")]
+use std::process::{Command, Stdio};
+
+use halo2_proofs::arithmetic::CurveAffine;
+#[cfg(any(test, feature = "evm"))]
+use halo2_proofs::circuit::Value;
+use halo2_proofs::halo2curves::bn256::{Bn256, Fq, Fr, G1Affine, G2Affine};
+use halo2_proofs::halo2curves::ff::{Field, FromUniformBytes, PrimeField};
+use halo2_proofs::halo2curves::group::prime::PrimeCurveAffine;
+use halo2_proofs::plonk::{create_proof, Any, Circuit, Expression, ProvingKey, VerifyingKey};
+#[cfg(feature = "evm")]
+use halo2_proofs::plonk::{keygen_pk, keygen_vk};
+use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::ProverGWC;
+use halo2_proofs::poly::Rotation;
+use halo2_proofs::transcript::{
+    EncodedChallenge, Transcript, TranscriptRead, TranscriptReadBuffer, TranscriptWrite, TranscriptWriterBuffer,
+};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+#[cfg(feature = "evm")]
+use revm::primitives::{Address, CreateScheme, ExecutionResult, Output, TransactTo, TxEnv};
+#[cfg(feature = "evm")]
+use revm::{InMemoryDB, EVM};
+use sha3::{Digest, Keccak256};
+
+#[cfg(feature = "evm")]
+use crate::layout::Layout;
+#[cfg(any(test, feature = "evm"))]
+use crate::merkle::{merkle_root, MerkleCircuit};
+#[cfg(any(test, feature = "evm"))]
+use crate::poseidon::PoseidonParams;
+use crate::prepared::PreparedVerifyingKey;
+#[cfg(feature = "evm")]
+use crate::MyCircuit;
+
+/// The BN254 scalar field modulus.
+const R: &str = "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+/// The BN254 base field modulus.
+const P: &str = "21888242871839275222246405745257275088696311157297823662689037894645226208583";
+
+/// A challenge of `KeccakTranscript`: the hash, as a big-endian integer, reduced
+/// modulo the scalar field.
+#[derive(Clone, Copy, Debug)]
+pub struct KeccakChallenge(Fr);
+
+impl EncodedChallenge<G1Affine> for KeccakChallenge {
+    type Input = [u8; 32];
+
+    fn new(challenge_input: &[u8; 32]) -> Self {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(challenge_input);
+        bytes[..32].reverse();
+        KeccakChallenge(Fr::from_uniform_bytes(&bytes))
+    }
+
+    fn get_scalar(&self) -> Fr {
+        self.0
+    }
+}
+
+/// A transcript that is cheap to replay on an EVM chain. Scalars are 32 byte
+/// big-endian integers and points their two big-endian coordinates, with the
+/// identity as `(0, 0)`, which is also what the BN254 precompiles take. The
+/// proof uses this same encoding, so a Solidity verifier absorbs the bytes of
+/// the proof as they are. A squeeze hashes the previous hash together with
+/// everything absorbed since, with Keccak256.
+pub struct KeccakTranscript<S> {
+    stream: S,
+    state: [u8; 32],
+    buffer: Vec<u8>,
+}
+
+impl<S> KeccakTranscript<S> {
+    pub fn new(stream: S) -> Self {
+        KeccakTranscript {
+            stream,
+            state: [0u8; 32],
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// The big-endian encoding of a field element.
+fn be_bytes<F: PrimeField<Repr = [u8; 32]>>(f: &F) -> [u8; 32] {
+    let mut bytes = f.to_repr();
+    bytes.reverse();
+    bytes
+}
+
+fn from_be_bytes<F: PrimeField<Repr = [u8; 32]>>(bytes: &[u8]) -> Option<F> {
+    let mut repr = [0u8; 32];
+    repr.copy_from_slice(bytes);
+    repr.reverse();
+    Option::from(F::from_repr(repr))
+}
+
+fn point_encoding(point: &G1Affine) -> [u8; 64] {
+    let mut bytes = [0u8; 64];
+    let coordinates = point.coordinates();
+    if bool::from(coordinates.is_some()) {
+        let coordinates = coordinates.unwrap();
+        bytes[..32].copy_from_slice(&be_bytes(coordinates.x()));
+        bytes[32..].copy_from_slice(&be_bytes(coordinates.y()));
+    }
+    bytes
+}
+
+fn point_decoding(bytes: &[u8; 64]) -> Option<G1Affine> {
+    if bytes.iter().all(|byte| *byte == 0) {
+        return Some(G1Affine::identity());
+    }
+    let x = from_be_bytes::<Fq>(&bytes[..32])?;
+    let y = from_be_bytes::<Fq>(&bytes[32..])?;
+    Option::from(G1Affine::from_xy(x, y))
+}
+
+impl<S> Transcript<G1Affine, KeccakChallenge> for KeccakTranscript<S> {
+    fn squeeze_challenge(&mut self) -> KeccakChallenge {
+        let mut hasher = Keccak256::new();
+        hasher.update(self.state);
+        hasher.update(&self.buffer);
+        self.state = hasher.finalize().into();
+        self.buffer.clear();
+        KeccakChallenge::new(&self.state)
+    }
+
+    fn common_point(&mut self, point: G1Affine) -> io::Result<()> {
+        self.buffer.extend(point_encoding(&point));
+        Ok(())
+    }
+
+    fn common_scalar(&mut self, scalar: Fr) -> io::Result<()> {
+        self.buffer.extend(be_bytes(&scalar));
+        Ok(())
+    }
+}
+
+impl<R: Read> TranscriptRead<G1Affine, KeccakChallenge> for KeccakTranscript<R> {
+    fn read_point(&mut self) -> io::Result<G1Affine> {
+        let mut bytes = [0u8; 64];
+        self.stream.read_exact(&mut bytes)?;
+        let point = point_decoding(&bytes)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof"))?;
+        self.common_point(point)?;
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<Fr> {
+        let mut bytes = [0u8; 32];
+        self.stream.read_exact(&mut bytes)?;
+        let scalar = from_be_bytes::<Fr>(&bytes)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "invalid field element encoding in proof"))?;
+        self.common_scalar(scalar)?;
+        Ok(scalar)
+    }
+}
+
+impl<R: Read> TranscriptReadBuffer<R, G1Affine, KeccakChallenge> for KeccakTranscript<R> {
+    fn init(reader: R) -> Self {
+        Self::new(reader)
+    }
+}
+
+impl<W: Write> TranscriptWrite<G1Affine, KeccakChallenge> for KeccakTranscript<W> {
+    fn write_point(&mut self, point: G1Affine) -> io::Result<()> {
+        self.common_point(point)?;
+        self.stream.write_all(&point_encoding(&point))
+    }
+
+    fn write_scalar(&mut self, scalar: Fr) -> io::Result<()> {
+        self.common_scalar(scalar)?;
+        self.stream.write_all(&be_bytes(&scalar))
+    }
+}
+
+impl<W: Write> TranscriptWriterBuffer<W, G1Affine, KeccakChallenge> for KeccakTranscript<W> {
+    fn init(writer: W) -> Self {
+        Self::new(writer)
+    }
+
+    fn finalize(self) -> W {
+        self.stream
+    }
+}
+
+fn hex<F: PrimeField<Repr = [u8; 32]>>(f: &F) -> String {
+    let mut hex = String::from("0x");
+    for byte in be_bytes(f) {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    hex
+}
+
+/// The coordinates of a G2 point in the order of the pairing precompile, which
+/// takes the imaginary part of every coordinate first.
+fn g2_words(point: &G2Affine) -> [String; 4] {
+    let coordinates = point.coordinates().unwrap();
+    [
+        hex(&coordinates.x().c1),
+        hex(&coordinates.x().c0),
+        hex(&coordinates.y().c1),
+        hex(&coordinates.y().c0),
+    ]
+}
+
+/// The body of `verifyProof`, built up one step of the explicit verifier at a
+/// time. Every scalar and point lives in the memory array `m`, a point taking
+/// two words, so the generated code never runs out of stack slots.
+struct SolidityVerifier {
+    lines: Vec<String>,
+    slots: usize,
+    /// The position in the proof of the next read.
+    offset: usize,
+    /// Where the bytes absorbed since the last squeeze start in the proof.
+    absorbed: usize,
+    /// The verifying key and the instance values, which are absorbed before
+    /// the first squeeze.
+    preamble: Option<String>,
+}
+
+impl SolidityVerifier {
+    fn emit(&mut self, line: impl Into<String>) {
+        self.lines.push(format!("        {}", line.into()));
+    }
+
+    fn comment(&mut self, comment: &str) {
+        self.lines.push(String::new());
+        self.emit(format!("// {}", comment));
+    }
+
+    fn alloc(&mut self, words: usize) -> usize {
+        self.slots += words;
+        self.slots - words
+    }
+
+    fn scalar(&mut self, value: &str) -> usize {
+        let slot = self.alloc(1);
+        self.emit(format!("m[{}] = {};", slot, value));
+        slot
+    }
+
+    fn constant_point(&mut self, point: &G1Affine) -> usize {
+        let slot = self.alloc(2);
+        let bytes = point_encoding(point);
+        let x = from_be_bytes::<Fq>(&bytes[..32]).unwrap();
+        let y = from_be_bytes::<Fq>(&bytes[32..]).unwrap();
+        self.emit(format!("m[{}] = {};", slot, hex(&x)));
+        self.emit(format!("m[{}] = {};", slot + 1, hex(&y)));
+        slot
+    }
+
+    fn read_point(&mut self) -> usize {
+        let slot = self.alloc(2);
+        self.emit(format!("m[{}] = word(proof, {});", slot, self.offset));
+        self.emit(format!("m[{}] = word(proof, {});", slot + 1, self.offset + 32));
+        self.offset += 64;
+        slot
+    }
+
+    fn read_points(&mut self, n: usize) -> Vec<usize> {
+        (0..n).map(|_| self.read_point()).collect()
+    }
+
+    fn read_scalar(&mut self) -> usize {
+        let slot = self.alloc(1);
+        self.emit(format!("m[{}] = word(proof, {});", slot, self.offset));
+        self.emit(format!("require(m[{}] < R, \"invalid scalar\");", slot));
+        self.offset += 32;
+        slot
+    }
+
+    fn read_scalars(&mut self, n: usize) -> Vec<usize> {
+        (0..n).map(|_| self.read_scalar()).collect()
+    }
+
+    fn squeeze(&mut self) -> usize {
+        let preamble = self.preamble.take().unwrap_or_default();
+        self.emit(format!(
+            "state = keccak256(abi.encodePacked(state, {}proof[{}:{}]));",
+            preamble, self.absorbed, self.offset
+        ));
+        self.absorbed = self.offset;
+        self.scalar("uint256(state) % R")
+    }
+
+    /// `dst = dst + p * s`, with `s` a Solidity expression.
+    fn mul_add(&mut self, dst: usize, p: usize, s: &str) {
+        let tmp = self.alloc(2);
+        self.emit(format!("ecMul(m, {}, {}, {});", tmp, p, s));
+        self.emit(format!("ecAdd(m, {}, {}, {});", dst, dst, tmp));
+    }
+}
+
+fn add(a: &str, b: &str) -> String {
+    format!("addmod({}, {}, R)", a, b)
+}
+
+fn sub(a: &str, b: &str) -> String {
+    format!("addmod({}, R - {}, R)", a, b)
+}
+
+fn mul(a: &str, b: &str) -> String {
+    format!("mulmod({}, {}, R)", a, b)
+}
+
+fn slot(slot: usize) -> String {
+    format!("m[{}]", slot)
+}
+
+/// Generates a Solidity verifier for proofs of the circuit of `vk` with a
+/// single instance, created with `KeccakTranscript` and the GWC multiopen
+/// argument. It takes the same steps as `explicit_verify`, with the layout of
+/// the circuit fixed at generation time, and uses the BN254 precompiles for the
+/// curve operations, the inversions and the final pairing check. Like
+/// `explicit_verify`, it does not support lookups.
+///
+/// The values of the instance columns are the second argument of
+/// `verifyProof`, column after column, where column `i` has `instance_lens[i]`
+/// values. Like the proof length, these are fixed at generation time. The
+/// contract absorbs them after the key and evaluates the columns at x itself.
+pub fn generate_solidity(params: &ParamsKZG<Bn256>, vk: &VerifyingKey<G1Affine>, instance_lens: &[usize]) -> String {
+    let cs = &vk.cs;
+    assert_eq!(instance_lens.len(), cs.num_instance_columns(), "one length per instance column");
+    assert!(cs.lookups().is_empty(), "lookups are not supported");
+    let pvk = PreparedVerifyingKey::new(vk);
+    let num_instances = instance_lens.iter().sum::<usize>();
+
+    let mut verifier = SolidityVerifier {
+        lines: Vec::new(),
+        slots: 0,
+        offset: 0,
+        absorbed: 0,
+        preamble: Some(format!(
+            "uint256({}), {}",
+            hex(&vk.transcript_repr()),
+            if num_instances > 0 { "instances, " } else { "" }
+        )),
+    };
+
+    verifier.comment("The commitments of the fixed and permutation columns");
+    let fixed_commitments = vk
+        .fixed_commitments()
+        .iter()
+        .map(|commitment| verifier.constant_point(commitment))
+        .collect::<Vec<_>>();
+    let permutation_commitments = vk
+        .permutation()
+        .commitments()
+        .iter()
+        .map(|commitment| verifier.constant_point(commitment))
+        .collect::<Vec<_>>();
+
+    verifier.comment("The advice commitments, and the challenges after every phase");
+    let advice_column_phase = cs.advice_column_phase();
+    let challenge_phase = cs.challenge_phase();
+    let mut advice_commitments = vec![0; cs.num_advice_columns()];
+    let mut challenges = vec![0; cs.num_challenges()];
+    let num_phases = advice_column_phase.iter().copied().max().map_or(1, |phase| phase + 1);
+    for current_phase in 0..num_phases {
+        for (phase, commitment) in advice_column_phase.iter().zip(advice_commitments.iter_mut()) {
+            if *phase == current_phase {
+                *commitment = verifier.read_point();
+            }
+        }
+        for (phase, challenge) in challenge_phase.iter().zip(challenges.iter_mut()) {
+            if *phase == current_phase {
+                *challenge = verifier.squeeze();
+            }
+        }
+    }
+
+    verifier.comment("theta, beta and gamma");
+    let theta = slot(verifier.squeeze());
+    let beta = slot(verifier.squeeze());
+    let gamma = slot(verifier.squeeze());
+
+    verifier.comment("The permutation and shuffle products");
+    let permutation_columns = cs.permutation().get_columns();
+    let chunk_len = cs.degree() - 2;
+    let num_sets = (permutation_columns.len() + chunk_len - 1) / chunk_len;
+    let permutation_products = verifier.read_points(num_sets);
+    let shuffle_products = verifier.read_points(cs.shuffles().len());
+
+    verifier.comment("The vanishing argument, and y and x");
+    let vanishing_rand = verifier.read_point();
+    let y = slot(verifier.squeeze());
+    let vanishing_split = verifier.read_points(vk.get_domain().get_quotient_poly_degree());
+    let x = slot(verifier.squeeze());
+
+    verifier.comment("The evaluations");
+    let advice_evals = verifier.read_scalars(cs.advice_queries().len());
+    let fixed_evals = verifier.read_scalars(cs.fixed_queries().len());
+    let random_eval = verifier.read_scalar();
+    let permutation_common_evals = verifier.read_scalars(permutation_commitments.len());
+    let permutation_evals = (0..num_sets)
+        .map(|set_index| {
+            let eval = verifier.read_scalar();
+            let next_eval = verifier.read_scalar();
+            let last_eval = (set_index + 1 < num_sets).then(|| verifier.read_scalar());
+            (eval, next_eval, last_eval)
+        })
+        .collect::<Vec<_>>();
+    let shuffle_evals = (0..cs.shuffles().len())
+        .map(|_| (verifier.read_scalar(), verifier.read_scalar()))
+        .collect::<Vec<_>>();
+
+    verifier.comment("x^n, and the Lagrange polynomials at x");
+    let xn = verifier.scalar(&x);
+    verifier.emit(format!("for (uint256 i = 0; i < {}; i++) m[{}] = mulmod(m[{}], m[{}], R);", params.k(), xn, xn, xn));
+    let xn = slot(xn);
+    let common = slot(verifier.scalar(&sub(&xn, "1")));
+    let blinding_factors = cs.blinding_factors() as i32;
+    let l_evals = pvk
+        .lagrange
+        .iter()
+        .map(|(omega, constant)| {
+            let denominator = format!("inv({})", sub(&x, &hex(omega)));
+            slot(verifier.scalar(&mul(&mul(&common, &hex(constant)), &denominator)))
+        })
+        .collect::<Vec<_>>();
+    let l_last = l_evals[0].clone();
+    let l_blind = slot(verifier.scalar("0"));
+    for l in &l_evals[1..l_evals.len() - 1] {
+        verifier.emit(format!("{} = {};", l_blind, add(&l_blind, l)));
+    }
+    let l_0 = l_evals[l_evals.len() - 1].clone();
+    let active_rows = slot(verifier.scalar(&sub("1", &add(&l_last, &l_blind))));
+
+    // The column at rotation r is sum_j value_j l_{j - r}(x), with every
+    // Lagrange polynomial computed once.
+    if !cs.instance_queries().is_empty() {
+        verifier.comment("The instance columns at x, from their values");
+    }
+    let n_inv = Fr::from(params.n()).invert().unwrap();
+    let first_values = instance_lens
+        .iter()
+        .scan(0, |first, len| {
+            *first += len;
+            Some(*first - len)
+        })
+        .collect::<Vec<_>>();
+    let mut instance_l_evals = BTreeMap::new();
+    let mut instance_evals = Vec::new();
+    for (column, at) in cs.instance_queries() {
+        let eval = slot(verifier.scalar("0"));
+        for j in 0..instance_lens[column.index()] {
+            let row = j as i32 - at.0;
+            let l = instance_l_evals
+                .entry(row)
+                .or_insert_with(|| {
+                    let omega = vk.get_domain().rotate_omega(Fr::ONE, Rotation(row));
+                    let denominator = format!("inv({})", sub(&x, &hex(&omega)));
+                    slot(verifier.scalar(&mul(&mul(&common, &hex(&(omega * n_inv))), &denominator)))
+                })
+                .clone();
+            let value = format!("instances[{}]", first_values[column.index()] + j);
+            verifier.emit(format!("{} = {};", eval, add(&eval, &mul(&value, &l))));
+        }
+        instance_evals.push(eval);
+    }
+
+    verifier.comment("The expected evaluation of h at x");
+    let advice_query_index = |column_index: usize, at: Rotation| {
+        cs.advice_queries()
+            .iter()
+            .position(|(column, rotation)| column.index() == column_index && *rotation == at)
+            .expect("advice query not found")
+    };
+    let fixed_query_index = |column_index: usize, at: Rotation| {
+        cs.fixed_queries()
+            .iter()
+            .position(|(column, rotation)| column.index() == column_index && *rotation == at)
+            .expect("fixed query not found")
+    };
+    let instance_query_index = |column_index: usize, at: Rotation| {
+        cs.instance_queries()
+            .iter()
+            .position(|(column, rotation)| column.index() == column_index && *rotation == at)
+            .expect("instance query not found")
+    };
+    let evaluate = |poly: &Expression<Fr>| {
+        poly.evaluate(
+            &|constant| hex(&constant),
+            &|_| panic!("selectors are compressed into fixed columns"),
+            &|query| slot(fixed_evals[fixed_query_index(query.column_index(), query.rotation())]),
+            &|query| slot(advice_evals[advice_query_index(query.column_index(), query.rotation())]),
+            &|query| instance_evals[instance_query_index(query.column_index(), query.rotation())].clone(),
+            &|challenge| slot(challenges[challenge.index()]),
+            &|a| sub("0", &a),
+            &|a, b| add(&a, &b),
+            &|a, b| mul(&a, &b),
+            &|a, scalar| mul(&a, &hex(&scalar)),
+        )
+    };
+    let h = verifier.alloc(1);
+    let h = slot(h);
+    verifier.emit(format!("{} = 0;", h));
+    let fold = |verifier: &mut SolidityVerifier, expression: String| {
+        verifier.emit(format!("{} = {};", h, add(&mul(&h, &y), &expression)));
+    };
+
+    for poly in cs.gates().iter().flat_map(|gate| gate.polynomials().iter()) {
+        fold(&mut verifier, evaluate(poly));
+    }
+
+    let cols = permutation_columns
+        .iter()
+        .map(|column| match column.column_type() {
+            Any::Advice(_) => slot(advice_evals[advice_query_index(column.index(), Rotation::cur())]),
+            Any::Fixed => slot(fixed_evals[fixed_query_index(column.index(), Rotation::cur())]),
+            Any::Instance => instance_evals[instance_query_index(column.index(), Rotation::cur())].clone(),
+        })
+        .collect::<Vec<_>>();
+    let first = slot(permutation_evals[0].0);
+    fold(&mut verifier, mul(&l_0, &sub("1", &first)));
+    let last = slot(permutation_evals[num_sets - 1].0);
+    fold(&mut verifier, mul(&l_last, &sub(&mul(&last, &last), &last)));
+    for (set, previous) in permutation_evals.iter().skip(1).zip(permutation_evals.iter()) {
+        fold(&mut verifier, mul(&sub(&slot(set.0), &slot(previous.2.unwrap())), &l_0));
+    }
+    let beta_x = slot(verifier.scalar(&mul(&beta, &x)));
+    for (chunk_index, ((set, cols), col_evals)) in permutation_evals
+        .iter()
+        .zip(cols.chunks(chunk_len))
+        .zip(permutation_common_evals.chunks(chunk_len))
+        .enumerate()
+    {
+        let mut left = slot(set.1);
+        for (col, col_eval) in cols.iter().zip(col_evals.iter()) {
+            left = mul(&left, &add(&add(col, &mul(&beta, &slot(*col_eval))), &gamma));
+        }
+        let left = slot(verifier.scalar(&left));
+
+        let mut right = slot(set.0);
+        for (col, delta) in cols.iter().zip(pvk.deltas[chunk_index * chunk_len..].iter()) {
+            right = mul(&right, &add(&add(col, &mul(&beta_x, &hex(delta))), &gamma));
+        }
+        let right = slot(verifier.scalar(&right));
+
+        fold(&mut verifier, mul(&sub(&left, &right), &active_rows));
+    }
+
+    for (argument, (eval, next_eval)) in cs.shuffles().iter().zip(shuffle_evals.iter()) {
+        let compress = |expressions: &[Expression<Fr>]| {
+            expressions
+                .iter()
+                .fold("0".to_string(), |acc, expression| add(&mul(&acc, &theta), &evaluate(expression)))
+        };
+        let input_eval = slot(verifier.scalar(&compress(&argument.input_expressions()[..])));
+        let shuffle_eval = slot(verifier.scalar(&compress(&argument.shuffle_expressions()[..])));
+        let (eval, next_eval) = (slot(*eval), slot(*next_eval));
+        fold(&mut verifier, mul(&l_0, &sub("1", &eval)));
+        fold(&mut verifier, mul(&l_last, &sub(&mul(&eval, &eval), &eval)));
+        let left = mul(&next_eval, &add(&shuffle_eval, &gamma));
+        let right = mul(&eval, &add(&input_eval, &gamma));
+        fold(&mut verifier, mul(&sub(&left, &right), &active_rows));
+    }
+    let h_eval = verifier.scalar(&mul(&h, &format!("inv({})", sub(&xn, "1"))));
+
+    verifier.comment("The commitment of h");
+    let h_commitment = verifier.alloc(2);
+    verifier.emit(format!("m[{}] = 0;", h_commitment));
+    verifier.emit(format!("m[{}] = 0;", h_commitment + 1));
+    for piece in vanishing_split.iter().rev() {
+        verifier.emit(format!("ecMul(m, {}, {}, {});", h_commitment, h_commitment, xn));
+        verifier.emit(format!("ecAdd(m, {}, {}, {});", h_commitment, h_commitment, piece));
+    }
+
+    // The queries in the order of `explicit_verify`, as (rotation, commitment, eval).
+    let x_last = Rotation(-(blinding_factors + 1));
+    let mut queries = Vec::new();
+    for (query_index, &(column, at)) in cs.advice_queries().iter().enumerate() {
+        queries.push((at, advice_commitments[column.index()], advice_evals[query_index]));
+    }
+    for (product, eval) in permutation_products.iter().zip(permutation_evals.iter()) {
+        queries.push((Rotation::cur(), *product, eval.0));
+        queries.push((Rotation::next(), *product, eval.1));
+    }
+    for (product, eval) in permutation_products.iter().zip(permutation_evals.iter()).rev().skip(1) {
+        queries.push((x_last, *product, eval.2.unwrap()));
+    }
+    for (product, eval) in shuffle_products.iter().zip(shuffle_evals.iter()) {
+        queries.push((Rotation::cur(), *product, eval.0));
+        queries.push((Rotation::next(), *product, eval.1));
+    }
+    for (query_index, &(column, at)) in cs.fixed_queries().iter().enumerate() {
+        queries.push((at, fixed_commitments[column.index()], fixed_evals[query_index]));
+    }
+    for (commitment, eval) in permutation_commitments.iter().zip(permutation_common_evals.iter()) {
+        queries.push((Rotation::cur(), *commitment, *eval));
+    }
+    queries.push((Rotation::cur(), h_commitment, h_eval));
+    queries.push((Rotation::cur(), vanishing_rand, random_eval));
+
+    // Grouped by point, in the order the points first appear.
+    let mut points: Vec<(Rotation, Vec<(usize, usize)>)> = Vec::new();
+    for (at, commitment, eval) in queries {
+        match points.iter_mut().find(|(rotation, _)| *rotation == at) {
+            Some((_, queries)) => queries.push((commitment, eval)),
+            None => points.push((at, vec![(commitment, eval)])),
+        }
+    }
+
+    verifier.comment("The multiopen argument");
+    let v = slot(verifier.squeeze());
+    let w = verifier.read_points(points.len());
+    let u = slot(verifier.squeeze());
+
+    let left = verifier.alloc(2);
+    let right = verifier.alloc(2);
+    for word in [left, left + 1, right, right + 1] {
+        verifier.emit(format!("m[{}] = 0;", word));
+    }
+    let eval_multi = slot(verifier.scalar("0"));
+    let power_of_u = slot(verifier.scalar("1"));
+    let power_of_v = slot(verifier.alloc(1));
+    for ((rotation, queries), w) in points.iter().zip(w.iter()) {
+        let z = mul(&x, &hex(&pvk.omega_power(*rotation)));
+        verifier.emit(format!("{} = 1;", power_of_v));
+        for (commitment, eval) in queries {
+            let power = mul(&power_of_u, &power_of_v);
+            verifier.mul_add(right, *commitment, &power);
+            verifier.emit(format!("{} = {};", eval_multi, add(&eval_multi, &mul(&power, &slot(*eval)))));
+            verifier.emit(format!("{} = {};", power_of_v, mul(&power_of_v, &v)));
+        }
+        verifier.mul_add(left, *w, &power_of_u);
+        verifier.mul_add(right, *w, &mul(&power_of_u, &z));
+        verifier.emit(format!("{} = {};", power_of_u, mul(&power_of_u, &u)));
+    }
+    let g0 = verifier.constant_point(&params.g[0]);
+    verifier.mul_add(right, g0, &sub("0", &eval_multi));
+
+    verifier.comment("e(left, [s]_2) = e(right, [1]_2)");
+    verifier.emit(format!("return pairing(m, {}, {});", left, right));
+
+    let [g2_x1, g2_x0, g2_y1, g2_y0] = g2_words(&params.g2());
+    let [s_g2_x1, s_g2_x0, s_g2_y1, s_g2_y0] = g2_words(&params.s_g2());
+
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/// Generated by halo2-test from a verifying key. Verifies proofs created with
+/// `KeccakTranscript` and the GWC multiopen argument over BN254.
+contract Halo2Verifier {{
+    uint256 constant R = {R};
+    uint256 constant P = {P};
+
+    function verifyProof(bytes calldata proof, uint256[] calldata instances) external view returns (bool) {{
+        require(proof.length == {proof_len}, "invalid proof length");
+        require(instances.length == {num_instances}, "invalid number of instances");
+        for (uint256 i = 0; i < instances.length; i++) {{
+            require(instances[i] < R, "invalid instance");
+        }}
+        uint256[] memory m = new uint256[]({slots});
+        bytes32 state;
+{body}
+    }}
+
+    function word(bytes calldata proof, uint256 offset) internal pure returns (uint256 w) {{
+        assembly {{
+            w := calldataload(add(proof.offset, offset))
+        }}
+    }}
+
+    function inv(uint256 a) internal view returns (uint256 r) {{
+        require(a != 0, "inverse of zero");
+        uint256[6] memory input = [uint256(32), 32, 32, a, R - 2, R];
+        uint256[1] memory output;
+        bool ok;
+        assembly {{
+            ok := staticcall(gas(), 5, input, 192, output, 32)
+        }}
+        require(ok, "modexp failed");
+        r = output[0];
+    }}
+
+    function ecAdd(uint256[] memory m, uint256 dst, uint256 p, uint256 q) internal view {{
+        uint256[4] memory input = [m[p], m[p + 1], m[q], m[q + 1]];
+        uint256[2] memory output;
+        bool ok;
+        assembly {{
+            ok := staticcall(gas(), 6, input, 128, output, 64)
+        }}
+        require(ok, "ecAdd failed");
+        m[dst] = output[0];
+        m[dst + 1] = output[1];
+    }}
+
+    function ecMul(uint256[] memory m, uint256 dst, uint256 p, uint256 s) internal view {{
+        uint256[3] memory input = [m[p], m[p + 1], s];
+        uint256[2] memory output;
+        bool ok;
+        assembly {{
+            ok := staticcall(gas(), 7, input, 96, output, 64)
+        }}
+        require(ok, "ecMul failed");
+        m[dst] = output[0];
+        m[dst + 1] = output[1];
+    }}
+
+    function pairing(uint256[] memory m, uint256 left, uint256 right) internal view returns (bool) {{
+        uint256[12] memory input = [
+            m[left], m[left + 1],
+            {s_g2_x1}, {s_g2_x0}, {s_g2_y1}, {s_g2_y0},
+            m[right], (P - m[right + 1]) % P,
+            {g2_x1}, {g2_x0}, {g2_y1}, {g2_y0}
+        ];
+        uint256[1] memory output;
+        bool ok;
+        assembly {{
+            ok := staticcall(gas(), 8, input, 384, output, 32)
+        }}
+        return ok && output[0] == 1;
+    }}
+}}
+"#,
+        proof_len = verifier.offset,
+        num_instances = num_instances,
+        slots = verifier.slots,
+        body = verifier.lines.join("\n"),
+    )
+}
+
+/// Whether `solc` is on the `PATH`, which `compile_solidity` needs.
+#[cfg(feature = "evm")]
+pub fn solc_available() -> bool {
+    Command::new("solc")
+        .arg("--version")
+        .output()
+        .map_or(false, |output| output.status.success())
+}
+
+/// Compiles `source` with the `solc` on the `PATH`, returning the creation
+/// bytecode of its contract.
+#[cfg(feature = "evm")]
+pub fn compile_solidity(source: &str) -> Vec<u8> {
+    let mut solc = Command::new("solc")
+        .args(["--bin", "--optimize", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("solc should be on the PATH");
+    solc.stdin.take().unwrap().write_all(source.as_bytes()).unwrap();
+    let output = solc.wait_with_output().unwrap();
+    assert!(output.status.success(), "solc failed");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let binary = stdout
+        .split("Binary:")
+        .last()
+        .and_then(|binary| binary.split_whitespace().next())
+        .expect("no bytecode in the output of solc");
+    (0..binary.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&binary[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// The calldata of `verifyProof(proof, instances)`.
+#[cfg(feature = "evm")]
+pub fn encode_calldata(proof: &[u8], instances: &[Fr]) -> Vec<u8> {
+    let mut calldata = Keccak256::digest(b"verifyProof(bytes,uint256[])")[..4].to_vec();
+    let word = |value: usize| {
+        let mut bytes = [0u8; 32];
+        bytes[24..].copy_from_slice(&(value as u64).to_be_bytes());
+        bytes
+    };
+    let padded_len = proof.len() + (32 - proof.len() % 32) % 32;
+    // The offsets of both arguments, then the length and bytes of the proof,
+    // padded to words, and the length and values of the instances.
+    calldata.extend(word(64));
+    calldata.extend(word(64 + 32 + padded_len));
+    calldata.extend(word(proof.len()));
+    calldata.extend(proof);
+    calldata.resize(4 + 64 + 32 + padded_len, 0);
+    calldata.extend(word(instances.len()));
+    for instance in instances {
+        calldata.extend(be_bytes(instance));
+    }
+    calldata
+}
+
+/// An EVM in memory, to deploy a generated verifier and call it.
+#[cfg(feature = "evm")]
+pub struct Evm {
+    evm: EVM<InMemoryDB>,
+}
+
+#[cfg(feature = "evm")]
+impl Evm {
+    pub fn new() -> Self {
+        let mut evm = EVM::new();
+        evm.database(InMemoryDB::default());
+        Evm { evm }
+    }
+
+    /// Deploys `bytecode`, returning the address of the contract.
+    pub fn create(&mut self, bytecode: Vec<u8>) -> Address {
+        let result = self.transact(TxEnv {
+            gas_limit: u64::MAX,
+            transact_to: TransactTo::Create(CreateScheme::Create),
+            data: bytecode.into(),
+            ..Default::default()
+        });
+        match result {
+            ExecutionResult::Success {
+                output: Output::Create(_, Some(address)),
+                ..
+            } => address,
+            result => panic!("deployment failed: {:?}", result),
+        }
+    }
+
+    /// Calls the contract at `address`, returning the gas used and the output,
+    /// or `None` if the call reverted.
+    pub fn call(&mut self, address: Address, calldata: Vec<u8>) -> (u64, Option<Vec<u8>>) {
+        let result = self.transact(TxEnv {
+            gas_limit: u64::MAX,
+            transact_to: TransactTo::Call(address),
+            data: calldata.into(),
+            ..Default::default()
+        });
+        match result {
+            ExecutionResult::Success {
+                gas_used,
+                output: Output::Call(output),
+                ..
+            } => (gas_used, Some(output.to_vec())),
+            ExecutionResult::Revert { gas_used, .. } | ExecutionResult::Halt { gas_used, .. } => (gas_used, None),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    fn transact(&mut self, tx: TxEnv) -> ExecutionResult {
+        self.evm.env.tx = tx;
+        let result = self.evm.transact_commit().expect("transaction should execute");
+        self.evm.env.tx = Default::default();
+        result
+    }
+}
+
+/// Deploys the verifier generated for `vk` and runs it on `proof`, with
+/// `instance` the values of the instance columns, returning whether the proof
+/// was accepted and the gas used.
+#[cfg(feature = "evm")]
+pub fn verify_on_evm(
+    params: &ParamsKZG<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    proof: &[u8],
+    instance: &[&[Fr]],
+) -> (bool, u64) {
+    let instance_lens = instance.iter().map(|column| column.len()).collect::<Vec<_>>();
+    let bytecode = compile_solidity(&generate_solidity(params, vk, &instance_lens));
+    let mut evm = Evm::new();
+    let address = evm.create(bytecode);
+    let (gas_used, output) = evm.call(address, encode_calldata(proof, &instance.concat()));
+    let accepted = output.map_or(false, |output| output.last() == Some(&1));
+    (accepted, gas_used)
+}
+
+/// Proves `circuit` with `KeccakTranscript`, as the generated verifier expects.
+/// `instance` has the values of the instance columns.
+pub fn prove<C: Circuit<Fr>>(
+    params: &ParamsKZG<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    instance: &[&[Fr]],
+) -> Vec<u8> {
+    let mut transcript = KeccakTranscript::new(vec![]);
+    create_proof::<KZGCommitmentScheme<Bn256>, ProverGWC<Bn256>, _, _, _, _>(
+        params,
+        pk,
+        &[circuit],
+        &[instance],
+        StdRng::from_seed([0u8; 32]),
+        &mut transcript,
+    )
+    .expect("Proof generation failed");
+    transcript.finalize()
+}
+
+/// A `MerkleCircuit` of depth 4 with `leaf` at position 0b0101, and its root,
+/// which is the only instance value.
+#[cfg(any(test, feature = "evm"))]
+fn merkle_circuit(leaf: u64) -> (MerkleCircuit<Fr>, Fr) {
+    let leaf = Fr::from(leaf);
+    let siblings = (0..4).map(|i| Fr::from(100 + i)).collect::<Vec<_>>();
+    let index_bits = [true, false, true, false];
+    let root = merkle_root(&PoseidonParams::new(), leaf, &siblings, &index_bits);
+    let circuit = MerkleCircuit {
+        leaf: Value::known(leaf),
+        siblings: siblings.iter().map(|sibling| Value::known(*sibling)).collect(),
+        index_bits: index_bits
+            .iter()
+            .map(|bit| Value::known(if *bit { Fr::ONE } else { Fr::ZERO }))
+            .collect(),
+    };
+    (circuit, root)
+}
+
+/// Runs the verifier generated for `MyCircuit` in the EVM, on a valid proof and
+/// on the same proof with its first evaluation changed, and the one generated
+/// for `MerkleCircuit` with the root and with another value as instance. Needs
+/// `solc`.
+#[cfg(feature = "evm")]
+pub fn run() {
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, StdRng::from_seed([0u8; 32]));
+    let constant = Fr::from(7);
+    let (a, b) = (Fr::from(2), Fr::from(3));
+    let circuit = MyCircuit {
+        constant,
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(constant * a.square() * b.square()),
+    };
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    let proof = prove(&params, &pk, circuit, &[]);
+
+    let (accepted, gas_used) = verify_on_evm(&params, pk.get_vk(), &proof, &[]);
+    assert!(accepted);
+    println!("Proof of {} bytes verified in the EVM with {} gas", proof.len(), gas_used);
+
+    // The first evaluation follows the commitments, of 64 bytes each.
+    let offset = Layout::new(&pk.get_vk().cs, 1).commitments() * 64;
+    let mut tampered = proof.clone();
+    tampered[offset + 31] ^= 1;
+    let (accepted, _) = verify_on_evm(&params, pk.get_vk(), &tampered, &[]);
+    assert!(!accepted);
+
+    let params: ParamsKZG<Bn256> = ParamsKZG::setup(9, StdRng::from_seed([0u8; 32]));
+    let (circuit, root) = merkle_circuit(42);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    let proof = prove(&params, &pk, circuit, &[&[root]]);
+
+    let (accepted, gas_used) = verify_on_evm(&params, pk.get_vk(), &proof, &[&[root]]);
+    assert!(accepted);
+    println!("Proof with an instance column verified in the EVM with {} gas", gas_used);
+    let (accepted, _) = verify_on_evm(&params, pk.get_vk(), &proof, &[&[Fr::from(42)]]);
+    assert!(!accepted);
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::plonk::{keygen_pk, keygen_vk, verify_proof};
+    use halo2_proofs::poly::kzg::multiopen::VerifierGWC;
+    use halo2_proofs::poly::kzg::strategy::SingleStrategy;
+
+    use super::*;
+    use crate::{explicit_verify_transcript, my_circuit};
+
+    fn setup() -> (ParamsKZG<Bn256>, ProvingKey<G1Affine>) {
+        let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, StdRng::from_seed([5u8; 32]));
+        let vk = keygen_vk(&params, &my_circuit::<Fr>(2, 3)).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&params, vk, &my_circuit::<Fr>(2, 3)).expect("keygen_pk should not fail");
+        (params, pk)
+    }
+
+    fn setup_merkle() -> (ParamsKZG<Bn256>, ProvingKey<G1Affine>) {
+        let params: ParamsKZG<Bn256> = ParamsKZG::setup(9, StdRng::from_seed([5u8; 32]));
+        let (circuit, _) = merkle_circuit(42);
+        let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+        (params, pk)
+    }
+
+    #[test]
+    fn keccak_transcript() {
+        // halo2's verifier as well as ours must accept proofs with the Keccak
+        // transcript, also with the root of a Merkle tree as instance.
+        let (params, pk) = setup();
+        let proof = prove(&params, &pk, my_circuit(2, 3), &[]);
+        verify_proof::<_, VerifierGWC<Bn256>, _, _, _>(
+            &params,
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            &[&[]],
+            &mut KeccakTranscript::new(proof.as_slice()),
+        )
+        .expect("Verification failed");
+        let mut transcript = KeccakTranscript::new(proof.as_slice());
+        let (accumulator, _) =
+            explicit_verify_transcript(&params, &PreparedVerifyingKey::new(pk.get_vk()), &mut transcript, &[&[]]);
+        assert!(accumulator.check());
+
+        let (params, pk) = setup_merkle();
+        let (circuit, root) = merkle_circuit(42);
+        let proof = prove(&params, &pk, circuit, &[&[root]]);
+        let mut transcript = KeccakTranscript::new(proof.as_slice());
+        let (accumulator, _) =
+            explicit_verify_transcript(&params, &PreparedVerifyingKey::new(pk.get_vk()), &mut transcript, &[&[&[root]]]);
+        assert!(accumulator.check());
+    }
+
+    /// Checks what can be checked of the contract for `vk` without an EVM: it
+    /// reads every word of `proof` once and in order, squeezes as often as the
+    /// transcript, and pins the key.
+    fn assert_contract(solidity: &str, vk: &VerifyingKey<G1Affine>, proof: &[u8]) {
+        assert!(solidity.contains(&format!("proof.length == {}", proof.len())));
+        let offsets = solidity
+            .split("word(proof, ")
+            .skip(1)
+            .map(|rest| rest[..rest.find(')').unwrap()].parse::<usize>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(offsets, (0..proof.len()).step_by(32).collect::<Vec<_>>());
+        // One squeeze after every phase with challenges, and theta, beta,
+        // gamma, y, x, v and u.
+        assert_eq!(solidity.matches("keccak256(").count(), vk.cs.num_challenges() + 7);
+        assert!(solidity.contains(&format!("uint256({})", hex(&vk.transcript_repr()))));
+        for commitment in vk.fixed_commitments().iter().chain(vk.permutation().commitments()) {
+            let x = from_be_bytes::<Fq>(&point_encoding(commitment)[..32]).unwrap();
+            assert!(solidity.contains(&hex(&x)));
+        }
+    }
+
+    #[test]
+    fn solidity() {
+        let (params, pk) = setup();
+        let proof = prove(&params, &pk, my_circuit(2, 3), &[]);
+        let solidity = generate_solidity(&params, pk.get_vk(), &[]);
+        assert_contract(&solidity, pk.get_vk(), &proof);
+        assert!(solidity.contains("instances.length == 0"));
+        assert!(!solidity.contains("instances["));
+
+        // The root is absorbed right after the key, before the first
+        // commitment, and evaluated at x for the permutation argument, which
+        // copies it into the circuit.
+        let (params, pk) = setup_merkle();
+        let (circuit, root) = merkle_circuit(42);
+        let proof = prove(&params, &pk, circuit, &[&[root]]);
+        let solidity = generate_solidity(&params, pk.get_vk(), &[1]);
+        assert_contract(&solidity, pk.get_vk(), &proof);
+        assert!(solidity.contains("instances.length == 1"));
+        assert!(solidity.contains("instances, proof[0:"));
+        assert!(solidity.contains("instances[0]"));
+    }
+
+    /// The generated contract in an embedded EVM, when `solc` is on the `PATH`.
+    #[cfg(feature = "evm")]
+    #[test]
+    fn evm() {
+        if solc_available() {
+            run();
+        } else {
+            println!("Skipped running the Solidity verifier in the EVM: solc is not on the PATH");
+        }
+    }
+}
@@ -0,0 +1,809 @@
+use std::cmp::Ordering;
+use std::marker::PhantomData;
+
+use halo2_proofs::circuit::{AssignedCell, Chip, Layouter, Region, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Selector};
+use halo2_proofs::poly::Rotation;
+
+use crate::range_check::{RangeCheckChip, RangeCheckConfig, RangeCheckInstructions};
+use crate::Number;
+
+/// Number of bits in a limb of a foreign field element.
+pub const LIMB_BITS: usize = 96;
+
+/// Number of limbs of a foreign field element, enough for the 381 bit base field
+/// of BLS12-381.
+pub const NUM_LIMBS: usize = 4;
+
+/// Number of bits looked up at once when range checking limbs and carries.
+const RANGE_BITS: usize = 8;
+
+/// The carries of a relation are signed and smaller than `2^CARRY_SHIFT` in
+/// absolute value, so we store `carry + 2^CARRY_SHIFT` and range check that to
+/// `CARRY_BITS` bits.
+const CARRY_SHIFT: usize = 100;
+const CARRY_BITS: usize = 104;
+
+/// Number of limb positions of a relation that are checked with carries, i.e. a
+/// relation is checked modulo `2^(CHECKED_LIMBS * LIMB_BITS)`. Together with the
+/// check modulo the native modulus this determines the relation over the
+/// integers, as long as both sides are smaller than the product of the two
+/// moduli (see `ForeignFieldChip::configure`).
+const CHECKED_LIMBS: usize = 6;
+
+// Unsigned integers as little-endian `u64` words. We only need a handful of
+// operations on them to compute quotients and remainders during witness
+// generation, so there is no need for a bignum dependency.
+
+fn big_cmp(a: &[u64], b: &[u64]) -> Ordering {
+    for i in (0..std::cmp::max(a.len(), b.len())).rev() {
+        let (x, y) = (a.get(i).copied().unwrap_or(0), b.get(i).copied().unwrap_or(0));
+        if x != y {
+            return x.cmp(&y);
+        }
+    }
+    Ordering::Equal
+}
+
+fn big_add(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let n = std::cmp::max(a.len(), b.len()) + 1;
+    let mut sum = vec![0u64; n];
+    let mut carry = 0u128;
+    for (i, word) in sum.iter_mut().enumerate() {
+        let s = a.get(i).copied().unwrap_or(0) as u128 + b.get(i).copied().unwrap_or(0) as u128 + carry;
+        *word = s as u64;
+        carry = s >> 64;
+    }
+    sum
+}
+
+/// Returns `a - b`, panicking if the result would be negative.
+fn big_sub(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut difference = vec![0u64; a.len()];
+    let mut borrow = 0i128;
+    for (i, word) in difference.iter_mut().enumerate() {
+        let d = a[i] as i128 - b.get(i).copied().unwrap_or(0) as i128 - borrow;
+        *word = d as u64;
+        borrow = (d < 0) as i128;
+    }
+    assert!(borrow == 0 && b.iter().skip(a.len()).all(|word| *word == 0), "negative difference");
+    difference
+}
+
+fn big_mul(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut product = vec![0u64; a.len() + b.len()];
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, y) in b.iter().enumerate() {
+            let t = *x as u128 * *y as u128 + product[i + j] as u128 + carry;
+            product[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        product[i + b.len()] = carry as u64;
+    }
+    product
+}
+
+fn big_bit(a: &[u64], i: usize) -> u64 {
+    a.get(i / 64).map(|word| (word >> (i % 64)) & 1).unwrap_or(0)
+}
+
+/// Long division, returning `(a / b, a mod b)`.
+fn big_div_rem(a: &[u64], b: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let mut quotient = vec![0u64; a.len()];
+    let mut remainder = vec![0u64; b.len() + 1];
+    for i in (0..64 * a.len()).rev() {
+        remainder = big_add(&remainder, &remainder);
+        remainder.truncate(b.len() + 1);
+        remainder[0] |= big_bit(a, i);
+        if big_cmp(&remainder, b) != Ordering::Less {
+            remainder = big_sub(&remainder, b);
+            quotient[i / 64] |= 1 << (i % 64);
+        }
+    }
+    (quotient, remainder)
+}
+
+/// The canonical integer representative of a field element. This avoids relying
+/// on the byte order of `to_repr`, which differs between fields.
+fn to_big<W: PrimeField>(w: &W) -> Vec<u64> {
+    let mut w = *w;
+    let mut big = vec![0u64; (W::NUM_BITS as usize + 63) / 64];
+    for i in 0..W::NUM_BITS as usize {
+        if bool::from(w.is_odd()) {
+            big[i / 64] |= 1 << (i % 64);
+            w -= W::ONE;
+        }
+        w *= W::TWO_INV;
+    }
+    big
+}
+
+/// Reduces an integer into the field.
+fn from_big<W: PrimeField>(big: &[u64]) -> W {
+    (0..64 * big.len()).rev().fold(W::ZERO, |acc, i| {
+        if big_bit(big, i) == 1 {
+            acc.double() + W::ONE
+        } else {
+            acc.double()
+        }
+    })
+}
+
+/// The first `n` limbs of `LIMB_BITS` bits of an integer, dropping the rest.
+fn big_limbs<F: PrimeField>(big: &[u64], n: usize) -> Vec<F> {
+    (0..n)
+        .map(|limb| {
+            let limb = (0..LIMB_BITS).fold(0u128, |acc, j| acc | ((big_bit(big, limb * LIMB_BITS + j) as u128) << j));
+            F::from_u128(limb)
+        })
+        .collect()
+}
+
+/// The limbs of a foreign field element, e.g. to expose it in an instance column.
+pub fn limbs<F: PrimeField, W: PrimeField>(w: &W) -> Vec<F> {
+    big_limbs(&to_big(w), NUM_LIMBS)
+}
+
+fn modulus<W: PrimeField>() -> Vec<u64> {
+    big_add(&to_big(&-W::ONE), &[1])
+}
+
+fn pow2<F: PrimeField>(exp: usize) -> F {
+    F::from(2).pow_vartime([exp as u64])
+}
+
+/// An element of the foreign field `W`, represented by `NUM_LIMBS` range checked
+/// limbs and their combination modulo the native modulus.
+///
+/// The represented integer is smaller than `2^(NUM_LIMBS * LIMB_BITS)` but not
+/// necessarily reduced modulo the foreign modulus, so the same field element can
+/// have different representations. Use `assert_equal` to compare elements.
+#[derive(Clone)]
+pub struct Element<F: PrimeField> {
+    /// The limbs, least significant first.
+    pub limbs: Vec<Number<F>>,
+    /// `sum limbs[i] * 2^(i * LIMB_BITS)` modulo the native modulus.
+    pub native: Number<F>,
+    pub integer: Value<Vec<u64>>,
+}
+
+impl<F: PrimeField> Element<F> {
+    /// The foreign field element this represents.
+    pub fn value<W: PrimeField>(&self) -> Value<W> {
+        self.integer.as_ref().map(|integer| from_big(integer))
+    }
+}
+
+pub trait ForeignFieldInstructions<F: PrimeField, W: PrimeField>: Chip<F> {
+    /// Variable representing a foreign field element.
+    type Element;
+
+    /// Loads the table used to range check limbs.
+    fn load_table(&self, layouter: impl Layouter<F>) -> Result<(), Error>;
+
+    /// Loads a foreign field element as a private input.
+    fn load_private(&self, layouter: impl Layouter<F>, value: Value<W>) -> Result<Self::Element, Error>;
+
+    /// Loads a foreign field element as a fixed constant.
+    fn load_constant(&self, layouter: impl Layouter<F>, constant: W) -> Result<Self::Element, Error>;
+
+    /// Returns `a + b`. The result is only constrained to be below
+    /// `2^(NUM_LIMBS * LIMB_BITS)`, not to be reduced: use `assert_canonical`
+    /// before its limbs are exposed or compared.
+    fn add(&self, layouter: impl Layouter<F>, a: &Self::Element, b: &Self::Element) -> Result<Self::Element, Error>;
+
+    /// Returns `a - b`, which like `add` is not constrained to be reduced.
+    fn sub(&self, layouter: impl Layouter<F>, a: &Self::Element, b: &Self::Element) -> Result<Self::Element, Error>;
+
+    /// Returns `a * b`, which like `add` is not constrained to be reduced.
+    fn mul(&self, layouter: impl Layouter<F>, a: &Self::Element, b: &Self::Element) -> Result<Self::Element, Error>;
+
+    /// Returns `a^-1`, which also constrains `a` to be nonzero.
+    fn inverse(&self, layouter: impl Layouter<F>, a: &Self::Element) -> Result<Self::Element, Error>;
+
+    /// Constrains `a` and `b` to represent the same foreign field element.
+    fn assert_equal(&self, layouter: impl Layouter<F>, a: &Self::Element, b: &Self::Element) -> Result<(), Error>;
+
+    /// Constrains `a` to be reduced, i.e. smaller than the modulus, so that its
+    /// limbs are the unique representation of the element.
+    fn assert_canonical(&self, layouter: impl Layouter<F>, a: &Self::Element) -> Result<(), Error>;
+}
+
+/// A chip for arithmetic over a foreign field `W` inside a circuit over `F`,
+/// e.g. the base field of BLS12-381 inside a circuit over its scalar field.
+///
+/// Every operation proves a relation
+///
+/// `sum a_i * b_i + sum +-x_j + c = q * p + r`
+///
+/// for a witnessed quotient `q` and remainder `r`. The relation is checked limb
+/// by limb with carries for the lower `CHECKED_LIMBS` limb positions, which
+/// proves it modulo `2^(CHECKED_LIMBS * LIMB_BITS)`, and once more modulo the
+/// native modulus using the `native` cells. By the Chinese remainder theorem it
+/// then holds over the integers.
+pub struct ForeignFieldChip<F: PrimeField, W: PrimeField> {
+    config: ForeignFieldConfig,
+    _marker: PhantomData<(F, W)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ForeignFieldConfig {
+    x: Column<Advice>,
+    y: Column<Advice>,
+    acc: Column<Advice>,
+    cx: Column<Fixed>,
+    cm: Column<Fixed>,
+    q_acc: Selector,
+    range: RangeCheckConfig,
+}
+
+/// An operand of a term.
+enum Operand<F: PrimeField> {
+    Empty,
+    Copy(AssignedCell<F, F>),
+    Witness(Value<F>),
+    Constant(F),
+}
+
+impl<F: PrimeField> Operand<F> {
+    fn value(&self) -> Value<F> {
+        match self {
+            Operand::Empty => Value::known(F::ZERO),
+            Operand::Copy(cell) => cell.value().copied(),
+            Operand::Witness(value) => *value,
+            Operand::Constant(value) => Value::known(*value),
+        }
+    }
+}
+
+/// One row of a linear combination, contributing `cx * x + cm * x * y`.
+struct Term<F: PrimeField> {
+    x: Operand<F>,
+    y: Operand<F>,
+    cx: F,
+    cm: F,
+}
+
+impl<F: PrimeField> Term<F> {
+    fn linear(x: Operand<F>, cx: F) -> Self {
+        Term {
+            x,
+            y: Operand::Empty,
+            cx,
+            cm: F::ZERO,
+        }
+    }
+
+    fn product(x: &Number<F>, y: &Number<F>) -> Self {
+        Term {
+            x: Operand::Copy(x.0.clone()),
+            y: Operand::Copy(y.0.clone()),
+            cx: F::ZERO,
+            cm: F::ONE,
+        }
+    }
+
+    fn value(&self) -> Value<F> {
+        let (cx, cm) = (self.cx, self.cm);
+        self.x.value().zip(self.y.value()).map(|(x, y)| cx * x + cm * x * y)
+    }
+
+    fn sum(terms: &[Self]) -> Value<F> {
+        terms.iter().fold(Value::known(F::ZERO), |acc, term| acc + term.value())
+    }
+}
+
+impl<F: PrimeField, W: PrimeField> ForeignFieldChip<F, W> {
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 3],
+        constant: Column<Fixed>,
+    ) -> <Self as Chip<F>>::Config {
+        // Both sides of a relation with a single product are below
+        // 2^(2 * NUM_LIMBS * LIMB_BITS + 1), which must be less than the product of
+        // the two moduli we check the relation with.
+        assert!(W::NUM_BITS as usize <= NUM_LIMBS * LIMB_BITS);
+        assert!(CHECKED_LIMBS * LIMB_BITS + F::NUM_BITS as usize - 1 > 2 * NUM_LIMBS * LIMB_BITS + 1);
+
+        meta.enable_constant(constant);
+        for column in &advice {
+            meta.enable_equality(*column);
+        }
+        let [x, y, acc] = advice;
+        let cx = meta.fixed_column();
+        let cm = meta.fixed_column();
+        let q_acc = meta.selector();
+
+        // | x   | y   | acc                               | cx   | cm   |
+        // |-----|-----|-----------------------------------|------|------|
+        // |     |     | 0                                 |      |      |
+        // | x_1 | y_1 | acc_0 + cx_1*x_1 + cm_1*x_1*y_1  | cx_1 | cm_1 |
+        // | ... | ... | ...                               | ...  | ...  |
+        // | x_n | y_n | 0                                 | cx_n | cm_n |
+        //
+        // The last accumulator is constrained to be zero.
+        meta.create_gate("foreign field combination", |meta| {
+            let x = meta.query_advice(x, Rotation::cur());
+            let y = meta.query_advice(y, Rotation::cur());
+            let acc_prev = meta.query_advice(acc, Rotation::prev());
+            let acc = meta.query_advice(acc, Rotation::cur());
+            let cx = meta.query_fixed(cx, Rotation::cur());
+            let cm = meta.query_fixed(cm, Rotation::cur());
+            let q_acc = meta.query_selector(q_acc);
+
+            vec![q_acc * (acc - acc_prev - cx * x.clone() - cm * x * y)]
+        });
+
+        let range = RangeCheckChip::configure(meta, [x, y], RANGE_BITS);
+
+        ForeignFieldConfig {
+            x,
+            y,
+            acc,
+            cx,
+            cm,
+            q_acc,
+            range,
+        }
+    }
+
+    fn assign_operand(
+        &self,
+        region: &mut Region<'_, F>,
+        column: Column<Advice>,
+        offset: usize,
+        operand: &Operand<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        match operand {
+            Operand::Empty => region.assign_advice(|| "empty", column, offset, || Value::known(F::ZERO)),
+            Operand::Copy(cell) => cell.copy_advice(|| "copy", region, column, offset),
+            Operand::Witness(value) => region.assign_advice(|| "witness", column, offset, || *value),
+            Operand::Constant(value) => region.assign_advice_from_constant(|| "constant", column, offset, *value),
+        }
+    }
+
+    /// Assigns `terms` as one linear combination that must sum to zero, starting
+    /// at `offset`, and returns the assigned `x` cell of every term.
+    fn assign_combination(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        terms: &[Term<F>],
+    ) -> Result<Vec<AssignedCell<F, F>>, Error> {
+        let config = &self.config;
+
+        let mut acc = region.assign_advice_from_constant(|| "initial acc", config.acc, *offset, F::ZERO)?;
+        let mut cells = Vec::with_capacity(terms.len());
+        for term in terms {
+            *offset += 1;
+            config.q_acc.enable(region, *offset)?;
+            region.assign_fixed(|| "cx", config.cx, *offset, || Value::known(term.cx))?;
+            region.assign_fixed(|| "cm", config.cm, *offset, || Value::known(term.cm))?;
+
+            let x = self.assign_operand(region, config.x, *offset, &term.x)?;
+            self.assign_operand(region, config.y, *offset, &term.y)?;
+            let value = acc.value().copied() + term.value();
+            acc = region.assign_advice(|| "acc", config.acc, *offset, || value)?;
+
+            cells.push(x);
+        }
+        region.constrain_constant(acc.cell(), F::ZERO)?;
+        *offset += 1;
+
+        Ok(cells)
+    }
+
+    /// Witnesses an integer below `2^(NUM_LIMBS * LIMB_BITS)` and range checks its
+    /// limbs.
+    fn assign_integer(&self, mut layouter: impl Layouter<F>, integer: Value<Vec<u64>>) -> Result<Element<F>, Error> {
+        let limbs = integer
+            .as_ref()
+            .map(|integer| big_limbs::<F>(integer, NUM_LIMBS))
+            .transpose_vec(NUM_LIMBS);
+        let native = integer.as_ref().map(|integer| from_big::<F>(integer));
+
+        let element = layouter.assign_region(
+            || "load limbs",
+            |mut region| {
+                let mut terms = vec![Term::linear(Operand::Witness(native), -F::ONE)];
+                for (i, limb) in limbs.iter().enumerate() {
+                    terms.push(Term::linear(Operand::Witness(*limb), pow2(i * LIMB_BITS)));
+                }
+                let cells = self.assign_combination(&mut region, &mut 0, &terms)?;
+
+                Ok(Element {
+                    limbs: cells[1..].iter().cloned().map(Number).collect(),
+                    native: Number(cells[0].clone()),
+                    integer: integer.clone(),
+                })
+            },
+        )?;
+
+        let range_chip = RangeCheckChip::<F>::construct(self.config.range.clone());
+        for limb in &element.limbs {
+            range_chip.range_check(layouter.namespace(|| "range check limb"), limb.clone(), LIMB_BITS / RANGE_BITS)?;
+        }
+
+        Ok(element)
+    }
+
+    /// Constrains the limbs of `element` to those of a constant integer.
+    fn constrain_integer(&self, mut layouter: impl Layouter<F>, element: &Element<F>, integer: &[u64]) -> Result<(), Error> {
+        let limbs = big_limbs::<F>(integer, NUM_LIMBS);
+
+        layouter.assign_region(
+            || "constrain limbs",
+            |mut region| {
+                for (limb, value) in element.limbs.iter().zip(limbs.iter()) {
+                    region.constrain_constant(limb.0.cell(), *value)?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Proves `sum a_i * b_i + sum +-x_j + constant = q * p + r` and returns `r`.
+    /// The left-hand side must be nonnegative after every partial sum, in the
+    /// order the terms are given.
+    fn relation(
+        &self,
+        mut layouter: impl Layouter<F>,
+        products: &[(&Element<F>, &Element<F>)],
+        linear: &[(&Element<F>, bool)],
+        constant: &[u64],
+    ) -> Result<Element<F>, Error> {
+        let modulus = modulus::<W>();
+
+        let lhs = products.iter().fold(Value::known(constant.to_vec()), |acc, (a, b)| {
+            acc.zip(a.integer.as_ref().zip(b.integer.as_ref()))
+                .map(|(acc, (a, b))| big_add(&acc, &big_mul(a, b)))
+        });
+        let lhs = linear.iter().fold(lhs, |acc, (x, negate)| {
+            acc.zip(x.integer.as_ref())
+                .map(|(acc, x)| if *negate { big_sub(&acc, x) } else { big_add(&acc, x) })
+        });
+        let q = lhs.as_ref().map(|lhs| big_div_rem(lhs, &modulus).0);
+        let r = lhs.as_ref().map(|lhs| big_div_rem(lhs, &modulus).1);
+
+        let q = self.assign_integer(layouter.namespace(|| "quotient"), q)?;
+        let r = self.assign_integer(layouter.namespace(|| "remainder"), r)?;
+
+        let modulus_limbs = big_limbs::<F>(&modulus, NUM_LIMBS);
+        let constant_limbs = big_limbs::<F>(constant, CHECKED_LIMBS);
+        let radix = pow2::<F>(LIMB_BITS);
+        let shift = pow2::<F>(CARRY_SHIFT);
+
+        // Limb position k of the relation is
+        //
+        // sum_{i+j=k} a_i * b_j + sum +-x_k + c_k - sum_{i+j=k} q_i * p_j - r_k + carry_{k-1} = 2^LIMB_BITS * carry_k
+        //
+        // where every carry is stored shifted by 2^CARRY_SHIFT.
+        let carries = layouter.assign_region(
+            || "limb relation",
+            |mut region| {
+                let mut offset = 0;
+                let mut carries: Vec<AssignedCell<F, F>> = Vec::with_capacity(CHECKED_LIMBS);
+                for k in 0..CHECKED_LIMBS {
+                    let positions = (0..NUM_LIMBS).filter(|i| k >= *i && k - i < NUM_LIMBS);
+
+                    let mut terms = Vec::new();
+                    for (a, b) in products {
+                        for i in positions.clone() {
+                            terms.push(Term::product(&a.limbs[i], &b.limbs[k - i]));
+                        }
+                    }
+                    for i in positions {
+                        terms.push(Term::linear(Operand::Copy(q.limbs[i].0.clone()), -modulus_limbs[k - i]));
+                    }
+                    if k < NUM_LIMBS {
+                        for (x, negate) in linear {
+                            let sign = if *negate { -F::ONE } else { F::ONE };
+                            terms.push(Term::linear(Operand::Copy(x.limbs[k].0.clone()), sign));
+                        }
+                        terms.push(Term::linear(Operand::Copy(r.limbs[k].0.clone()), -F::ONE));
+                    }
+
+                    let mut offsets = constant_limbs[k] + radix * shift;
+                    if let Some(carry) = carries.last() {
+                        terms.push(Term::linear(Operand::Copy(carry.clone()), F::ONE));
+                        offsets -= shift;
+                    }
+                    terms.push(Term::linear(Operand::Constant(offsets), F::ONE));
+
+                    // The carry is whatever is left over, which is divisible by the
+                    // radix if the relation holds.
+                    let carry = Term::sum(&terms).map(|sum| sum * radix.invert().unwrap());
+                    terms.push(Term::linear(Operand::Witness(carry), -radix));
+
+                    let cells = self.assign_combination(&mut region, &mut offset, &terms)?;
+                    carries.push(cells[cells.len() - 1].clone());
+                }
+                Ok(carries)
+            },
+        )?;
+
+        let modulus_native = from_big::<F>(&modulus);
+        layouter.assign_region(
+            || "native relation",
+            |mut region| {
+                let mut terms = products
+                    .iter()
+                    .map(|(a, b)| Term::product(&a.native, &b.native))
+                    .collect::<Vec<_>>();
+                for (x, negate) in linear {
+                    let sign = if *negate { -F::ONE } else { F::ONE };
+                    terms.push(Term::linear(Operand::Copy(x.native.0.clone()), sign));
+                }
+                terms.push(Term::linear(Operand::Constant(from_big(constant)), F::ONE));
+                terms.push(Term::linear(Operand::Copy(q.native.0.clone()), -modulus_native));
+                terms.push(Term::linear(Operand::Copy(r.native.0.clone()), -F::ONE));
+
+                self.assign_combination(&mut region, &mut 0, &terms).map(|_| ())
+            },
+        )?;
+
+        let range_chip = RangeCheckChip::<F>::construct(self.config.range.clone());
+        for carry in carries {
+            range_chip.range_check(layouter.namespace(|| "range check carry"), Number(carry), CARRY_BITS / RANGE_BITS)?;
+        }
+
+        Ok(r)
+    }
+
+    /// Returns `sum a_i * b_i` for native values. This uses the same gate as the
+    /// foreign field relations, so circuits that mix both need no extra columns.
+    pub fn sum_of_products(
+        &self,
+        mut layouter: impl Layouter<F>,
+        terms: &[(&Number<F>, &Number<F>)],
+    ) -> Result<Number<F>, Error> {
+        layouter.assign_region(
+            || "sum of products",
+            |mut region| {
+                let mut terms = terms.iter().map(|(a, b)| Term::product(a, b)).collect::<Vec<_>>();
+                let sum = Term::sum(&terms);
+                terms.push(Term::linear(Operand::Witness(sum), -F::ONE));
+
+                let cells = self.assign_combination(&mut region, &mut 0, &terms)?;
+                Ok(Number(cells[cells.len() - 1].clone()))
+            },
+        )
+    }
+
+    /// Returns `sum a_i * b_i + sum c_j * x_j + constant` for native values.
+    pub fn linear_combination(
+        &self,
+        mut layouter: impl Layouter<F>,
+        products: &[(&Number<F>, &Number<F>)],
+        linear: &[(&Number<F>, F)],
+        constant: F,
+    ) -> Result<Number<F>, Error> {
+        layouter.assign_region(
+            || "linear combination",
+            |mut region| {
+                let mut terms = products.iter().map(|(a, b)| Term::product(a, b)).collect::<Vec<_>>();
+                for (x, c) in linear {
+                    terms.push(Term::linear(Operand::Copy(x.0.clone()), *c));
+                }
+                terms.push(Term::linear(Operand::Constant(constant), F::ONE));
+                let sum = Term::sum(&terms);
+                terms.push(Term::linear(Operand::Witness(sum), -F::ONE));
+
+                let cells = self.assign_combination(&mut region, &mut 0, &terms)?;
+                Ok(Number(cells[cells.len() - 1].clone()))
+            },
+        )
+    }
+
+    /// A multiple of the modulus that is at least `2^(NUM_LIMBS * LIMB_BITS)`, so
+    /// that `a - b + offset` is nonnegative for any elements `a` and `b`.
+    fn sub_offset() -> Vec<u64> {
+        let shift = NUM_LIMBS * LIMB_BITS + 1 - W::NUM_BITS as usize;
+        let mut multiple = vec![0u64; shift / 64 + 1];
+        multiple[shift / 64] = 1 << (shift % 64);
+        big_mul(&modulus::<W>(), &multiple)
+    }
+}
+
+impl<F: PrimeField, W: PrimeField> Chip<F> for ForeignFieldChip<F, W> {
+    type Config = ForeignFieldConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: PrimeField, W: PrimeField> ForeignFieldInstructions<F, W> for ForeignFieldChip<F, W> {
+    type Element = Element<F>;
+
+    fn load_table(&self, layouter: impl Layouter<F>) -> Result<(), Error> {
+        RangeCheckChip::<F>::construct(self.config.range.clone()).load_table(layouter)
+    }
+
+    fn load_private(&self, layouter: impl Layouter<F>, value: Value<W>) -> Result<Self::Element, Error> {
+        self.assign_integer(layouter, value.map(|value| to_big(&value)))
+    }
+
+    fn load_constant(&self, mut layouter: impl Layouter<F>, constant: W) -> Result<Self::Element, Error> {
+        let integer = to_big(&constant);
+
+        // Constant limbs do not need to be range checked.
+        layouter.assign_region(
+            || "load constant",
+            |mut region| {
+                let mut terms = vec![Term::linear(Operand::Constant(from_big(&integer)), -F::ONE)];
+                for (i, limb) in big_limbs::<F>(&integer, NUM_LIMBS).into_iter().enumerate() {
+                    terms.push(Term::linear(Operand::Constant(limb), pow2(i * LIMB_BITS)));
+                }
+                let cells = self.assign_combination(&mut region, &mut 0, &terms)?;
+
+                Ok(Element {
+                    limbs: cells[1..].iter().cloned().map(Number).collect(),
+                    native: Number(cells[0].clone()),
+                    integer: Value::known(integer.clone()),
+                })
+            },
+        )
+    }
+
+    fn add(&self, layouter: impl Layouter<F>, a: &Self::Element, b: &Self::Element) -> Result<Self::Element, Error> {
+        self.relation(layouter, &[], &[(a, false), (b, false)], &[])
+    }
+
+    fn sub(&self, layouter: impl Layouter<F>, a: &Self::Element, b: &Self::Element) -> Result<Self::Element, Error> {
+        self.relation(layouter, &[], &[(a, false), (b, true)], &Self::sub_offset())
+    }
+
+    fn mul(&self, layouter: impl Layouter<F>, a: &Self::Element, b: &Self::Element) -> Result<Self::Element, Error> {
+        self.relation(layouter, &[(a, b)], &[], &[])
+    }
+
+    fn inverse(&self, mut layouter: impl Layouter<F>, a: &Self::Element) -> Result<Self::Element, Error> {
+        // For a = 0 there is no inverse, and any witness fails the check below.
+        let inverse = a.value::<W>().map(|a| a.invert().unwrap_or(W::ZERO));
+        let inverse = self.load_private(layouter.namespace(|| "load inverse"), inverse)?;
+
+        let one = self.relation(layouter.namespace(|| "a * a^-1"), &[(a, &inverse)], &[], &[])?;
+        self.constrain_integer(layouter.namespace(|| "a * a^-1 = 1"), &one, &[1])?;
+
+        Ok(inverse)
+    }
+
+    fn assert_equal(&self, mut layouter: impl Layouter<F>, a: &Self::Element, b: &Self::Element) -> Result<(), Error> {
+        let difference = self.sub(layouter.namespace(|| "a - b"), a, b)?;
+        self.constrain_integer(layouter.namespace(|| "a - b = 0"), &difference, &[])
+    }
+
+    fn assert_canonical(&self, mut layouter: impl Layouter<F>, a: &Self::Element) -> Result<(), Error> {
+        // We witness d = p - 1 - a with range checked limbs, so d >= 0, and check
+        // a + d = p - 1 limb by limb. Both limbs are below 2^LIMB_BITS, so every
+        // carry is a bit and the last one must be zero.
+        let bound = big_sub(&modulus::<W>(), &[1]);
+        let d = a.integer.as_ref().map(|a| match big_cmp(a, &bound) {
+            // There is no valid witness for an unreduced `a`.
+            Ordering::Greater => vec![0],
+            _ => big_sub(&bound, a),
+        });
+        let d = self.assign_integer(layouter.namespace(|| "p - 1 - a"), d)?;
+
+        let bound_limbs = big_limbs::<F>(&bound, NUM_LIMBS);
+        let radix = pow2::<F>(LIMB_BITS);
+        layouter.assign_region(
+            || "a + d = p - 1",
+            |mut region| {
+                let mut offset = 0;
+                let mut carry: Option<AssignedCell<F, F>> = None;
+                for k in 0..NUM_LIMBS {
+                    let mut terms = vec![
+                        Term::linear(Operand::Copy(a.limbs[k].0.clone()), F::ONE),
+                        Term::linear(Operand::Copy(d.limbs[k].0.clone()), F::ONE),
+                        Term::linear(Operand::Constant(-bound_limbs[k]), F::ONE),
+                    ];
+                    if let Some(carry) = &carry {
+                        terms.push(Term::linear(Operand::Copy(carry.clone()), F::ONE));
+                    }
+                    if k + 1 == NUM_LIMBS {
+                        self.assign_combination(&mut region, &mut offset, &terms)?;
+                        break;
+                    }
+
+                    let next = Term::sum(&terms).map(|sum| sum * radix.invert().unwrap());
+                    terms.push(Term::linear(Operand::Witness(next), -radix));
+                    let cells = self.assign_combination(&mut region, &mut offset, &terms)?;
+                    let next = Number(cells[cells.len() - 1].clone());
+
+                    // next * next - next = 0
+                    let boolean = [Term::product(&next, &next), Term::linear(Operand::Copy(next.0.clone()), -F::ONE)];
+                    self.assign_combination(&mut region, &mut offset, &boolean)?;
+                    carry = Some(next.0);
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Proves `c = (a + b) / a` for private elements of the foreign field `W`.
+#[derive(Default)]
+pub struct ForeignFieldCircuit<W: PrimeField> {
+    pub a: Value<W>,
+    pub b: Value<W>,
+    pub c: Value<W>,
+}
+
+impl<F: PrimeField, W: PrimeField> Circuit<F> for ForeignFieldCircuit<W> {
+    type Config = ForeignFieldConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+
+        ForeignFieldChip::<F, W>::configure(meta, advice, constant)
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let chip = ForeignFieldChip::<F, W>::construct(config);
+        chip.load_table(layouter.namespace(|| "load table"))?;
+
+        let a = chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+        let c = chip.load_private(layouter.namespace(|| "load c"), self.c)?;
+
+        let a_inv = chip.inverse(layouter.namespace(|| "1 / a"), &a)?;
+        let sum = chip.add(layouter.namespace(|| "a + b"), &a, &b)?;
+        let out = chip.mul(layouter.namespace(|| "(a + b) / a"), &sum, &a_inv)?;
+
+        chip.assert_equal(layouter.namespace(|| "assert c"), &out, &c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::{Fq, Scalar};
+    use halo2_proofs::halo2curves::ff::Field;
+
+    use super::*;
+
+    #[test]
+    fn arithmetic() {
+        // The base field of BLS12-381 inside a circuit over its scalar field,
+        // which is what in-circuit G1 operations are built on.
+        let a = Fq::from(0xdead_beef).invert().unwrap();
+        let b = -Fq::from(3);
+        let c = (a + b) * a.invert().unwrap();
+        let foreign_circuit = ForeignFieldCircuit {
+            a: Value::known(a),
+            b: Value::known(b),
+            c: Value::known(c),
+        };
+        let prover = MockProver::<Scalar>::run(12, &foreign_circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+        let wrong = ForeignFieldCircuit {
+            c: Value::known(c + Fq::ONE),
+            ..foreign_circuit
+        };
+        let prover = MockProver::<Scalar>::run(12, &wrong, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use std::collections::BTreeSet;
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2_proofs::halo2curves::ff::{Field, PrimeField};
+use halo2_proofs::halo2curves::group::GroupEncoding;
+use halo2_proofs::plonk::ConstraintSystem;
+
+/// What a proof with `num_instances` instances of a circuit consists of, derived
+/// from its `ConstraintSystem` after key generation (which turns the selectors
+/// into fixed columns). Instances are not queried with KZG, so instance columns
+/// add nothing to the proof.
+#[derive(Clone, Debug)]
+pub struct Layout {
+    pub num_instances: usize,
+    pub advice_commitments: usize,
+    /// The permuted input and table of every lookup.
+    pub lookup_permuted_commitments: usize,
+    pub permutation_product_commitments: usize,
+    pub lookup_product_commitments: usize,
+    pub shuffle_product_commitments: usize,
+    /// The random polynomial of the vanishing argument.
+    pub random_commitments: usize,
+    /// The pieces of h(X), which has degree (d - 1) * n for constraints of
+    /// degree d.
+    pub quotient_pieces: usize,
+    pub advice_evals: usize,
+    pub fixed_evals: usize,
+    pub random_evals: usize,
+    pub permutation_common_evals: usize,
+    /// Every set at x and omega x, and all but the last one at omega^last x.
+    pub permutation_product_evals: usize,
+    /// The product at x and omega x, the permuted input at x and omega^-1 x and
+    /// the permuted table at x.
+    pub lookup_evals: usize,
+    pub shuffle_evals: usize,
+    /// The rotations of x that polynomials are opened at.
+    pub rotations: BTreeSet<i32>,
+}
+
+impl Layout {
+    pub fn new<F: Field>(cs: &ConstraintSystem<F>, num_instances: usize) -> Self {
+        let permutation_columns = cs.permutation().get_columns().len();
+        let chunk_len = cs.degree() - 2;
+        let num_sets = (permutation_columns + chunk_len - 1) / chunk_len;
+        let lookups = cs.lookups().len();
+        let shuffles = cs.shuffles().len();
+
+        let mut rotations = cs
+            .advice_queries()
+            .iter()
+            .map(|(_, at)| at.0)
+            .chain(cs.fixed_queries().iter().map(|(_, at)| at.0))
+            .chain(Some(0))
+            .collect::<BTreeSet<_>>();
+        if num_sets > 0 || lookups > 0 || shuffles > 0 {
+            rotations.insert(1);
+        }
+        if num_sets > 1 {
+            rotations.insert(-((cs.blinding_factors() + 1) as i32));
+        }
+        if lookups > 0 {
+            rotations.insert(-1);
+        }
+
+        Layout {
+            num_instances,
+            advice_commitments: num_instances * cs.num_advice_columns(),
+            lookup_permuted_commitments: num_instances * 2 * lookups,
+            permutation_product_commitments: num_instances * num_sets,
+            lookup_product_commitments: num_instances * lookups,
+            shuffle_product_commitments: num_instances * shuffles,
+            random_commitments: 1,
+            quotient_pieces: cs.degree() - 1,
+            advice_evals: num_instances * cs.advice_queries().len(),
+            fixed_evals: cs.fixed_queries().len(),
+            random_evals: 1,
+            permutation_common_evals: permutation_columns,
+            permutation_product_evals: num_instances * (3 * num_sets).saturating_sub(1),
+            lookup_evals: num_instances * 5 * lookups,
+            shuffle_evals: num_instances * 2 * shuffles,
+            rotations,
+        }
+    }
+
+    /// The commitments before the multiopen argument.
+    pub fn commitments(&self) -> usize {
+        self.advice_commitments
+            + self.lookup_permuted_commitments
+            + self.permutation_product_commitments
+            + self.lookup_product_commitments
+            + self.shuffle_product_commitments
+            + self.random_commitments
+            + self.quotient_pieces
+    }
+
+    pub fn evals(&self) -> usize {
+        self.advice_evals
+            + self.fixed_evals
+            + self.random_evals
+            + self.permutation_common_evals
+            + self.permutation_product_evals
+            + self.lookup_evals
+            + self.shuffle_evals
+    }
+
+    /// The commitments of the multiopen argument: GWC opens every point on its
+    /// own, SHPLONK takes two for all of them.
+    pub fn openings(&self, shplonk: bool) -> usize {
+        if shplonk {
+            2
+        } else {
+            self.rotations.len()
+        }
+    }
+
+    /// The size of a proof written with halo2's `Blake2bWrite`, which writes
+    /// points compressed and scalars in their canonical representation.
+    pub fn proof_bytes<C: CurveAffine>(&self, shplonk: bool) -> usize {
+        let point = <C as GroupEncoding>::Repr::default().as_ref().len();
+        let scalar = <C::Scalar as PrimeField>::Repr::default().as_ref().len();
+        (self.commitments() + self.openings(shplonk)) * point + self.evals() * scalar
+    }
+
+    pub fn print<C: CurveAffine>(&self, name: &str) {
+        println!("{} ({} instance(s))", name, self.num_instances);
+        for (what, count) in [
+            ("advice commitments", self.advice_commitments),
+            ("lookup permuted commitments", self.lookup_permuted_commitments),
+            ("permutation product commitments", self.permutation_product_commitments),
+            ("lookup product commitments", self.lookup_product_commitments),
+            ("shuffle product commitments", self.shuffle_product_commitments),
+            ("random commitment", self.random_commitments),
+            ("quotient pieces", self.quotient_pieces),
+            ("advice evals", self.advice_evals),
+            ("fixed evals", self.fixed_evals),
+            ("random eval", self.random_evals),
+            ("permutation common evals", self.permutation_common_evals),
+            ("permutation product evals", self.permutation_product_evals),
+            ("lookup evals", self.lookup_evals),
+            ("shuffle evals", self.shuffle_evals),
+        ] {
+            if count > 0 {
+                println!("  {:<32} {:>4}", what, count);
+            }
+        }
+        println!("  {:<32} {:>4} {:?}", "opening points", self.rotations.len(), self.rotations);
+        println!(
+            "  {:<32} {:>4} bytes with GWC, {} with SHPLONK",
+            "proof",
+            self.proof_bytes::<C>(false),
+            self.proof_bytes::<C>(true)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::circuit::Value;
+    use halo2_proofs::halo2curves::bls12_381::{G1Affine, Scalar};
+    use halo2_proofs::plonk::Circuit;
+    use halo2_proofs::poly::kzg::multiopen::ProverGWC;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::multiset::MultisetCircuit;
+    use crate::range_check::RangeCheckCircuit;
+    use crate::shuffle::ShuffleCircuit;
+    use crate::{my_circuit, prove, prove_and_verify};
+
+    /// Proves `circuit` with halo2 and checks the size of the proof against its
+    /// layout.
+    fn assert_proof_bytes<C: Circuit<Scalar>>(k: u32, circuit: C) {
+        let (_, pk, proof) = prove_and_verify(k, circuit, &[]);
+        assert_eq!(proof.len(), Layout::new(&pk.get_vk().cs, 1).proof_bytes::<G1Affine>(false));
+    }
+
+    fn values(values: &[u64]) -> Vec<Value<Scalar>> {
+        values.iter().map(|v| Value::known(Scalar::from(*v))).collect()
+    }
+
+    #[test]
+    fn proof_bytes() {
+        assert_proof_bytes(4, my_circuit(2, 3));
+        // The lookup adds its permuted input and table, and its product.
+        assert_proof_bytes(6, RangeCheckCircuit { value: Value::known(Scalar::from(0xbeef)) });
+        // The running product of the second phase is one more advice column.
+        assert_proof_bytes(5, MultisetCircuit { a: values(&[1, 2, 3, 4]), b: values(&[3, 1, 4, 2]) });
+        assert_proof_bytes(5, ShuffleCircuit { a: values(&[5, 8, 1, 3]), b: values(&[1, 3, 5, 8]) });
+    }
+
+    #[test]
+    fn proof_bytes_of_several_instances() {
+        let (params, pk, _) = prove_and_verify(4, my_circuit(2, 3), &[]);
+        let circuits = [(2, 3), (4, 5), (6, 7)].map(|(a, b)| my_circuit(a, b));
+        let proof = prove::<ProverGWC<_>, _>(&params, &pk, &circuits, &[&[], &[], &[]], StdRng::from_seed([2u8; 32]));
+        assert_eq!(proof.len(), Layout::new(&pk.get_vk().cs, 3).proof_bytes::<G1Affine>(false));
+    }
+}
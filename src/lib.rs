@@ -0,0 +1,1410 @@
+#[allow(unused_imports)]
+use core::num;
+#[allow(unused_imports)]
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::iter;
+use std::time::Instant;
+
+use halo2_proofs::halo2curves::group::{Curve, Group};
+use halo2_proofs::halo2curves::group::prime::PrimeCurveAffine;
+use halo2_proofs::halo2curves::pairing::MultiMillerLoop;
+#[allow(unused_imports)]
+use halo2_proofs::plonk::{Advice, Any, vanishing, Circuit, Column, ConstraintSystem, create_proof, Error, Expression, Fixed, keygen_pk, keygen_vk, verify_proof, ProvingKey, Selector};
+#[allow(unused_imports)]
+use halo2_proofs::halo2curves::bls12_381::{Bls12, Fq, G1Affine, Scalar, G1Projective, MillerLoopResult};
+use halo2_proofs::halo2curves::ff::Field;
+use halo2_proofs::halo2curves::ff::{FromUniformBytes, PrimeField, WithSmallOrderMulGroup};
+use halo2_proofs::helpers::SerdeCurveAffine;
+use halo2_proofs::circuit::{AssignedCell, Layouter, SimpleFloorPlanner, Value, Chip, Region};
+#[allow(unused_imports)]
+use halo2_proofs::poly::commitment::{Prover, Verifier, Blind, self};
+use halo2_proofs::poly::kzg::msm::{DualMSM, MSMKZG};
+#[allow(unused_imports)]
+use halo2_proofs::poly::kzg::strategy::{SingleStrategy, GuardKZG};
+use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
+use halo2_proofs::poly::kzg::multiopen::{ProverGWC, VerifierGWC};
+#[allow(unused_imports)]
+use halo2_proofs::poly::{Rotation, self};
+use halo2_proofs::transcript::{Blake2bRead, Blake2bWrite, Challenge255, TranscriptReadBuffer, TranscriptWriterBuffer, TranscriptRead};
+use halo2_proofs::transcript::EncodedChallenge;
+#[allow(unused_imports)]
+use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::arithmetic::{CurveAffine, powers};
+#[allow(unused_imports)]
+use rand::{Rng, RngCore, SeedableRng};
+#[allow(unused_imports)]
+use halo2_proofs::poly::query::CommitmentReference;
+use rand::rngs::StdRng;
+use halo2_proofs::poly::commitment::MSM;
+
+pub mod aggregator;
+pub mod batch;
+pub mod bits;
+pub mod blake2b;
+pub mod chain;
+pub mod conditional;
+mod cost;
+pub mod degree;
+pub mod ecc;
+pub mod eddsa;
+pub mod evm;
+pub mod foreign_field;
+pub mod layout;
+pub mod merkle;
+pub mod msm;
+pub mod multiset;
+pub mod poseidon;
+mod prepared;
+pub mod range_check;
+pub mod sha256;
+pub mod shuffle;
+mod spread;
+
+use bits::BitConfig;
+use chain::ChainCircuit;
+use conditional::ConditionalConfig;
+use merkle::{merkle_root, MerkleCircuit};
+pub use msm::MultiopenInput;
+use multiset::MultisetCircuit;
+use poseidon::PoseidonParams;
+pub use prepared::PreparedVerifyingKey;
+use range_check::RangeCheckCircuit;
+use shuffle::ShuffleCircuit;
+
+pub trait NumericInstructions<F: Field>: Chip<F> {
+    /// Variable representing a number.
+    type Num;
+
+    /// Loads a number into the circuit as a private input.
+    fn load_private(&self, layouter: impl Layouter<F>, a: Value<F>) -> Result<Self::Num, Error>;
+
+    /// Loads a number into the circuit as a fixed constant.
+    fn load_constant(&self, layouter: impl Layouter<F>, constant: F) -> Result<Self::Num, Error>;
+
+    /// Returns `c = a * b`.
+    fn mul(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error>;
+}
+
+/// The chip that will implement our instructions! Chips store their own
+/// config, as well as type markers if necessary.
+pub struct FieldChip<F: Field> {
+    config: FieldConfig,
+    _marker: PhantomData<F>,
+}
+
+/// Chip state is stored in a config struct. This is generated by the chip
+/// during configuration, and then stored inside the chip.
+#[derive(Clone, Debug)]
+pub struct FieldConfig {
+    /// For this chip, we will use two advice columns to implement our instructions.
+    /// These are also the columns through which we communicate with other parts of
+    /// the circuit.
+    advice: [Column<Advice>; 2],
+
+    // We need a selector to enable the multiplication gate, so that we aren't placing
+    // any constraints on cells where `NumericInstructions::mul` is not being used.
+    // This is important when building larger circuits, where columns are used by
+    // multiple sets of instructions.
+    s_mul: Selector,
+
+    /// The gates of `bits::BitInstructions`, if `FieldChip::configure_bits`
+    /// added them.
+    bits: Option<BitConfig>,
+
+    /// The gates of `conditional::ConditionalInstructions`, if
+    /// `FieldChip::configure_conditional` added them.
+    conditional: Option<ConditionalConfig>,
+}
+
+impl<F: Field> FieldChip<F> {
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 2],
+        constant: Column<Fixed>,
+    ) -> <Self as Chip<F>>::Config {
+        meta.enable_constant(constant);
+        for column in &advice {
+            meta.enable_equality(*column);
+        }
+        let s_mul = meta.selector();
+
+        // Define our multiplication gate!
+        meta.create_gate("mul", |meta| {
+            // To implement multiplication, we need three advice cells and a selector
+            // cell. We arrange them like so:
+            //
+            // | a0  | a1  | s_mul |
+            // |-----|-----|-------|
+            // | lhs | rhs | s_mul |
+            // | out |     |       |
+            //
+            // Gates may refer to any relative offsets we want, but each distinct
+            // offset adds a cost to the proof. The most common offsets are 0 (the
+            // current row), 1 (the next row), and -1 (the previous row), for which
+            // `Rotation` has specific constructors.
+            let lhs = meta.query_advice(advice[0], Rotation::cur());
+            let rhs = meta.query_advice(advice[1], Rotation::cur());
+            let out = meta.query_advice(advice[0], Rotation::next());
+            let s_mul = meta.query_selector(s_mul);
+
+            // Finally, we return the polynomial expressions that constrain this gate.
+            // For our multiplication gate, we only need a single polynomial constraint.
+            //
+            // The polynomial expressions returned from `create_gate` will be
+            // constrained by the proving system to equal zero. Our expression
+            // has the following properties:
+            // - When s_mul = 0, any value is allowed in lhs, rhs, and out.
+            // - When s_mul != 0, this constrains lhs * rhs = out.
+            vec![s_mul * (lhs * rhs - out)]
+        });
+
+        FieldConfig {
+            advice,
+            s_mul,
+            bits: None,
+            conditional: None,
+        }
+    }
+}
+
+impl<F: Field> Chip<F> for FieldChip<F> {
+    type Config = FieldConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+/// A variable representing a number.
+#[derive(Clone)]
+pub struct Number<F: Field>(AssignedCell<F, F>);
+
+impl<F: Field> NumericInstructions<F> for FieldChip<F> {
+    type Num = Number<F>;
+
+    fn load_private(
+        &self,
+        mut layouter: impl Layouter<F>,
+        value: Value<F>,
+    ) -> Result<Self::Num, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "load private",
+            |mut region| {
+                region
+                    .assign_advice(|| "private input", config.advice[0], 0, || value)
+                    .map(Number)
+            },
+        )
+    }
+
+    fn load_constant(
+        &self,
+        mut layouter: impl Layouter<F>,
+        constant: F,
+    ) -> Result<Self::Num, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "load constant",
+            |mut region| {
+                region
+                    .assign_advice_from_constant(|| "constant value", config.advice[0], 0, constant)
+                    .map(Number)
+            },
+        )
+    }
+
+    fn mul(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        b: Self::Num,
+    ) -> Result<Self::Num, Error> {
+        let config = self.config();
+
+        layouter.assign_region(
+            || "mul",
+            |mut region: Region<'_, F>| {
+                // We only want to use a single multiplication gate in this region,
+                // so we enable it at region offset 0; this means it will constrain
+                // cells at offsets 0 and 1.
+                config.s_mul.enable(&mut region, 0)?;
+
+                // The inputs we've been given could be located anywhere in the circuit,
+                // but we can only rely on relative offsets inside this region. So we
+                // assign new cells inside the region and constrain them to have the
+                // same values as the inputs.
+                a.0.copy_advice(|| "lhs", &mut region, config.advice[0], 0)?;
+                b.0.copy_advice(|| "rhs", &mut region, config.advice[1], 0)?;
+
+                // Now we can assign the multiplication result, which is to be assigned
+                // into the output position.
+                let value = a.0.value().copied() * b.0.value();
+
+                // Finally, we do the assignment to the output, returning a
+                // variable to be used in another part of the circuit.
+                region
+                    .assign_advice(|| "lhs * rhs", config.advice[0], 1, || value)
+                    .map(Number)
+            },
+        )
+    }
+}
+
+/// The full circuit implementation.
+///
+/// In this struct we store the private input variables. We use `Option<F>` because
+/// they won't have any value during key generation. During proving, if any of these
+/// were `None` we would get an error.
+#[derive(Default)]
+pub struct MyCircuit<F: Field> {
+    pub constant: F,
+    pub a: Value<F>,
+    pub b: Value<F>,
+    pub c: Value<F>,
+}
+
+impl<F: Field> Circuit<F> for MyCircuit<F> {
+    // Since we are using a single chip for everything, we can just reuse its config.
+    type Config = FieldConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        // We create the two advice columns that FieldChip uses for I/O.
+        let advice = [meta.advice_column(), meta.advice_column()];
+
+        // Create a fixed column to load constants.
+        let constant = meta.fixed_column();
+
+        FieldChip::configure(meta, advice, constant)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<F>::construct(config);
+
+        // Load our private values into the circuit.
+        let a = field_chip.load_private(layouter.namespace(|| "load a"), self.a)?;
+        let b = field_chip.load_private(layouter.namespace(|| "load b"), self.b)?;
+        let c = field_chip.load_private(layouter.namespace(|| "load c"), self.c)?;
+
+        // Load the constant factor into the circuit.
+        let constant =
+            field_chip.load_constant(layouter.namespace(|| "load constant"), self.constant)?;
+
+        // We only have access to plain multiplication.
+        // We could implement our circuit as:
+        //     asq  = a*a
+        //     bsq  = b*b
+        //     absq = asq*bsq
+        //     c    = constant*asq*bsq
+        //
+        // but it's more efficient to implement it as:
+        //     ab   = a*b
+        //     absq = ab^2
+        //     c    = constant*absq
+        let ab = field_chip.mul(layouter.namespace(|| "a * b"), a, b)?;
+        let absq = field_chip.mul(layouter.namespace(|| "ab * ab"), ab.clone(), ab)?;
+        let c_out = field_chip.mul(layouter.namespace(|| "constant * absq"), constant, absq)?;
+
+        layouter.assign_region(
+            || "Assert equality",
+            |mut region| region.constrain_equal(c_out.0.cell(), c.0.cell()),
+        )
+    }
+}
+
+/// Generates the proving key of `circuit`, whose witnesses are not used.
+pub fn keygen<C: Circuit<Scalar>>(params: &ParamsKZG<Bls12>, circuit: &C) -> ProvingKey<G1Affine> {
+    let vk = keygen_vk(params, circuit).expect("keygen_vk should not fail");
+    keygen_pk(params, vk, circuit).expect("keygen_pk should not fail")
+}
+
+/// Proves `circuits` in one proof with the multiopen prover `P` and a Blake2b
+/// transcript. `instances` has the values of the instance columns of every
+/// circuit.
+pub fn prove<'params, P: Prover<'params, KZGCommitmentScheme<Bls12>>, C: Circuit<Scalar>>(
+    params: &'params ParamsKZG<Bls12>,
+    pk: &ProvingKey<G1Affine>,
+    circuits: &[C],
+    instances: &[&[&[Scalar]]],
+    rng: impl RngCore,
+) -> Vec<u8> {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<G1Affine>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<Bls12>, P, _, _, _, _>(params, pk, circuits, instances, rng, &mut transcript)
+        .expect("Proof generation failed");
+    transcript.finalize()
+}
+
+/// Runs halo2's own prover and verifier on `circuit`, returning the parameters,
+/// the proving key and the proof.
+#[cfg(test)]
+fn prove_and_verify<C: Circuit<Scalar>>(
+    k: u32,
+    circuit: C,
+    instances: &[&[Scalar]],
+) -> (ParamsKZG<Bls12>, ProvingKey<G1Affine>, Vec<u8>) {
+    let seed = [0u8; 32];
+    let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+    let params: ParamsKZG<Bls12> = ParamsKZG::setup(k, &mut rng);
+    let pk = keygen(&params, &circuit);
+    let proof = prove::<ProverGWC<_>, _>(&params, &pk, &[circuit], &[instances], rng);
+
+    let verifier = SingleStrategy::new(&params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<G1Affine>>::init(proof.as_slice());
+    verify_proof::<_, VerifierGWC<Bls12>, _, _, _>(
+        &params,
+        pk.get_vk(),
+        verifier,
+        &[instances],
+        &mut transcript,
+    ).expect("Verification failed");
+
+    (params, pk, proof)
+}
+
+/// `MyCircuit` with the constant 7 and c = 7 a^2 b^2.
+#[cfg(test)]
+fn my_circuit<F: PrimeField>(a: u64, b: u64) -> MyCircuit<F> {
+    let constant = F::from(7);
+    let (a, b) = (F::from(a), F::from(b));
+    MyCircuit {
+        constant,
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(constant * a.square() * b.square()),
+    }
+}
+
+/// A query of the explicit verifier, whose commitment is a linear combination
+/// of points, a missing factor being one. Only the H commitment has more than
+/// one term: its pieces go into the final MSM, like halo2's verifier does with
+/// `CommitmentReference::MSM`.
+#[derive(Clone, Debug)]
+struct LinearQuery<C: CurveAffine> {
+    point: C::Scalar,
+    commitment: Vec<(Option<C::Scalar>, C)>,
+    eval: C::Scalar,
+}
+
+/// The verifier of `MyCircuit`, step by step. It was written for this example,
+/// but the phases, gates, permutation sets, shuffles and number of quotient
+/// pieces are taken from `vk.cs`, so other circuits without lookups (e.g.
+/// `multiset::MultisetCircuit` with its second phase, `shuffle::ShuffleCircuit`
+/// or `merkle::MerkleCircuit` with its instance column) verify as well. It is
+/// generic over the pairing engine, and used with both BLS12-381 and BN254. A
+/// single proof can cover several instances of the circuit, which share the
+/// vanishing argument and the multiopen stage; `instances` has the values of
+/// the instance columns of every one, as for halo2's `verify_proof`.
+/// The constants that only depend on the key are taken from `pvk`, which is
+/// prepared once for all proofs.
+/// Returns the KZG accumulator, which still needs its pairing check, and the
+/// inputs of the multiopen stage that produced it.
+pub fn explicit_verify<'params, E>(
+    params: &'params ParamsKZG<E>,
+    pvk: &PreparedVerifyingKey<E::G1Affine>,
+    proof: &[u8],
+    instances: &[&[&[E::Scalar]]],
+) -> (DualMSM<'params, E>, MultiopenInput<E::G1Affine>)
+where
+    E: MultiMillerLoop + Debug,
+    E::G1Affine: SerdeCurveAffine<ScalarExt = E::Scalar, CurveExt = E::G1>,
+    E::G2Affine: SerdeCurveAffine,
+    E::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+{
+    // first  we initialize a transcript to store the proof.
+    let mut transcript = Blake2bRead::<_, _, Challenge255<E::G1Affine>>::init(proof);
+    explicit_verify_transcript(params, pvk, &mut transcript, instances)
+}
+
+/// `explicit_verify` for a proof created with any transcript, e.g. the Poseidon
+/// transcript of `aggregator`.
+fn explicit_verify_transcript<'params, E, EC: EncodedChallenge<E::G1Affine>, T: TranscriptRead<E::G1Affine, EC>>(
+    params: &'params ParamsKZG<E>,
+    pvk: &PreparedVerifyingKey<E::G1Affine>,
+    transcript: &mut T,
+    instances: &[&[&[E::Scalar]]],
+) -> (DualMSM<'params, E>, MultiopenInput<E::G1Affine>)
+where
+    E: MultiMillerLoop + Debug,
+    E::G1Affine: SerdeCurveAffine<ScalarExt = E::Scalar, CurveExt = E::G1>,
+    E::G2Affine: SerdeCurveAffine,
+    E::Scalar: WithSmallOrderMulGroup<3> + FromUniformBytes<64>,
+{
+    // The layout of the circuit: which columns are in which phase, which gates
+    // there are and which columns are in the permutation argument.
+    let vk = pvk.vk;
+    let cs = &vk.cs;
+    assert!(cs.lookups().is_empty(), "lookups are not supported");
+    let num_instances = instances.len();
+    for instance in instances {
+        assert_eq!(instance.len(), cs.num_instance_columns(), "one set of values per instance column");
+    }
+
+    // Add verification key hash to transcript
+    vk.hash_into(transcript).expect("Failed to hash into");
+
+    // With KZG the instance columns are not committed to: their values go into
+    // the transcript as they are, and we evaluate them ourselves at x below.
+    // `MyCircuit` has none.
+    for instance in instances {
+        for column in instance.iter() {
+            for value in column.iter() {
+                transcript.common_scalar(*value).unwrap();
+            }
+        }
+    }
+
+    // The advice columns are committed to phase by phase. After the commitments
+    // of a phase (of every instance), we sample the challenges that are usable
+    // after that phase, so the columns of the next phase can depend on them.
+    // For `MyCircuit` everything is in the first phase: two advice commitments
+    // for every instance, and no challenges.
+    let advice_column_phase = cs.advice_column_phase();
+    let challenge_phase = cs.challenge_phase();
+    let mut advice_commitments =
+        vec![vec![E::G1Affine::identity(); cs.num_advice_columns()]; num_instances];
+    let mut challenges = vec![E::Scalar::ZERO; cs.num_challenges()];
+    let num_phases = advice_column_phase.iter().copied().max().map_or(1, |phase| phase + 1);
+    for current_phase in 0..num_phases {
+        for advice_commitments in advice_commitments.iter_mut() {
+            for (phase, commitment) in advice_column_phase.iter().zip(advice_commitments.iter_mut()) {
+                if *phase == current_phase {
+                    *commitment = transcript.read_point().unwrap();
+                }
+            }
+        }
+        for (phase, challenge) in challenge_phase.iter().zip(challenges.iter_mut()) {
+            if *phase == current_phase {
+                *challenge = *transcript.squeeze_challenge_scalar::<()>();
+            }
+        }
+    }
+    // println!("advice_commitments: {:?}", advice_commitments);
+
+    // Sample theta challenge for keeping lookup and shuffle columns linearly independent
+    // Even if we don't have lookups, we need to keep this in order to be consistent with the transcript
+    let theta = transcript.squeeze_challenge_scalar::<()>();
+    // println!("theta: {:?}", theta);
+
+    // Sample beta challenge
+    let beta = transcript.squeeze_challenge_scalar::<()>();
+    // println!("beta: {:?}", beta);
+
+    // Sample gamma challenge
+    let gamma = transcript.squeeze_challenge_scalar::<()>();
+    // println!("gamma: {:?}", gamma);
+
+    // The columns of the permutation argument are split in chunks, such that the
+    // constraint of every chunk fits in the degree of the circuit. Each chunk
+    // gets its own permutation product (a "set"). For `MyCircuit` the degree is
+    // 3, so every chunk has one column and we have three sets for every instance.
+    let permutation_columns = cs.permutation().get_columns();
+    let chunk_len = cs.degree() - 2;
+    let num_sets = (permutation_columns.len() + chunk_len - 1) / chunk_len;
+    let permutations_committed = (0..num_instances)
+        .map(|_| {
+            (0..num_sets)
+                .map(|_| transcript.read_point().expect("aha"))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    // println!("permutations_committed: {:?}", permutations_committed);
+
+    // One product commitment for every shuffle argument, for every instance.
+    // `MyCircuit` has no shuffles.
+    let shuffles_committed = (0..num_instances)
+        .map(|_| {
+            (0..cs.shuffles().len())
+                .map(|_| transcript.read_point().unwrap())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // Now we read the commitment of a randomly sampled polynomial (step 3 of protocol).
+    // This one, and everything else of the vanishing argument, is shared by all instances.
+    let vanishing_rand = transcript.read_point().unwrap();
+
+    // Sample y challenge, which keeps the gates linearly independent.
+    let y = transcript.squeeze_challenge_scalar::<()>();
+    // println!("y: {:?}", y);
+
+    // Now we get the commitments of the split H polynomial. h(X) has degree
+    // (d - 1) * n for constraints of degree d, so it is committed to in d - 1
+    // pieces of n coefficients. For `MyCircuit` d = 3, which gives two pieces.
+    let vanishing_split = (0..vk.get_domain().get_quotient_poly_degree())
+        .map(|_| transcript.read_point().expect("Failed here"))
+        .collect::<Vec<_>>();
+
+    // Sample x challenge, which is used to ensure the circuit is
+    // satisfied with high probability.
+    let x = transcript.squeeze_challenge_scalar::<()>();
+    // println!("x: {:?}", x);
+
+    // One evaluation for every advice query, for every instance. For `MyCircuit`
+    // that is one for each advice, and one for the next of the first advice.
+    let advice_evals = (0..num_instances)
+        .map(|_| {
+            (0..cs.advice_queries().len())
+                .map(|_| transcript.read_scalar().unwrap())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    
+    // One evaluation for every fixed query. For `MyCircuit` one for our fixed
+    // value and another one for the selector.
+    let fixed_evals = (0..cs.fixed_queries().len())
+        .map(|_| transcript.read_scalar().unwrap())
+        .collect::<Vec<_>>();
+
+    // Random point to prove correctness of the random commitment of the vanishing polynomial
+    let random_eval = transcript.read_scalar().expect("aha");
+
+    // Evaluations of the permutations polynomials, one for every column in the argument.
+    let permutations_common_evals = (0..vk.permutation().commitments().len())
+        .map(|_| transcript.read_scalar().unwrap())
+        .collect::<Vec<_>>();
+    // println!("permutations_common_evals: {:?}", permutations_common_evals);
+
+    // Now we need the evaluations used to validate the permutation argument, meaning evaluations at the
+    // current and next powers of omega, and for all except the last, to the last power of omega.
+    // We build a tuple for each set, for every instance.
+    let permutations_evaluated = permutations_committed
+        .iter()
+        .map(|permutations_committed| {
+            permutations_committed
+                .iter()
+                .enumerate()
+                .map(|(set_index, commitment)| {
+                    let eval = transcript.read_scalar().unwrap();
+                    let next_eval = transcript.read_scalar().unwrap();
+                    // We don't need the evaluation of the last power of omega for the last one.
+                    let last_eval = if set_index + 1 < num_sets {
+                        Some(transcript.read_scalar().unwrap())
+                    } else {
+                        None
+                    };
+                    (*commitment, eval, next_eval, last_eval)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // The shuffle products are opened at x and \omega x, for every instance.
+    let shuffles_evaluated = shuffles_committed
+        .iter()
+        .map(|shuffles_committed| {
+            shuffles_committed
+                .iter()
+                .map(|commitment| {
+                    let eval = transcript.read_scalar().unwrap();
+                    let next_eval = transcript.read_scalar().unwrap();
+                    (*commitment, eval, next_eval)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // The position of a query in the evaluations we read above.
+    let advice_query_index = |column_index: usize, at: Rotation| {
+        cs.advice_queries()
+            .iter()
+            .position(|(column, rotation)| column.index() == column_index && *rotation == at)
+            .expect("advice query not found")
+    };
+    let fixed_query_index = |column_index: usize, at: Rotation| {
+        cs.fixed_queries()
+            .iter()
+            .position(|(column, rotation)| column.index() == column_index && *rotation == at)
+            .expect("fixed query not found")
+    };
+    let instance_query_index = |column_index: usize, at: Rotation| {
+        cs.instance_queries()
+            .iter()
+            .position(|(column, rotation)| column.index() == column_index && *rotation == at)
+            .expect("instance query not found")
+    };
+
+    // This check ensures the circuit is satisfied so long as the polynomial
+    // commitments open to the correct values.
+    // The counts of `cost::measure` are recorded along the way, and are no-ops
+    // otherwise.
+    cost::phase("vanishing");
+    let vanishing = {
+        // x^n, by squaring k times.
+        let xn = pvk.xn(*x);
+
+        // l_i(x) for the last and blinding rows and the first one, from the
+        // barycentric constants omega^i / n of the prepared key, 1 / (x^n - 1)
+        // for h(x) below, and the instance columns at every rotation they are
+        // queried at, all with a single inversion.
+        let blinding_factors = cs.blinding_factors();
+        let (l_evals, vanishing_inv, instance_evals) = pvk.l_evals_and_vanishing_inv(*x, xn, instances);
+        assert_eq!(l_evals.len(), 2 + blinding_factors);
+        let l_last = l_evals[0];
+        let l_blind: E::Scalar = l_evals[1..(1 + blinding_factors)]
+            .iter()
+            .fold(E::Scalar::ZERO, |acc, eval| {
+                cost::record(|c| c.field_adds += 1);
+                acc + eval
+            });
+        let l_0 = l_evals[1 + blinding_factors];
+
+        // (1 - (l_last(X) + l_blind(X))) * (
+        //   z_i(\omega X) \prod (p(X) + \beta s_i(X) + \gamma)
+        // - z_i(X) \prod (p(X) + \delta^i \beta X + \gamma)
+        // )
+        // for the set with the given index, whose columns evaluate to `cols`.
+        // The powers of delta of every column are in the prepared key.
+        let beta_x = *beta * *x;
+        cost::record(|c| c.field_muls += 1);
+        let last_permutation_constraint = |cols: &[E::Scalar], col_evals: &[E::Scalar], set: &(E::G1Affine, E::Scalar, E::Scalar, Option<E::Scalar>), chunk_index: usize| {
+            let mut left = set.2;
+            for (col, col_eval) in cols.iter().zip(col_evals.iter()) {
+                left *= &(*col + &(*beta * col_eval) + &*gamma);
+                cost::record(|c| {
+                    c.field_muls += 2;
+                    c.field_adds += 2;
+                });
+            }
+
+            let mut right = set.1;
+            let deltas = &pvk.deltas[chunk_index * chunk_len..];
+            for (col, delta) in cols.iter().zip(deltas.iter()) {
+                right *= &(*col + &(beta_x * delta) + &*gamma);
+                cost::record(|c| {
+                    c.field_muls += 2;
+                    c.field_adds += 2;
+                });
+            }
+
+            cost::record(|c| {
+                c.field_muls += 1;
+                c.field_adds += 3;
+            });
+            (left - &right) * (E::Scalar::ONE - &(l_last + &l_blind))
+        };
+
+        // Compute the expected value of h(x). The expressions of every instance
+        // follow each other, all with the same fixed evaluations.
+        let expressions = advice_evals
+            .iter()
+            .zip(instance_evals.iter())
+            .zip(permutations_evaluated.iter())
+            .zip(shuffles_evaluated.iter())
+            .flat_map(|(((advice_evals, instance_evals), sets), shuffles)| {
+            let fixed_evals = &fixed_evals;
+            let challenges = &challenges;
+
+            // The evaluation of every column of the permutation argument at x.
+            let cols = permutation_columns
+                .iter()
+                .map(|column| match column.column_type() {
+                    Any::Advice(_) => advice_evals[advice_query_index(column.index(), Rotation::cur())],
+                    Any::Fixed => fixed_evals[fixed_query_index(column.index(), Rotation::cur())],
+                    Any::Instance => instance_evals[instance_query_index(column.index(), Rotation::cur())],
+                })
+                .collect::<Vec<_>>();
+
+            // An expression, with the evaluations we read in place of the queries.
+            // The selectors were turned into fixed columns by the key generation.
+            let evaluate = move |poly: &Expression<E::Scalar>| {
+                poly.evaluate(
+                    &|constant| constant,
+                    &|_| panic!("selectors are compressed into fixed columns"),
+                    &|query| fixed_evals[fixed_query_index(query.column_index(), query.rotation())],
+                    &|query| advice_evals[advice_query_index(query.column_index(), query.rotation())],
+                    &|query| instance_evals[instance_query_index(query.column_index(), query.rotation())],
+                    &|challenge| challenges[challenge.index()],
+                    &|a| {
+                        cost::record(|c| c.field_adds += 1);
+                        -a
+                    },
+                    &|a, b| {
+                        cost::record(|c| c.field_adds += 1);
+                        a + &b
+                    },
+                    &|a, b| {
+                        cost::record(|c| c.field_muls += 1);
+                        a * &b
+                    },
+                    &|a, scalar| {
+                        cost::record(|c| c.field_muls += 1);
+                        a * &scalar
+                    },
+                )
+            };
+            // Several expressions compressed into one with powers of theta.
+            let compress = move |expressions: &[Expression<E::Scalar>]| {
+                expressions
+                    .iter()
+                    .map(evaluate)
+                    .fold(E::Scalar::ZERO, |acc, eval| {
+                        cost::record(|c| {
+                            c.field_muls += 1;
+                            c.field_adds += 1;
+                        });
+                        acc * &*theta + &eval
+                    })
+            };
+
+            // The gates. For `MyCircuit` this is only fixed_evals[1] * (a0 * a1 - a2),
+            // where the first fixed column (which we don't use in the gate) is the constant.
+            cs.gates()
+                .iter()
+                .flat_map(|gate| gate.polynomials().iter())
+                .map(evaluate)
+                .collect::<Vec<_>>()
+                .into_iter()
+                // Now we work on the permutation argument
+                // Enforce only for the first set.
+                // l_0(X) * (1 - z_0(X)) = 0
+                // CP1 in notes
+                .chain(sets.first().map(|first| {
+                    cost::record(|c| {
+                        c.field_muls += 1;
+                        c.field_adds += 1;
+                    });
+                    l_0 * &(E::Scalar::ONE - &first.1)
+                }))
+                // Next we enforce only for the last set.
+                // l_last(X) * (z_l(X)^2 - z_l(X)) = 0
+                // CP2 in notes
+                .chain(sets.last().map(|last| {
+                    cost::record(|c| {
+                        c.field_muls += 2;
+                        c.field_adds += 1;
+                    });
+                    &l_last * &(last.1.square() - &last.1)
+                }))
+                // Except for the first set, enforce.
+                // l_0(X) * (z_i(X) - z_{i-1}(\omega^(last) X)) = 0
+                // CP3 and CP4 in notes
+                .chain(sets.iter().skip(1).zip(sets.iter()).map(|(set, previous)| {
+                    cost::record(|c| {
+                        c.field_muls += 1;
+                        c.field_adds += 1;
+                    });
+                    (set.1 - previous.3.unwrap()) * &l_0
+                }))
+                // And for all the sets we enforce:
+                // (1 - (l_last(X) + l_blind(X))) * (
+                //   z_i(\omega X) \prod (p(X) + \beta s_i(X) + \gamma)
+                // - z_i(X) \prod (p(X) + \delta^i \beta X + \gamma)
+                // )
+                //
+                // For `MyCircuit` the order of our columns in the permutation argument is:
+                // 1. Fixed column CP7 in notes
+                // 2. Advice column CP5 in notes
+                // 3. Advice column CP6 in notes
+                .chain(
+                    sets.iter()
+                        .zip(cols.chunks(chunk_len))
+                        .zip(permutations_common_evals.chunks(chunk_len))
+                        .enumerate()
+                        .map(|(chunk_index, ((set, cols), col_evals))| {
+                            last_permutation_constraint(cols, col_evals, set, chunk_index)
+                        })
+                        .collect::<Vec<_>>(),
+                )
+                // Then the shuffle arguments. With a(X) and s(X) the compressed input
+                // and shuffle expressions, for every shuffle we enforce:
+                // l_0(X) * (1 - z(X)) = 0
+                // l_last(X) * (z(X)^2 - z(X)) = 0
+                // (1 - (l_last(X) + l_blind(X))) * (z(\omega X) (s(X) + \gamma) - z(X) (a(X) + \gamma)) = 0
+                .chain(
+                    cs.shuffles()
+                        .iter()
+                        .zip(shuffles.iter())
+                        .flat_map(|(argument, shuffle)| {
+                            let input_eval = compress(&argument.input_expressions()[..]);
+                            let shuffle_eval = compress(&argument.shuffle_expressions()[..]);
+                            let left = shuffle.2 * &(shuffle_eval + &*gamma);
+                            let right = shuffle.1 * &(input_eval + &*gamma);
+                            // The two products above, then the three constraints
+                            // below in order.
+                            cost::record(|c| {
+                                c.field_muls += 2;
+                                c.field_adds += 2;
+                            });
+                            cost::record(|c| {
+                                c.field_muls += 1;
+                                c.field_adds += 1;
+                            });
+                            cost::record(|c| {
+                                c.field_muls += 2;
+                                c.field_adds += 1;
+                            });
+                            cost::record(|c| {
+                                c.field_muls += 1;
+                                c.field_adds += 3;
+                            });
+                            [
+                                l_0 * &(E::Scalar::ONE - &shuffle.1),
+                                l_last * &(shuffle.1.square() - &shuffle.1),
+                                (left - &right) * (E::Scalar::ONE - &(l_last + &l_blind)),
+                            ]
+                        })
+                        .collect::<Vec<_>>(),
+                )
+        });
+
+        // Now we compute the vanishing polynomial expected evaluation
+        let expected_h_eval = expressions.fold(E::Scalar::ZERO, |h_eval, v| {
+            cost::record(|c| {
+                c.field_muls += 1;
+                c.field_adds += 1;
+            });
+            h_eval * &*y + &v
+        });
+        let expected_h_eval = expected_h_eval * vanishing_inv;
+        cost::record(|c| c.field_muls += 1);
+        // println!("expected_h_eval: {:?}", expected_h_eval);
+
+        // and its commitment, h_0 + xn h_1 + xn^2 h_2 + ..., which we leave to the
+        // final MSM. Like halo2's verifier, we only compute the powers of xn.
+        let h_commitment = vanishing_split
+            .iter()
+            .zip(powers(xn))
+            .enumerate()
+            .map(|(i, (commitment, power))| {
+                // xn itself is free, every higher power one multiplication.
+                if i > 1 {
+                    cost::record(|c| c.field_muls += 1);
+                }
+                ((i > 0).then_some(power), *commitment)
+            })
+            .collect::<Vec<_>>();
+
+        (h_commitment, expected_h_eval)
+    };
+    // println!("vanishing: {:?}", vanishing);
+
+    cost::phase("multiopen");
+    // Every rotation of x is a multiplication with a power of omega of the
+    // prepared key.
+    let rotate_omega = |at: Rotation| pvk.rotate(*x, at);
+    let blinding_factors = cs.blinding_factors();
+    let x_next = rotate_omega(Rotation::next());
+    let x_last = rotate_omega(Rotation(-((blinding_factors + 1) as i32)));
+
+    // The queries of every instance, followed by those that all instances share.
+    let queries = advice_commitments
+        .iter()
+        .zip(advice_evals.iter())
+        .zip(permutations_evaluated.iter())
+        .zip(shuffles_evaluated.iter())
+        .flat_map(|(((advice_commitments, advice_evals), sets), shuffles)| {
+        iter::empty()
+            .chain(cs.advice_queries().iter().enumerate().map(
+                move |(query_index, &(column, at))| LinearQuery {
+                    point: rotate_omega(at),
+                    commitment: vec![(None, advice_commitments[column.index()])],
+                    eval: advice_evals[query_index],
+                },
+            ))
+            // Open permutation product commitments at x and \omega x
+            .chain(sets.iter().flat_map(move |set| {
+                iter::empty()
+                    .chain(Some(LinearQuery {
+                        point: *x,
+                        commitment: vec![(None, set.0)],
+                        eval: set.1,
+                    }))
+                    .chain(Some(LinearQuery {
+                        point: x_next,
+                        commitment: vec![(None, set.0)],
+                        eval: set.2,
+                    }))
+            }))
+            // and all but the last one at \omega^(last) x, in reverse order.
+            .chain(sets.iter().rev().skip(1).map(move |set| LinearQuery {
+                point: x_last,
+                commitment: vec![(None, set.0)],
+                eval: set.3.unwrap(),
+            }))
+            // Open shuffle product commitments at x and \omega x
+            .chain(shuffles.iter().flat_map(move |shuffle| {
+                iter::empty()
+                    .chain(Some(LinearQuery {
+                        point: *x,
+                        commitment: vec![(None, shuffle.0)],
+                        eval: shuffle.1,
+                    }))
+                    .chain(Some(LinearQuery {
+                        point: x_next,
+                        commitment: vec![(None, shuffle.0)],
+                        eval: shuffle.2,
+                    }))
+            }))
+    })
+        .chain(
+            cs
+                .fixed_queries()
+                .iter()
+                .enumerate()
+                .map(|(query_index, &(column, at))| LinearQuery {
+                    point: rotate_omega(at),
+                    commitment: vec![(None, vk.fixed_commitments()[column.index()])],
+                    eval: fixed_evals[query_index],
+                }),
+        )
+        .chain(vk.permutation().commitments()
+            .iter()
+            .zip(permutations_common_evals.iter())
+            .map(move |(commitment, &eval)| LinearQuery {
+                point: *x,
+                commitment: vec![(None, *commitment)],
+                eval,
+            }))
+        .chain(Some(LinearQuery {
+            point: *x,
+            commitment: vanishing.0,
+            eval: vanishing.1,
+        }))
+        .chain(Some(LinearQuery {
+            point: *x,
+            commitment: vec![(None, vanishing_rand)],
+            eval: random_eval,
+        })).collect::<Vec<_>>();
+    // println!("queries: {:?}", queries);
+
+    let v  = transcript.squeeze_challenge_scalar::<()>().inner;
+    // println!("v: {:?}", v);
+
+    let mut point_query_map: Vec<(E::Scalar, Vec<_>)> = Vec::new();
+    for query in queries {
+        if let Some(pos) = point_query_map
+            .iter()
+            .position(|(point, _)| *point == query.point)
+        {
+            let (_, queries) = &mut point_query_map[pos];
+            queries.push(query);
+        } else {
+            point_query_map.push((query.point, vec![query]));
+        }
+    }
+
+    let commitment_data = point_query_map;
+    // println!("commitment_data: {:?}", commitment_data);
+    
+    // One opening proof for every point we open at.
+    let w = (0..commitment_data.len())
+        .map(|_| transcript.read_point().unwrap())
+        .collect::<Vec<_>>();
+    // println!("w: {:?}", w);
+    
+    let w_clone = w.clone();
+
+    let u = transcript.squeeze_challenge_scalar::<()>().inner;
+
+    let mut commitment_multi = MSMKZG::<E>::new();
+    let mut eval_multi = E::Scalar::ZERO;
+
+    let mut witness = MSMKZG::<E>::new();
+    let mut witness_with_aux = MSMKZG::<E>::new();
+
+    for ((commitment_at_a_point, wi), power_of_u) in
+        commitment_data.iter().zip(w.into_iter()).zip(powers(u))
+    {
+        let (z, queries) = commitment_at_a_point;
+        assert!(!queries.is_empty());
+
+        let (mut commitment_batch, eval_batch) = queries
+                .iter()
+                .zip(powers(v))
+                .map(|(query, power_of_v)| {
+                    assert_eq!(query.point, *z);
+
+                    // Every term of the commitment, e.g. every piece of H. Terms
+                    // with a factor take one more multiplication.
+                    let mut msm = MSMKZG::<E>::new();
+                    for (factor, point) in &query.commitment {
+                        let scalar = factor.map_or(power_of_v, |factor| {
+                            cost::record(|c| c.field_muls += 1);
+                            power_of_v * factor
+                        });
+                        msm.append_term(scalar, point.to_curve());
+                    }
+                    // The power of v itself, and the evaluation times it.
+                    let eval = power_of_v * query.eval;
+                    cost::record(|c| c.field_muls += 2);
+
+                    (msm, eval)
+                })
+                .reduce(|(mut commitment_acc, eval_acc), (commitment, eval)| {
+                    commitment_acc.add_msm(&commitment);
+                    cost::record(|c| c.field_adds += 1);
+                    (commitment_acc, eval_acc + eval)
+                })
+                .unwrap();
+
+        // Scaling multiplies every scalar of the batch.
+        let terms = commitment_batch.scalars().len();
+        commitment_batch.scale(power_of_u);
+        commitment_multi.add_msm(&commitment_batch);
+        eval_multi += power_of_u * eval_batch;
+        cost::record(|c| {
+            c.field_muls += terms + 1;
+            c.field_adds += 1;
+        });
+
+        // The power of u itself, and its product with z.
+        witness_with_aux.append_term(power_of_u * z, wi.to_curve());
+        witness.append_term(power_of_u, wi.to_curve());
+        cost::record(|c| c.field_muls += 2);
+    }
+
+    let verifier2 = SingleStrategy::new(params);
+    let mut msm_accumulator = verifier2.msm;
+
+    msm_accumulator.left.add_msm(&witness);
+
+    msm_accumulator.right.add_msm(&witness_with_aux);
+    msm_accumulator.right.add_msm(&commitment_multi);
+    let g0 = params.g[0].to_curve();
+    msm_accumulator.right.append_term(eval_multi, -g0);
+
+    // The inputs of the accumulation above, which `msm::AccumulatorCircuit`
+    // reproduces in a circuit.
+    let multiopen_input = MultiopenInput {
+        points: commitment_data
+            .iter()
+            .map(|(point, queries)| {
+                let queries = queries.iter().map(|query| (query.commitment.clone(), query.eval)).collect();
+                (*point, queries)
+            })
+            .collect(),
+        w: w_clone.clone(),
+        v,
+        u,
+        g0: params.g[0],
+    };
+
+    (msm_accumulator, multiopen_input)
+}
+
+/// Times the two ways to open an H commitment of `pieces` pieces in the final
+/// MSM of `explicit_verify`, here with 22 other terms: computing
+/// h_0 + xn h_1 + xn^2 h_2 + ... first, a scalar multiplication per piece, and
+/// adding it as a single term, or adding every piece with its power of xn.
+fn bench_h_commitment(pieces: &[usize], iterations: u32) {
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let others = (0..22)
+        .map(|_| (Scalar::random(&mut rng), G1Projective::random(&mut rng)))
+        .collect::<Vec<_>>();
+
+    println!("{:>6} {:>14} {:>14} {:>10}", "pieces", "folded (ms)", "deferred (ms)", "speedup");
+    for &d in pieces {
+        let split = (0..d).map(|_| G1Projective::random(&mut rng).to_affine()).collect::<Vec<_>>();
+        let (xn, power_of_v) = (Scalar::random(&mut rng), Scalar::random(&mut rng));
+        let msm = || {
+            let mut msm = MSMKZG::<Bls12>::new();
+            for (scalar, point) in &others {
+                msm.append_term(*scalar, *point);
+            }
+            msm
+        };
+
+        let start = Instant::now();
+        let mut folded = G1Projective::identity();
+        for _ in 0..iterations {
+            let h_commitment = split
+                .iter()
+                .rev()
+                .fold(G1Projective::identity(), |acc, commitment| acc * xn + commitment)
+                .to_affine();
+            let mut msm = msm();
+            msm.append_term(power_of_v, h_commitment.to_curve());
+            folded = msm.eval();
+        }
+        let folded_time = start.elapsed();
+
+        let start = Instant::now();
+        let mut deferred = G1Projective::identity();
+        for _ in 0..iterations {
+            let mut msm = msm();
+            for (commitment, power) in split.iter().zip(powers(xn)) {
+                msm.append_term(power_of_v * power, commitment.to_curve());
+            }
+            deferred = msm.eval();
+        }
+        let deferred_time = start.elapsed();
+        assert_eq!(folded, deferred);
+
+        let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0 / iterations as f64;
+        println!(
+            "{:>6} {:>14.3} {:>14.3} {:>9.2}x",
+            d,
+            ms(folded_time),
+            ms(deferred_time),
+            folded_time.as_secs_f64() / deferred_time.as_secs_f64()
+        );
+    }
+}
+
+/// Proves and verifies the `MyCircuit` example below, or runs the subcommand
+/// named by the first argument. The checks of the other circuits and verifiers
+/// are unit tests. The binary only calls this, so that other targets, like
+/// benchmarks, can use the circuits and verifiers of this crate.
+pub fn main() {
+    // `cargo run --release -- batch 1 2 4 8` only times the explicit verifier with
+    // one pairing check per proof against a `BatchVerifier`, for each number of proofs.
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some("batch") {
+        let ns = args[1..]
+            .iter()
+            .map(|n| n.parse().expect("expected a number of proofs"))
+            .collect::<Vec<usize>>();
+        batch::bench(if ns.is_empty() { &[1, 2, 4, 8, 16, 32] } else { &ns });
+        return;
+    }
+    // `cargo run --release -- h 2 4 8` times opening the H commitment with its
+    // pieces in the final MSM against computing it first, for each number of pieces.
+    if args.first().map(String::as_str) == Some("h") {
+        let pieces = args[1..]
+            .iter()
+            .map(|n| n.parse().expect("expected a number of pieces"))
+            .collect::<Vec<usize>>();
+        bench_h_commitment(if pieces.is_empty() { &[2, 3, 4, 8] } else { &pieces }, 100);
+        return;
+    }
+    // `cargo run --release -- cost` prints what the prover takes and the
+    // verifier spends on a few circuits with different layouts.
+    if args.first().map(String::as_str) == Some("cost") {
+        let constant = Scalar::from(7);
+        let (a, b) = (Scalar::from(2), Scalar::from(3));
+        cost::report("MyCircuit", 4, &[], || MyCircuit {
+            constant,
+            a: Value::known(a),
+            b: Value::known(b),
+            c: Value::known(constant * a.square() * b.square()),
+        });
+        let values = |values: &[u64]| values.iter().map(|v| Value::known(Scalar::from(*v))).collect::<Vec<_>>();
+        cost::report("MultisetCircuit", 5, &[], || MultisetCircuit {
+            a: values(&[1, 2, 3, 4]),
+            b: values(&[3, 1, 4, 2]),
+        });
+        cost::report("ShuffleCircuit", 5, &[], || ShuffleCircuit {
+            a: values(&[5, 8, 1, 3]),
+            b: values(&[1, 3, 5, 8]),
+        });
+        // The same chain of multiplications over more columns.
+        for (n_advice, n_fixed) in [(2, 1), (4, 1), (4, 3)] {
+            let name = format!("ChainCircuit {{ n_advice: {}, n_fixed: {} }}", n_advice, n_fixed);
+            cost::report(&name, 6, &[], || ChainCircuit {
+                n_muls: 16,
+                n_advice,
+                n_fixed,
+                with_lookup: false,
+                a: Value::known(Scalar::ONE),
+            });
+        }
+        // A Merkle path, whose root is the only instance value: it goes into
+        // the transcript, and its Lagrange polynomial shares the one inversion.
+        let poseidon = PoseidonParams::<Scalar>::new();
+        let siblings = (0..4).map(|i| Scalar::from(100 + i)).collect::<Vec<_>>();
+        let index_bits = [true, false, true, false];
+        let root = merkle_root(&poseidon, Scalar::from(42), &siblings, &index_bits);
+        cost::report("MerkleCircuit", 9, &[&[root]], || MerkleCircuit {
+            leaf: Value::known(Scalar::from(42)),
+            siblings: siblings.iter().map(|sibling| Value::known(*sibling)).collect(),
+            index_bits: index_bits
+                .iter()
+                .map(|bit| Value::known(if *bit { Scalar::ONE } else { Scalar::ZERO }))
+                .collect(),
+        });
+        return;
+    }
+    // `cargo run --release -- degree` shows where the degree, and with it the
+    // number of H pieces, of a few circuits comes from.
+    if args.first().map(String::as_str) == Some("degree") {
+        let constant = Scalar::from(7);
+        let (a, b) = (Scalar::from(2), Scalar::from(3));
+        degree::report("MyCircuit", 4, &MyCircuit {
+            constant,
+            a: Value::known(a),
+            b: Value::known(b),
+            c: Value::known(constant * a.square() * b.square()),
+        });
+        degree::report("RangeCheckCircuit", 6, &RangeCheckCircuit { value: Value::known(Scalar::from(0xbeef)) });
+        let values = |values: &[u64]| values.iter().map(|v| Value::known(Scalar::from(*v))).collect::<Vec<_>>();
+        degree::report("MultisetCircuit", 5, &MultisetCircuit {
+            a: values(&[1, 2, 3, 4]),
+            b: values(&[3, 1, 4, 2]),
+        });
+        degree::report("ShuffleCircuit", 5, &ShuffleCircuit {
+            a: values(&[5, 8, 1, 3]),
+            b: values(&[1, 3, 5, 8]),
+        });
+        return;
+    }
+    // `cargo run --release --features evm -- evm` runs the Solidity verifiers
+    // generated for `MyCircuit` and for `MerkleCircuit`, with its instance
+    // column, in an embedded EVM. It needs `solc` on the `PATH`.
+    if args.first().map(String::as_str) == Some("evm") {
+        #[cfg(feature = "evm")]
+        evm::run();
+        #[cfg(not(feature = "evm"))]
+        println!("The embedded EVM needs the evm feature");
+        return;
+    }
+    // `cargo run --release -- aggregate 2 26` proves `AggregatorCircuit` over 2
+    // proofs of `MyCircuit` at k = 26, and checks the accumulator it exposes.
+    if args.first().map(String::as_str) == Some("aggregate") {
+        let n = args.get(1).map_or(2, |n| n.parse().expect("expected a number of proofs"));
+        let k = args.get(2).map_or(26, |k| k.parse().expect("expected a k"));
+        aggregator::run(n, k);
+        return;
+    }
+
+    // The number of rows in our circuit cannot exceed 2^k. Since our example
+    // circuit is very small, we can pick a very small value here.
+    let k = 4;
+
+    // Prepare the private and public inputs to the circuit!
+    let constant = Scalar::from(7);
+    let a = Scalar::from(2);
+    let b = Scalar::from(3);
+    let c = constant * a.square() * b.square();
+
+    // Instantiate the circuit with the private inputs.
+    let circuit = MyCircuit {
+        constant,
+        a: Value::known(a),
+        b: Value::known(b),
+        c: Value::known(c),
+    };
+    
+    let seed = [0u8; 32];  // Choose a fixed seed for testing
+    let mut rng: StdRng = SeedableRng::from_seed(seed);
+
+    // Given the correct public input, our circuit will verify.
+    let params: ParamsKZG<Bls12> = ParamsKZG::setup(k, &mut rng);
+    let pk = keygen(&params, &circuit);
+
+    let proof = prove::<ProverGWC<_>, _>(&params, &pk, &[circuit], &[&[]], rng);
+
+    let verifier = SingleStrategy::new(&params);
+    let mut transcript_verifier = Blake2bRead::<_, _, Challenge255<G1Affine>>::init(proof.as_slice());
+    
+    verify_proof::<_, VerifierGWC<Bls12>, _, _, _>(
+        &params,
+        &pk.get_vk(),
+        verifier,
+        &[&[]],
+        &mut transcript_verifier
+    ).expect("Verification failed");
+
+    // The same proof with our own verifier, `explicit_verify`, which works for
+    // any circuit it reads from the key. Everything that only depends on the
+    // key is computed once, for all proofs.
+    let pvk = PreparedVerifyingKey::new(pk.get_vk());
+    let (msm_accumulator, _) = explicit_verify(&params, &pvk, &proof, &[&[]]);
+
+    let final_verify = msm_accumulator.check();
+
+    println!("Final pairing check: {:?}", final_verify);
+
+    println!("Passed");
+}
+
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr as Bn256Scalar};
+
+    use super::*;
+
+    #[test]
+    fn several_instances() {
+        // Several instances of `MyCircuit` in one proof share the vanishing
+        // argument and the multiopen stage, so they are still opened at three
+        // points with a single pairing check. Only the commitments and
+        // evaluations of every instance are added to the proof.
+        let (params, pk, _) = prove_and_verify(4, my_circuit(2, 3), &[]);
+        let circuits = [(2, 3), (4, 5), (6, 7)].map(|(a, b)| my_circuit(a, b));
+        let proof = prove::<ProverGWC<_>, _>(&params, &pk, &circuits, &[&[], &[], &[]], StdRng::from_seed([2u8; 32]));
+        verify_proof::<_, VerifierGWC<Bls12>, _, _, _>(
+            &params,
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            &[&[], &[], &[]],
+            &mut Blake2bRead::<_, _, Challenge255<G1Affine>>::init(proof.as_slice()),
+        )
+        .expect("Verification failed");
+        let pvk = PreparedVerifyingKey::new(pk.get_vk());
+        let (accumulator, input) = explicit_verify(&params, &pvk, &proof, &[&[], &[], &[]]);
+        assert_eq!(input.points.len(), 3);
+        assert!(accumulator.check());
+    }
+
+    #[test]
+    fn bn254() {
+        // The explicit verifier is generic over the pairing engine, so the same
+        // `MyCircuit` verifies on BN254, which has precompiles on EVM chains. As
+        // for BLS12-381, its accumulator must pass the pairing check and match
+        // the native accumulation, and a tampered accumulator must not pass.
+        let params: ParamsKZG<Bn256> = ParamsKZG::setup(4, StdRng::from_seed([5u8; 32]));
+        let vk = keygen_vk(&params, &my_circuit::<Bn256Scalar>(2, 3)).expect("keygen_vk should not fail");
+        let pk = keygen_pk(&params, vk, &my_circuit::<Bn256Scalar>(2, 3)).expect("keygen_pk should not fail");
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof::<KZGCommitmentScheme<Bn256>, ProverGWC<Bn256>, _, _, _, _>(
+            &params,
+            &pk,
+            &[my_circuit(2, 3)],
+            &[&[]],
+            StdRng::from_seed([6u8; 32]),
+            &mut transcript,
+        )
+        .expect("Proof generation failed");
+        let proof = transcript.finalize();
+        verify_proof::<_, VerifierGWC<Bn256>, _, _, _>(
+            &params,
+            pk.get_vk(),
+            SingleStrategy::new(&params),
+            &[&[]],
+            &mut Blake2bRead::<_, _, Challenge255<_>>::init(proof.as_slice()),
+        )
+        .expect("Verification failed");
+        let pvk = PreparedVerifyingKey::new(pk.get_vk());
+        let (accumulator, input) = explicit_verify(&params, &pvk, &proof, &[&[]]);
+        let (left, right) = input.accumulate();
+        assert_eq!(left, accumulator.left.eval().to_affine());
+        assert_eq!(right, accumulator.right.eval().to_affine());
+        assert!(accumulator.check());
+        let (mut tampered, _) = explicit_verify(&params, &pvk, &proof, &[&[]]);
+        tampered.right.append_term(Bn256Scalar::ONE, params.g[0].to_curve());
+        assert!(!tampered.check());
+    }
+}
@@ -0,0 +1,146 @@
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{Circuit, Column, ConstraintSystem, Error, Instance};
+
+use crate::conditional::ConditionalInstructions;
+use crate::poseidon::{PoseidonChip, PoseidonConfig, PoseidonInstructions, PoseidonParams};
+use crate::{FieldChip, FieldConfig, NumericInstructions};
+
+/// Computes the root of the tree containing `leaf`, where `siblings[i]` is the
+/// sibling at level `i` (counted from the leaves) and `index_bits[i]` is set
+/// when the current node is the right child at that level.
+pub fn merkle_root<F: PrimeField>(
+    params: &PoseidonParams<F>,
+    leaf: F,
+    siblings: &[F],
+    index_bits: &[bool],
+) -> F {
+    siblings
+        .iter()
+        .zip(index_bits.iter())
+        .fold(leaf, |node, (sibling, is_right)| {
+            if *is_right {
+                params.hash(&[*sibling, node])
+            } else {
+                params.hash(&[node, *sibling])
+            }
+        })
+}
+
+/// Proves that a private leaf is in a Poseidon Merkle tree of depth
+/// `siblings.len()`, whose root is the first row of the instance column.
+#[derive(Default)]
+pub struct MerkleCircuit<F: PrimeField> {
+    pub leaf: Value<F>,
+    pub siblings: Vec<Value<F>>,
+    /// Position of the leaf, least significant bit (the lowest level) first.
+    pub index_bits: Vec<Value<F>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MerkleConfig<F: PrimeField> {
+    field: FieldConfig,
+    poseidon: PoseidonConfig<F>,
+    instance: Column<Instance>,
+}
+
+impl<F: PrimeField> Circuit<F> for MerkleCircuit<F> {
+    type Config = MerkleConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            leaf: Value::unknown(),
+            siblings: vec![Value::unknown(); self.siblings.len()],
+            index_bits: vec![Value::unknown(); self.index_bits.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        // Both chips share the same advice columns.
+        let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+        let round_constants = [meta.fixed_column(), meta.fixed_column(), meta.fixed_column()];
+        let constant = meta.fixed_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        let field = FieldChip::configure(meta, [advice[0], advice[1]], constant);
+        MerkleConfig {
+            field: FieldChip::configure_conditional(meta, field, advice[2]),
+            poseidon: PoseidonChip::configure(meta, advice, round_constants),
+            instance,
+        }
+    }
+
+    fn synthesize(&self, config: Self::Config, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let field_chip = FieldChip::<F>::construct(config.field);
+        let poseidon_chip = PoseidonChip::<F>::construct(config.poseidon);
+
+        let mut node = field_chip.load_private(layouter.namespace(|| "load leaf"), self.leaf)?;
+
+        for (level, (sibling, is_right)) in self.siblings.iter().zip(self.index_bits.iter()).enumerate() {
+            let mut layouter = layouter.namespace(|| format!("level {}", level));
+
+            let sibling = field_chip.load_private(layouter.namespace(|| "load sibling"), *sibling)?;
+            let is_right = field_chip.load_private(layouter.namespace(|| "load index bit"), *is_right)?;
+
+            // The select gates behind `cond_swap` also constrain the index bit to
+            // be boolean.
+            let (left, right) =
+                field_chip.cond_swap(layouter.namespace(|| "swap"), is_right, node, sibling)?;
+            node = poseidon_chip.hash(layouter.namespace(|| "hash"), &[left, right])?;
+        }
+
+        layouter.constrain_instance(node.0.cell(), config.instance, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+    use halo2_proofs::halo2curves::ff::Field;
+
+    use super::*;
+    use crate::{cost, explicit_verify, prove_and_verify, PreparedVerifyingKey};
+
+    #[test]
+    fn membership() {
+        // Leaf 42 at position 0b0101 in a tree of depth 4, with the root as the
+        // only public input.
+        let poseidon = PoseidonParams::<Scalar>::new();
+        let leaf = Scalar::from(42);
+        let siblings = (0..4).map(|i| Scalar::from(100 + i)).collect::<Vec<_>>();
+        let index_bits = [true, false, true, false];
+        let root = merkle_root(&poseidon, leaf, &siblings, &index_bits);
+        let merkle_circuit = MerkleCircuit {
+            leaf: Value::known(leaf),
+            siblings: siblings.iter().map(|sibling| Value::known(*sibling)).collect(),
+            index_bits: index_bits
+                .iter()
+                .map(|bit| Value::known(if *bit { Scalar::ONE } else { Scalar::ZERO }))
+                .collect(),
+        };
+        let prover = MockProver::run(9, &merkle_circuit, vec![vec![root]]).unwrap();
+        prover.assert_satisfied();
+        let prover = MockProver::run(9, &merkle_circuit, vec![vec![leaf]]).unwrap();
+        assert!(prover.verify().is_err());
+        let (params, pk, proof) = prove_and_verify(9, merkle_circuit, &[&[root]]);
+
+        // The explicit verifier evaluates the instance column itself, from the
+        // root that went into the transcript. Another root changes every
+        // challenge, so the proof no longer verifies.
+        let pvk = PreparedVerifyingKey::new(pk.get_vk());
+        let (accumulator, _) = explicit_verify(&params, &pvk, &proof, &[&[&[root]]]);
+        assert!(accumulator.check());
+        let (accumulator, _) = explicit_verify(&params, &pvk, &proof, &[&[&[leaf]]]);
+        assert!(!accumulator.check());
+
+        // The Lagrange polynomial of the root's row shares the inversion of the
+        // last, blinding and first rows and of x^n - 1.
+        let total = cost::explicit_verify_cost(&params, &pk, &proof, &[&[root]]).total();
+        assert_eq!(total.inversions, 1);
+        assert_eq!(total.inversions_saved, pk.get_vk().cs.blinding_factors() + 2);
+    }
+}
@@ -0,0 +1,731 @@
+use halo2_proofs::arithmetic::{powers, CurveAffine};
+use halo2_proofs::circuit::{Chip, Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bls12_381::{Fq, G1Affine, G1Projective, Scalar};
+use halo2_proofs::halo2curves::ff::{Field, PrimeField};
+use halo2_proofs::halo2curves::group::{Curve, Group};
+use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Instance};
+
+use crate::bits::BitInstructions;
+use crate::conditional::ConditionalInstructions;
+use crate::foreign_field::{self, Element, ForeignFieldChip, ForeignFieldConfig, ForeignFieldInstructions};
+use crate::{FieldChip, FieldConfig, NumericInstructions, Number};
+
+/// The inputs of the last stage of the explicit verifier in `explicit_verify`, after the
+/// multiopen challenges `v` and `u` are squeezed. Only BLS12-381 inputs can be
+/// checked by `AccumulatorCircuit`.
+#[derive(Clone, Debug)]
+pub struct MultiopenInput<C: CurveAffine = G1Affine> {
+    /// Every opening point with its queries, as `(commitment, eval)` pairs, in
+    /// the order they are grouped in. A commitment is a linear combination of
+    /// points, a missing factor being one: the H commitment is not computed, but
+    /// given by its pieces with powers of `x^n`.
+    pub points: Vec<(C::Scalar, Vec<(Vec<(Option<C::Scalar>, C)>, C::Scalar)>)>,
+    /// The opening proofs, one per point.
+    pub w: Vec<C>,
+    pub v: C::Scalar,
+    pub u: C::Scalar,
+    /// The first point of the SRS.
+    pub g0: C,
+}
+
+impl<C: CurveAffine> MultiopenInput<C> {
+    /// Returns the accumulator `(left, right)` for which the proof is valid iff
+    /// `e(left, [s] g2) = e(right, g2)`. This is the same accumulation as
+    /// `commitment_multi`, `witness` and `witness_with_aux` in `explicit_verify`:
+    ///
+    /// - `left = sum u^i w_i`
+    /// - `right = sum u^i z_i w_i + sum u^i v^j C_ij - (sum u^i v^j e_ij) g0`
+    ///
+    /// where every `C_ij` is the sum of its terms.
+    pub fn accumulate(&self) -> (C, C) {
+        let mut left = C::CurveExt::identity();
+        let mut right = C::CurveExt::identity();
+        let mut eval_multi = C::Scalar::ZERO;
+
+        for (((z, queries), w), power_of_u) in self.points.iter().zip(self.w.iter()).zip(powers(self.u)) {
+            for ((commitment, eval), power_of_v) in queries.iter().zip(powers(self.v)) {
+                for (factor, point) in commitment {
+                    right += *point * (power_of_u * power_of_v * factor.unwrap_or(C::Scalar::ONE));
+                }
+                eval_multi += power_of_u * power_of_v * eval;
+            }
+            left += *w * power_of_u;
+            right += *w * (power_of_u * z);
+        }
+        right -= self.g0 * eval_multi;
+
+        (left.to_affine(), right.to_affine())
+    }
+}
+
+/// The limbs of both coordinates of a point, as exposed by `MsmChip::constrain_instance`.
+pub fn point_instance(p: &G1Affine) -> Vec<Scalar> {
+    let coordinates = p.coordinates().unwrap();
+    let mut limbs = foreign_field::limbs::<Scalar, Fq>(coordinates.x());
+    limbs.extend(foreign_field::limbs::<Scalar, Fq>(coordinates.y()));
+    limbs
+}
+
+/// The absolute value of the BLS12-381 parameter u = -0xd201000000010000.
+const U_ABS: u64 = 0xd201_0000_0001_0000;
+
+/// The cube root of unity beta in the base field for which (x, y) -> (beta x, y)
+/// is multiplication by -u^2 on the prime order subgroup. Of the two roots
+/// (-1 +- sqrt(-3)) / 2, the other one is multiplication by u^2 - 1.
+fn endomorphism_beta() -> Fq {
+    let sqrt = (-Fq::from(3)).sqrt().unwrap();
+    let half = Fq::from(2).invert().unwrap();
+    let g = G1Projective::generator();
+    let x = *g.to_affine().coordinates().unwrap().x();
+    let minus_u2_x = *(-(g * Scalar::from(U_ABS).square())).to_affine().coordinates().unwrap().x();
+    [(sqrt - Fq::ONE) * half, (-sqrt - Fq::ONE) * half]
+        .into_iter()
+        .find(|beta| x * beta == minus_u2_x)
+        .unwrap()
+}
+
+/// The starting point of every MSM. Incomplete addition cannot handle the
+/// identity, so we start from a point that has no known relation to the inputs
+/// and subtract its multiple at the end.
+fn aux_point() -> G1Affine {
+    (G1Projective::generator() * Scalar::from_u128(u128::from_le_bytes(*b"halo2-test msm!!"))).to_affine()
+}
+
+/// A BLS12-381 G1 point assigned in the circuit, in affine coordinates over the
+/// foreign base field. The identity cannot be represented.
+#[derive(Clone)]
+pub struct G1Point {
+    pub x: Element<Scalar>,
+    pub y: Element<Scalar>,
+}
+
+pub trait MsmInstructions: Chip<Scalar> {
+    /// Variable representing a curve point.
+    type Point;
+    /// Variable representing a scalar.
+    type Num;
+
+    /// Loads the table used by the foreign field arithmetic.
+    fn load_table(&self, layouter: impl Layouter<Scalar>) -> Result<(), Error>;
+
+    /// Loads a point as a private input, constraining it to be on the curve and
+    /// in the prime order subgroup.
+    fn witness_point(&self, layouter: impl Layouter<Scalar>, p: Value<G1Affine>) -> Result<Self::Point, Error>;
+
+    /// Loads a point as a fixed constant.
+    fn constant_point(&self, layouter: impl Layouter<Scalar>, p: G1Affine) -> Result<Self::Point, Error>;
+
+    /// Returns `p + q`, for points with distinct x coordinates.
+    fn add(&self, layouter: impl Layouter<Scalar>, p: &Self::Point, q: &Self::Point) -> Result<Self::Point, Error>;
+
+    /// Returns `[2] p`.
+    fn double(&self, layouter: impl Layouter<Scalar>, p: &Self::Point) -> Result<Self::Point, Error>;
+
+    /// Returns `sum [k_j] P_j`, for scalars of at most `num_bits` bits.
+    fn msm(
+        &self,
+        layouter: impl Layouter<Scalar>,
+        terms: &[(Self::Num, Self::Point)],
+        num_bits: usize,
+    ) -> Result<Self::Point, Error>;
+}
+
+/// A chip for BLS12-381 G1 arithmetic in a circuit over the BLS12-381 scalar
+/// field. Coordinates use `ForeignFieldChip`, while scalars are native and are
+/// decomposed, and coordinates chosen, with the bit and conditional
+/// instructions of `FieldChip`.
+///
+/// Additions use the incomplete affine formulas, which fail when both inputs
+/// have the same x coordinate. In an MSM with honestly generated inputs (such as
+/// commitments from a proof) this only happens with negligible probability.
+pub struct MsmChip {
+    config: MsmConfig,
+}
+
+#[derive(Clone, Debug)]
+pub struct MsmConfig {
+    field: FieldConfig,
+    foreign: ForeignFieldConfig,
+}
+
+impl MsmChip {
+    pub fn construct(config: <Self as Chip<Scalar>>::Config) -> Self {
+        Self { config }
+    }
+
+    /// Configures the chip. All sub-chips share the same advice columns.
+    pub fn configure(
+        meta: &mut ConstraintSystem<Scalar>,
+        advice: [Column<Advice>; 3],
+        constant: Column<Fixed>,
+        modulus: Column<Fixed>,
+    ) -> <Self as Chip<Scalar>>::Config {
+        let field = FieldChip::configure(meta, [advice[0], advice[1]], constant);
+        let field = FieldChip::configure_bits(meta, field, advice[2], modulus);
+        MsmConfig {
+            field: FieldChip::configure_conditional(meta, field, advice[2]),
+            foreign: ForeignFieldChip::<Scalar, Fq>::configure(meta, advice, constant),
+        }
+    }
+
+    pub fn field(&self) -> FieldChip<Scalar> {
+        FieldChip::construct(self.config.field.clone())
+    }
+
+    pub fn foreign(&self) -> ForeignFieldChip<Scalar, Fq> {
+        ForeignFieldChip::construct(self.config.foreign.clone())
+    }
+
+    /// Constrains the limbs of `p.x` and `p.y` to `2 * NUM_LIMBS` consecutive
+    /// rows of `instance`, starting at `row`. The coordinates are constrained to
+    /// be reduced first, so the limbs are the unique ones of `point_instance`.
+    pub fn constrain_instance(
+        &self,
+        mut layouter: impl Layouter<Scalar>,
+        p: &G1Point,
+        instance: Column<Instance>,
+        row: usize,
+    ) -> Result<(), Error> {
+        let foreign = self.foreign();
+        foreign.assert_canonical(layouter.namespace(|| "canonical x"), &p.x)?;
+        foreign.assert_canonical(layouter.namespace(|| "canonical y"), &p.y)?;
+        for (i, limb) in p.x.limbs.iter().chain(p.y.limbs.iter()).enumerate() {
+            layouter.constrain_instance(limb.0.cell(), instance, row + i)?;
+        }
+        Ok(())
+    }
+
+    /// Loads a point as a private input, constraining it to be on the curve
+    /// but not to be in the prime order subgroup.
+    pub fn witness_on_curve(&self, mut layouter: impl Layouter<Scalar>, p: Value<G1Affine>) -> Result<G1Point, Error> {
+        let foreign = self.foreign();
+
+        let coordinates = p.map(|p| {
+            let coordinates = p.coordinates().unwrap();
+            (*coordinates.x(), *coordinates.y())
+        });
+        let x = foreign.load_private(layouter.namespace(|| "x"), coordinates.map(|(x, _)| x))?;
+        let y = foreign.load_private(layouter.namespace(|| "y"), coordinates.map(|(_, y)| y))?;
+
+        // y^2 = x^3 + b
+        let y2 = foreign.mul(layouter.namespace(|| "y^2"), &y, &y)?;
+        let x2 = foreign.mul(layouter.namespace(|| "x^2"), &x, &x)?;
+        let x3 = foreign.mul(layouter.namespace(|| "x^3"), &x2, &x)?;
+        let b = foreign.load_constant(layouter.namespace(|| "b"), G1Affine::b())?;
+        let rhs = foreign.add(layouter.namespace(|| "x^3 + b"), &x3, &b)?;
+        foreign.assert_equal(layouter.namespace(|| "on curve"), &y2, &rhs)?;
+
+        Ok(G1Point { x, y })
+    }
+
+    fn select_element(
+        &self,
+        mut layouter: impl Layouter<Scalar>,
+        cond: &Number<Scalar>,
+        a: &Element<Scalar>,
+        b: &Element<Scalar>,
+    ) -> Result<Element<Scalar>, Error> {
+        let field = self.field();
+
+        let mut limbs = Vec::with_capacity(a.limbs.len());
+        for (i, (a, b)) in a.limbs.iter().zip(b.limbs.iter()).enumerate() {
+            let limb = field.select(layouter.namespace(|| format!("limb {}", i)), cond.clone(), a.clone(), b.clone())?;
+            limbs.push(limb);
+        }
+        let native = field.select(layouter.namespace(|| "native"), cond.clone(), a.native.clone(), b.native.clone())?;
+        let integer = cond
+            .0
+            .value()
+            .zip(a.integer.as_ref().zip(b.integer.as_ref()))
+            .map(|(cond, (a, b))| if cond.is_zero_vartime() { b.clone() } else { a.clone() });
+
+        Ok(Element { limbs, native, integer })
+    }
+
+    fn select(
+        &self,
+        mut layouter: impl Layouter<Scalar>,
+        cond: &Number<Scalar>,
+        p: &G1Point,
+        q: &G1Point,
+    ) -> Result<G1Point, Error> {
+        Ok(G1Point {
+            x: self.select_element(layouter.namespace(|| "x"), cond, &p.x, &q.x)?,
+            y: self.select_element(layouter.namespace(|| "y"), cond, &p.y, &q.y)?,
+        })
+    }
+
+    /// Given the slope `lambda` of the line through `p` and `q` (or the tangent
+    /// at `p`), returns the third intersection with the curve reflected, i.e.
+    /// `x_r = lambda^2 - x_p - x_q` and `y_r = lambda * (x_p - x_r) - y_p`.
+    fn finish_add(
+        &self,
+        mut layouter: impl Layouter<Scalar>,
+        lambda: &Element<Scalar>,
+        p: &G1Point,
+        x_q: &Element<Scalar>,
+    ) -> Result<G1Point, Error> {
+        let foreign = self.foreign();
+
+        let lambda2 = foreign.mul(layouter.namespace(|| "lambda^2"), lambda, lambda)?;
+        let x = foreign.sub(layouter.namespace(|| "lambda^2 - x_p"), &lambda2, &p.x)?;
+        let x = foreign.sub(layouter.namespace(|| "x_r"), &x, x_q)?;
+        let dx = foreign.sub(layouter.namespace(|| "x_p - x_r"), &p.x, &x)?;
+        let y = foreign.mul(layouter.namespace(|| "lambda * (x_p - x_r)"), lambda, &dx)?;
+        let y = foreign.sub(layouter.namespace(|| "y_r"), &y, &p.y)?;
+
+        Ok(G1Point { x, y })
+    }
+}
+
+impl Chip<Scalar> for MsmChip {
+    type Config = MsmConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl MsmInstructions for MsmChip {
+    type Point = G1Point;
+    type Num = Number<Scalar>;
+
+    fn load_table(&self, layouter: impl Layouter<Scalar>) -> Result<(), Error> {
+        self.foreign().load_table(layouter)
+    }
+
+    fn witness_point(&self, mut layouter: impl Layouter<Scalar>, p: Value<G1Affine>) -> Result<Self::Point, Error> {
+        let foreign = self.foreign();
+        let p = self.witness_on_curve(layouter.namespace(|| "on curve"), p)?;
+
+        // The curve has a cofactor, so we also check that p is in the prime
+        // order subgroup, with the endomorphism (x, y) -> (beta x, y) of
+        // eprint 2021/1130: it is multiplication by -u^2 on the subgroup, and
+        // only there. [|u|] is applied twice by double and add over its
+        // constant bits. The incomplete formulas have no valid witness in their
+        // exceptional cases, so they never give a wrong multiple either.
+        let mut s = p.clone();
+        for step in 0..2 {
+            let base = s.clone();
+            for i in (0..63).rev() {
+                let mut layouter = layouter.namespace(|| format!("[u] step {} bit {}", step, i));
+                s = self.double(layouter.namespace(|| "double"), &s)?;
+                if (U_ABS >> i) & 1 == 1 {
+                    s = self.add(layouter.namespace(|| "add"), &s, &base)?;
+                }
+            }
+        }
+        let beta = foreign.load_constant(layouter.namespace(|| "beta"), endomorphism_beta())?;
+        let beta_x = foreign.mul(layouter.namespace(|| "beta x"), &beta, &p.x)?;
+        foreign.assert_equal(layouter.namespace(|| "x of -[u^2] p"), &s.x, &beta_x)?;
+        let sum_y = foreign.add(layouter.namespace(|| "y + y of [u^2] p"), &s.y, &p.y)?;
+        let zero = foreign.load_constant(layouter.namespace(|| "zero"), Fq::ZERO)?;
+        foreign.assert_equal(layouter.namespace(|| "y of -[u^2] p"), &sum_y, &zero)?;
+
+        Ok(p)
+    }
+
+    fn constant_point(&self, mut layouter: impl Layouter<Scalar>, p: G1Affine) -> Result<Self::Point, Error> {
+        let foreign = self.foreign();
+        let coordinates = p.coordinates().unwrap();
+
+        Ok(G1Point {
+            x: foreign.load_constant(layouter.namespace(|| "x"), *coordinates.x())?,
+            y: foreign.load_constant(layouter.namespace(|| "y"), *coordinates.y())?,
+        })
+    }
+
+    fn add(&self, mut layouter: impl Layouter<Scalar>, p: &Self::Point, q: &Self::Point) -> Result<Self::Point, Error> {
+        let foreign = self.foreign();
+
+        // lambda = (y_q - y_p) / (x_q - x_p), where the inverse also constrains
+        // x_p != x_q.
+        let dy = foreign.sub(layouter.namespace(|| "y_q - y_p"), &q.y, &p.y)?;
+        let dx = foreign.sub(layouter.namespace(|| "x_q - x_p"), &q.x, &p.x)?;
+        let dx_inv = foreign.inverse(layouter.namespace(|| "1 / (x_q - x_p)"), &dx)?;
+        let lambda = foreign.mul(layouter.namespace(|| "lambda"), &dy, &dx_inv)?;
+
+        self.finish_add(layouter.namespace(|| "finish"), &lambda, p, &q.x)
+    }
+
+    fn double(&self, mut layouter: impl Layouter<Scalar>, p: &Self::Point) -> Result<Self::Point, Error> {
+        let foreign = self.foreign();
+
+        // lambda = 3 x^2 / 2 y, since a = 0.
+        let x2 = foreign.mul(layouter.namespace(|| "x^2"), &p.x, &p.x)?;
+        let two_x2 = foreign.add(layouter.namespace(|| "2 x^2"), &x2, &x2)?;
+        let three_x2 = foreign.add(layouter.namespace(|| "3 x^2"), &two_x2, &x2)?;
+        let two_y = foreign.add(layouter.namespace(|| "2 y"), &p.y, &p.y)?;
+        let two_y_inv = foreign.inverse(layouter.namespace(|| "1 / 2 y"), &two_y)?;
+        let lambda = foreign.mul(layouter.namespace(|| "lambda"), &three_x2, &two_y_inv)?;
+
+        self.finish_add(layouter.namespace(|| "finish"), &lambda, p, &p.x)
+    }
+
+    fn msm(
+        &self,
+        mut layouter: impl Layouter<Scalar>,
+        terms: &[(Self::Num, Self::Point)],
+        num_bits: usize,
+    ) -> Result<Self::Point, Error> {
+        let field = self.field();
+        let bits = terms
+            .iter()
+            .enumerate()
+            .map(|(j, (k, _))| field.to_bits(layouter.namespace(|| format!("bits of scalar {}", j)), k.clone(), num_bits))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Double and add from the most significant bit down, sharing the
+        // doublings between all terms. Starting from `aux`, the accumulator ends
+        // up at [2^num_bits] aux + sum [k_j] P_j.
+        let aux = aux_point();
+        let mut acc = self.constant_point(layouter.namespace(|| "aux"), aux)?;
+        for i in (0..num_bits).rev() {
+            let mut layouter = layouter.namespace(|| format!("bit {}", i));
+            acc = self.double(layouter.namespace(|| "double"), &acc)?;
+            for (j, (_, p)) in terms.iter().enumerate() {
+                let sum = self.add(layouter.namespace(|| format!("add term {}", j)), &acc, p)?;
+                acc = self.select(layouter.namespace(|| format!("select term {}", j)), &bits[j][i], &sum, &acc)?;
+            }
+        }
+
+        let offset = (0..num_bits).fold(G1Projective::from(aux), |acc, _| acc.double());
+        let correction = self.constant_point(layouter.namespace(|| "-[2^n] aux"), (-offset).to_affine())?;
+        self.add(layouter.namespace(|| "remove aux"), &acc, &correction)
+    }
+}
+
+/// Proves `sum [k_j] P_j = R` for private scalars of `num_bits` bits and private
+/// points, where the limbs of `R` are the instance.
+pub struct MsmCircuit {
+    pub terms: Vec<(Value<Scalar>, Value<G1Affine>)>,
+    pub num_bits: usize,
+}
+
+impl Circuit<Scalar> for MsmCircuit {
+    type Config = (MsmConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        MsmCircuit {
+            terms: vec![(Value::unknown(), Value::unknown()); self.terms.len()],
+            num_bits: self.num_bits,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+        let modulus = meta.fixed_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (MsmChip::configure(meta, advice, constant, modulus), instance)
+    }
+
+    fn synthesize(&self, (config, instance): Self::Config, mut layouter: impl Layouter<Scalar>) -> Result<(), Error> {
+        let chip = MsmChip::construct(config);
+        let field = chip.field();
+        chip.load_table(layouter.namespace(|| "load table"))?;
+
+        let mut terms = Vec::with_capacity(self.terms.len());
+        for (j, (k, p)) in self.terms.iter().enumerate() {
+            let k = field.load_private(layouter.namespace(|| format!("load k_{}", j)), *k)?;
+            let p = chip.witness_point(layouter.namespace(|| format!("load P_{}", j)), *p)?;
+            terms.push((k, p));
+        }
+        let r = chip.msm(layouter.namespace(|| "msm"), &terms, self.num_bits)?;
+
+        chip.constrain_instance(layouter.namespace(|| "expose R"), &r, instance, 0)
+    }
+}
+
+/// A commitment as the terms of a linear combination of points, a missing factor
+/// being one, as in `MultiopenInput`.
+pub type Commitment = Vec<(Option<Value<Scalar>>, Value<G1Affine>)>;
+
+/// Reproduces the last stage of the explicit verifier in `explicit_verify` in a circuit,
+/// exposing the KZG accumulator `(left, right)` as the limbs of `left.x`,
+/// `left.y`, `right.x` and `right.y`, in that order. The final pairing check
+/// `e(left, [s] g2) = e(right, g2)` is deferred to whoever consumes the
+/// instance, e.g. an outer recursive circuit.
+///
+/// All commitments, evaluations and challenges are private here. `g0` depends
+/// on the SRS and is fixed at keygen time.
+pub struct AccumulatorCircuit {
+    /// As in `MultiopenInput`, with private values.
+    pub points: Vec<(Value<Scalar>, Vec<(Commitment, Value<Scalar>)>)>,
+    pub w: Vec<Value<G1Affine>>,
+    pub v: Value<Scalar>,
+    pub u: Value<Scalar>,
+    pub g0: G1Affine,
+    /// The width of the scalars of both MSMs, full for the inputs of a proof.
+    /// Made up inputs with small challenges, openings and evaluations can do
+    /// with less, which keeps the circuit small enough for the `MockProver`.
+    pub num_bits: usize,
+}
+
+impl AccumulatorCircuit {
+    pub fn new(input: &MultiopenInput) -> Self {
+        AccumulatorCircuit {
+            points: input
+                .points
+                .iter()
+                .map(|(z, queries)| {
+                    let queries = queries
+                        .iter()
+                        .map(|(commitment, eval)| {
+                            let commitment = commitment
+                                .iter()
+                                .map(|(factor, point)| (factor.map(Value::known), Value::known(*point)))
+                                .collect();
+                            (commitment, Value::known(*eval))
+                        })
+                        .collect();
+                    (Value::known(*z), queries)
+                })
+                .collect(),
+            w: input.w.iter().map(|w| Value::known(*w)).collect(),
+            v: Value::known(input.v),
+            u: Value::known(input.u),
+            g0: input.g0,
+            num_bits: Scalar::NUM_BITS as usize,
+        }
+    }
+
+    /// The instance for an accumulator computed with `MultiopenInput::accumulate`.
+    pub fn instance((left, right): &(G1Affine, G1Affine)) -> Vec<Scalar> {
+        let mut instance = point_instance(left);
+        instance.extend(point_instance(right));
+        instance
+    }
+}
+
+impl Circuit<Scalar> for AccumulatorCircuit {
+    type Config = (MsmConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        AccumulatorCircuit {
+            points: self
+                .points
+                .iter()
+                .map(|(_, queries)| {
+                    let queries = queries
+                        .iter()
+                        .map(|(commitment, _)| {
+                            let commitment = commitment
+                                .iter()
+                                .map(|(factor, _)| (factor.map(|_| Value::unknown()), Value::unknown()))
+                                .collect();
+                            (commitment, Value::unknown())
+                        })
+                        .collect();
+                    (Value::unknown(), queries)
+                })
+                .collect(),
+            w: vec![Value::unknown(); self.w.len()],
+            v: Value::unknown(),
+            u: Value::unknown(),
+            g0: self.g0,
+            num_bits: self.num_bits,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+        let modulus = meta.fixed_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (MsmChip::configure(meta, advice, constant, modulus), instance)
+    }
+
+    fn synthesize(&self, (config, instance): Self::Config, mut layouter: impl Layouter<Scalar>) -> Result<(), Error> {
+        let chip = MsmChip::construct(config);
+        let field = chip.field();
+        chip.load_table(layouter.namespace(|| "load table"))?;
+
+        let v = field.load_private(layouter.namespace(|| "load v"), self.v)?;
+        let u = field.load_private(layouter.namespace(|| "load u"), self.u)?;
+        let one = field.load_constant(layouter.namespace(|| "load 1"), Scalar::ONE)?;
+
+        let mut witness = Vec::new();
+        let mut commitment_multi = Vec::new();
+        let mut evals = Vec::new();
+
+        let mut power_of_u = one.clone();
+        for (i, ((z, queries), w)) in self.points.iter().zip(self.w.iter()).enumerate() {
+            let mut layouter = layouter.namespace(|| format!("point {}", i));
+            let z = field.load_private(layouter.namespace(|| "load z"), *z)?;
+            let w = chip.witness_point(layouter.namespace(|| "load w"), *w)?;
+
+            let mut power_of_v = one.clone();
+            for (j, (commitment, eval)) in queries.iter().enumerate() {
+                let mut layouter = layouter.namespace(|| format!("query {}", j));
+                let eval = field.load_private(layouter.namespace(|| "load eval"), *eval)?;
+                let scalar = field.mul(layouter.namespace(|| "u^i v^j"), power_of_u.clone(), power_of_v.clone())?;
+
+                // Every term of the commitment, e.g. every piece of H, goes into
+                // the MSM on its own, with its factor.
+                for (t, (factor, point)) in commitment.iter().enumerate() {
+                    let point = chip.witness_point(layouter.namespace(|| format!("load term {}", t)), *point)?;
+                    let scalar = match factor {
+                        Some(factor) => {
+                            let factor = field.load_private(layouter.namespace(|| format!("load factor {}", t)), *factor)?;
+                            field.mul(layouter.namespace(|| format!("u^i v^j factor {}", t)), scalar.clone(), factor)?
+                        }
+                        None => scalar.clone(),
+                    };
+                    commitment_multi.push((scalar, point));
+                }
+                evals.push((scalar, eval));
+                power_of_v = field.mul(layouter.namespace(|| "next v^j"), power_of_v, v.clone())?;
+            }
+
+            // `witness` and `witness_with_aux` in `explicit_verify`. The latter ends up in the
+            // right-hand side just like `commitment_multi`.
+            let power_of_u_z = field.mul(layouter.namespace(|| "u^i z"), power_of_u.clone(), z)?;
+            witness.push((power_of_u.clone(), w.clone()));
+            commitment_multi.push((power_of_u_z, w));
+            power_of_u = field.mul(layouter.namespace(|| "next u^i"), power_of_u, u.clone())?;
+        }
+
+        let eval_multi = chip.foreign().sum_of_products(
+            layouter.namespace(|| "eval_multi"),
+            &evals.iter().map(|(scalar, eval)| (scalar, eval)).collect::<Vec<_>>(),
+        )?;
+        let minus_g0 = chip.constant_point(layouter.namespace(|| "-g0"), -self.g0)?;
+        commitment_multi.push((eval_multi, minus_g0));
+
+        let left = chip.msm(layouter.namespace(|| "left"), &witness, self.num_bits)?;
+        let right = chip.msm(layouter.namespace(|| "right"), &commitment_multi, self.num_bits)?;
+
+        chip.constrain_instance(layouter.namespace(|| "expose left"), &left, instance, 0)?;
+        chip.constrain_instance(layouter.namespace(|| "expose right"), &right, instance, 2 * foreign_field::NUM_LIMBS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+
+    use super::*;
+    use crate::{explicit_verify, my_circuit, prove_and_verify, PreparedVerifyingKey};
+
+    #[test]
+    fn msm() {
+        // Full width scalars work the same way but need 255 doublings, so we
+        // keep the scalars to 4 bits here. Most of the rows go to the subgroup
+        // checks of the two points.
+        let g = G1Projective::generator();
+        let points = [(g * Scalar::from(3)).to_affine(), (g * Scalar::from(7)).to_affine()];
+        let scalars = [Scalar::from(5), Scalar::from(11)];
+        let expected = (points[0] * scalars[0] + points[1] * scalars[1]).to_affine();
+        let msm_circuit = MsmCircuit {
+            terms: scalars.iter().zip(points.iter()).map(|(k, p)| (Value::known(*k), Value::known(*p))).collect(),
+            num_bits: 4,
+        };
+        let prover = MockProver::run(20, &msm_circuit, vec![point_instance(&expected)]).unwrap();
+        prover.assert_satisfied();
+        let prover = MockProver::run(20, &msm_circuit, vec![point_instance(&points[0])]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn point_outside_subgroup() {
+        // Most points on the curve are outside the prime order subgroup, as the
+        // cofactor is much larger than one. They satisfy the curve equation,
+        // but not the endomorphism check.
+        let p = (1u64..)
+            .find_map(|x| {
+                let x = Fq::from(x);
+                let y = Option::<Fq>::from((x.square() * x + G1Affine::b()).sqrt())?;
+                Option::<G1Affine>::from(G1Affine::from_xy(x, y))
+            })
+            .unwrap();
+        let minus_u2_p = (-(p * Scalar::from(U_ABS).square())).to_affine();
+        assert_ne!(*minus_u2_p.coordinates().unwrap().x(), *p.coordinates().unwrap().x() * endomorphism_beta());
+
+        let msm_circuit = MsmCircuit {
+            terms: vec![(Value::known(Scalar::ONE), Value::known(p))],
+            num_bits: 1,
+        };
+        let prover = MockProver::run(20, &msm_circuit, vec![point_instance(&p)]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn accumulator_of_small_input() {
+        // Made up inputs with small challenges, opening points and evaluations
+        // keep every scalar, and `eval_multi`, to 4 bits, and still go through
+        // the whole accumulation: two opening points, the first with two
+        // queries, one of them with a commitment of two terms like H. The limbs
+        // it exposes must be those of the native accumulator.
+        let g = G1Projective::generator();
+        let point = |k: u64| (g * Scalar::from(k)).to_affine();
+        let small_input = MultiopenInput {
+            points: vec![
+                (
+                    Scalar::from(3),
+                    vec![
+                        (vec![(None, point(11)), (Some(Scalar::from(4)), point(29))], Scalar::from(1)),
+                        (vec![(None, point(13))], Scalar::from(2)),
+                    ],
+                ),
+                (Scalar::from(5), vec![(vec![(None, point(17))], Scalar::from(1))]),
+            ],
+            w: vec![point(19), point(23)],
+            v: Scalar::from(2),
+            u: Scalar::from(3),
+            g0: point(1),
+        };
+        let (left, right) = small_input.accumulate();
+        let accumulator_circuit = AccumulatorCircuit {
+            num_bits: 4,
+            ..AccumulatorCircuit::new(&small_input)
+        };
+        let prover = MockProver::run(22, &accumulator_circuit, vec![AccumulatorCircuit::instance(&(left, right))]).unwrap();
+        prover.assert_satisfied();
+        let prover = MockProver::run(22, &accumulator_circuit, vec![AccumulatorCircuit::instance(&(right, left))]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    #[ignore = "full width MSMs need 2^23 rows, run with `cargo test --release -- --ignored`"]
+    fn accumulator_of_proof() {
+        // Full width scalars from the inputs of a real proof, cut down to the
+        // opening at x and its query of the H commitment, whose two pieces are
+        // folded in the circuit with x^n as the factor of the second one. The
+        // accumulation of any input matches the native one, valid proof or not.
+        let (params, pk, proof) = prove_and_verify(4, my_circuit(2, 3), &[]);
+        let (_, mut input) = explicit_verify(&params, &PreparedVerifyingKey::new(pk.get_vk()), &proof, &[&[]]);
+        let h_point = input.points.iter().position(|(_, queries)| queries.iter().any(|(c, _)| c.len() > 1)).unwrap();
+        let (z, queries) = input.points.swap_remove(h_point);
+        input.w = vec![input.w[h_point]];
+        input.points = vec![(z, queries.into_iter().filter(|(c, _)| c.len() > 1).collect())];
+
+        let accumulator_circuit = AccumulatorCircuit::new(&input);
+        let instance = AccumulatorCircuit::instance(&input.accumulate());
+        MockProver::run(23, &accumulator_circuit, vec![instance]).unwrap().assert_satisfied();
+    }
+
+    #[test]
+    fn native_accumulator_of_proof() {
+        // The native accumulation of the inputs of a real proof is the
+        // accumulator of the explicit verifier.
+        let (params, pk, proof) = prove_and_verify(4, my_circuit(2, 3), &[]);
+        let (accumulator, input) = explicit_verify(&params, &PreparedVerifyingKey::new(pk.get_vk()), &proof, &[&[]]);
+        let (left, right) = input.accumulate();
+        assert_eq!(left, accumulator.left.eval().to_affine());
+        assert_eq!(right, accumulator.right.eval().to_affine());
+    }
+}
@@ -0,0 +1,228 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::circuit::{Chip, Layouter, Region, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::Field;
+use halo2_proofs::plonk::{
+    Advice, Challenge, Circuit, Column, ConstraintSystem, Error, FirstPhase, SecondPhase, Selector,
+};
+use halo2_proofs::poly::Rotation;
+
+use crate::{FieldChip, FieldConfig, NumericInstructions, Number};
+
+pub trait MultisetInstructions<F: Field>: Chip<F> {
+    /// Variable representing a number.
+    type Num;
+
+    /// Constrains `b` to be a permutation of `a`.
+    fn assert_permutation(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &[Self::Num],
+        b: &[Self::Num],
+    ) -> Result<(), Error>;
+}
+
+/// A chip that checks that two lists hold the same values, with a running
+/// product over a random challenge. The product lives in a second phase advice
+/// column, as it can only be computed once the lists are committed to and the
+/// challenge is known.
+pub struct MultisetChip<F: Field> {
+    config: MultisetConfig,
+    _marker: PhantomData<F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MultisetConfig {
+    advice: [Column<Advice>; 2],
+    z: Column<Advice>,
+    gamma: Challenge,
+    s_product: Selector,
+}
+
+impl<F: Field> MultisetChip<F> {
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    /// `advice` must be first phase columns with equality enabled. The running
+    /// product gets its own second phase column.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 2],
+    ) -> <Self as Chip<F>>::Config {
+        let z = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(z);
+        let gamma = meta.challenge_usable_after(FirstPhase);
+        let s_product = meta.selector();
+
+        // | a0 | a1 | z       | s_product |
+        // |----|----|---------|-----------|
+        // | a  | b  | z       | s_product |
+        // |    |    | z_next  |           |
+        //
+        // With z_next * (gamma - b) = z * (gamma - a), z_0 = 1 and z_n = 1 we get
+        // \prod (gamma - a_i) = \prod (gamma - b_i). For a random gamma this only
+        // holds with negligible probability, unless the b_i permute the a_i.
+        meta.create_gate("running product", |meta| {
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let z_next = meta.query_advice(z, Rotation::next());
+            let z = meta.query_advice(z, Rotation::cur());
+            let gamma = meta.query_challenge(gamma);
+            let s_product = meta.query_selector(s_product);
+
+            vec![s_product * (z_next * (gamma.clone() - b) - z * (gamma - a))]
+        });
+
+        MultisetConfig {
+            advice,
+            z,
+            gamma,
+            s_product,
+        }
+    }
+}
+
+impl<F: Field> Chip<F> for MultisetChip<F> {
+    type Config = MultisetConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: Field> MultisetInstructions<F> for MultisetChip<F> {
+    type Num = Number<F>;
+
+    fn assert_permutation(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &[Self::Num],
+        b: &[Self::Num],
+    ) -> Result<(), Error> {
+        let config = self.config();
+        assert_eq!(a.len(), b.len());
+
+        // Unknown until the first phase is committed to.
+        let gamma = layouter.get_challenge(config.gamma);
+
+        layouter.assign_region(
+            || "running product",
+            |mut region: Region<'_, F>| {
+                let mut z = region.assign_advice(|| "z_0", config.z, 0, || Value::known(F::ONE))?;
+                region.constrain_constant(z.cell(), F::ONE)?;
+
+                for (row, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                    config.s_product.enable(&mut region, row)?;
+
+                    a.0.copy_advice(|| "a", &mut region, config.advice[0], row)?;
+                    b.0.copy_advice(|| "b", &mut region, config.advice[1], row)?;
+
+                    let value = z
+                        .value()
+                        .zip(gamma.zip(a.0.value().zip(b.0.value())))
+                        .map(|(z, (gamma, (a, b)))| {
+                            *z * (gamma - a) * (gamma - b).invert().unwrap_or(F::ZERO)
+                        });
+                    z = region.assign_advice(|| "z", config.z, row + 1, || value)?;
+                }
+
+                region.constrain_constant(z.cell(), F::ONE)
+            },
+        )
+    }
+}
+
+/// Proves that `b` is a permutation of `a`.
+pub struct MultisetCircuit<F: Field> {
+    pub a: Vec<Value<F>>,
+    pub b: Vec<Value<F>>,
+}
+
+impl<F: Field> Circuit<F> for MultisetCircuit<F> {
+    type Config = (FieldConfig, MultisetConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            a: vec![Value::unknown(); self.a.len()],
+            b: vec![Value::unknown(); self.b.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+
+        let field_config = FieldChip::configure(meta, advice, constant);
+        let multiset_config = MultisetChip::configure(meta, advice);
+
+        (field_config, multiset_config)
+    }
+
+    fn synthesize(
+        &self,
+        (field_config, multiset_config): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<F>::construct(field_config);
+        let multiset_chip = MultisetChip::<F>::construct(multiset_config);
+
+        let a = self
+            .a
+            .iter()
+            .map(|a| field_chip.load_private(layouter.namespace(|| "load a"), *a))
+            .collect::<Result<Vec<_>, _>>()?;
+        let b = self
+            .b
+            .iter()
+            .map(|b| field_chip.load_private(layouter.namespace(|| "load b"), *b))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        multiset_chip.assert_permutation(layouter.namespace(|| "a ~ b"), &a, &b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+
+    use super::*;
+    use crate::{explicit_verify, prove_and_verify, PreparedVerifyingKey};
+
+    fn multiset(a: &[u64], b: &[u64]) -> MultisetCircuit<Scalar> {
+        MultisetCircuit {
+            a: a.iter().map(|a| Value::known(Scalar::from(*a))).collect(),
+            b: b.iter().map(|b| Value::known(Scalar::from(*b))).collect(),
+        }
+    }
+
+    #[test]
+    fn multiset_equality() {
+        let prover = MockProver::run(5, &multiset(&[1, 2, 3, 4], &[3, 1, 4, 2]), vec![]).unwrap();
+        prover.assert_satisfied();
+        let prover = MockProver::run(5, &multiset(&[1, 2, 3, 4], &[3, 1, 4, 4]), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn explicit_verify_second_phase() {
+        // The running product is in a second phase advice column, computed with
+        // a challenge that is sampled after the first phase. The explicit
+        // verifier must read the commitments and squeeze the challenge in that
+        // same order.
+        let (params, pk, proof) = prove_and_verify(5, multiset(&[1, 2, 3, 4], &[3, 1, 4, 2]), &[]);
+        let (accumulator, _) = explicit_verify(&params, &PreparedVerifyingKey::new(pk.get_vk()), &proof, &[&[]]);
+        assert!(accumulator.check());
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2_proofs::halo2curves::ff::{BatchInvert, Field, PrimeField, WithSmallOrderMulGroup};
+use halo2_proofs::plonk::VerifyingKey;
+use halo2_proofs::poly::Rotation;
+
+use crate::cost;
+
+/// A verifying key with everything the explicit verifier needs from the domain
+/// computed once, so that a proof only costs the work that depends on `x`:
+/// `x^n` is `k` squarings, every rotation of `x` one multiplication and every
+/// Lagrange polynomial at `x` two, next to a single inversion shared with the
+/// vanishing argument and the instance rows.
+pub struct PreparedVerifyingKey<'a, C: CurveAffine> {
+    pub vk: &'a VerifyingKey<C>,
+    /// omega^i for every rotation i of a query, and of the Lagrange polynomials.
+    omega_powers: BTreeMap<i32, C::Scalar>,
+    /// DELTA^j for the j-th column of the permutation argument.
+    pub deltas: Vec<C::Scalar>,
+    /// (omega^i, omega^i / n) for i in -(blinding_factors + 1)..=0, the barycentric
+    /// constants of l_i(x) = (x^n - 1) * omega^i / n / (x - omega^i). This is
+    /// where 1/n goes, so it is not inverted per proof either.
+    pub lagrange: Vec<(C::Scalar, C::Scalar)>,
+    /// 1 / n, for the Lagrange polynomials of the instance rows, which depend
+    /// on how many instance values a proof has.
+    n_inv: C::Scalar,
+}
+
+impl<'a, C: CurveAffine> PreparedVerifyingKey<'a, C>
+where
+    C::Scalar: WithSmallOrderMulGroup<3>,
+{
+    pub fn new(vk: &'a VerifyingKey<C>) -> Self {
+        let cs = &vk.cs;
+        let domain = vk.get_domain();
+        let last = -((cs.blinding_factors() + 1) as i32);
+
+        let rotations = cs
+            .advice_queries()
+            .iter()
+            .map(|(_, at)| at.0)
+            .chain(cs.fixed_queries().iter().map(|(_, at)| at.0))
+            .chain(cs.instance_queries().iter().map(|(_, at)| -at.0))
+            .chain([0, 1])
+            .chain(last..=0);
+        let omega_powers = rotations
+            .map(|rotation| (rotation, domain.rotate_omega(C::Scalar::ONE, Rotation(rotation))))
+            .collect::<BTreeMap<_, _>>();
+
+        let deltas = std::iter::successors(Some(C::Scalar::ONE), |delta| Some(*delta * C::Scalar::DELTA))
+            .take(cs.permutation().get_columns().len())
+            .collect();
+
+        let n_inv = C::Scalar::from(1u64 << domain.k()).invert().unwrap();
+        let lagrange = (last..=0)
+            .map(|rotation| {
+                let omega = omega_powers[&rotation];
+                (omega, omega * n_inv)
+            })
+            .collect();
+
+        PreparedVerifyingKey {
+            vk,
+            omega_powers,
+            deltas,
+            lagrange,
+            n_inv,
+        }
+    }
+
+    /// x^n, with n = 2^k.
+    pub fn xn(&self, x: C::Scalar) -> C::Scalar {
+        let k = self.vk.get_domain().k();
+        (0..k).fold(x, |xn, _| {
+            cost::record(|c| c.field_muls += 1);
+            xn.square()
+        })
+    }
+
+    /// omega^at, for a rotation of a query.
+    pub fn omega_power(&self, at: Rotation) -> C::Scalar {
+        self.omega_powers[&at.0]
+    }
+
+    /// omega^at * x, for a rotation of a query.
+    pub fn rotate(&self, x: C::Scalar, at: Rotation) -> C::Scalar {
+        cost::record(|c| c.field_muls += 1);
+        x * self.omega_power(at)
+    }
+
+    /// l_i(x) for i in -(blinding_factors + 1)..=0, 1 / (x^n - 1) for the
+    /// vanishing argument, and the evaluations of the instance columns (see
+    /// `instance_evals`). All the denominators, x - omega^i for the blinding and
+    /// the instance rows and x^n - 1, are inverted together in one Montgomery
+    /// batch inversion: one inversion and three multiplications per
+    /// denominator, instead of an inversion each.
+    pub fn l_evals_and_vanishing_inv(
+        &self,
+        x: C::Scalar,
+        xn: C::Scalar,
+        instances: &[&[&[C::Scalar]]],
+    ) -> (Vec<C::Scalar>, C::Scalar, Vec<Vec<C::Scalar>>) {
+        let common = xn - C::Scalar::ONE;
+        let (max_rotation, instance_omegas) = self.instance_rows(instances);
+        let mut denominators = self
+            .lagrange
+            .iter()
+            .map(|(omega, _)| omega)
+            .chain(instance_omegas.iter())
+            .map(|omega| x - omega)
+            .chain(Some(common))
+            .collect::<Vec<_>>();
+        cost::record(|c| c.field_adds += denominators.len());
+        denominators.iter_mut().batch_invert();
+        // What `batch_invert` does inside halo2.
+        let m = denominators.len();
+        cost::record(|c| {
+            c.inversions += 1;
+            c.inversions_saved += m - 1;
+            c.field_muls += 3 * (m - 1);
+        });
+
+        let vanishing_inv = denominators.pop().unwrap();
+        let instance_denominators = denominators.split_off(self.lagrange.len());
+        let l_evals = self
+            .lagrange
+            .iter()
+            .zip(denominators.iter())
+            .map(|((_, constant), denominator)| {
+                cost::record(|c| c.field_muls += 2);
+                common * constant * denominator
+            })
+            .collect();
+        let instance_evals =
+            self.instance_evals(common, max_rotation, &instance_omegas, &instance_denominators, instances);
+        (l_evals, vanishing_inv, instance_evals)
+    }
+
+    /// The largest rotation of an instance query, at least 0, and omega^i for
+    /// the rows i in -max_rotation..max_len - min_rotation that the instance
+    /// values are at, by stepping from the first one, which is in the prepared
+    /// key. No rows without instance queries.
+    fn instance_rows(&self, instances: &[&[&[C::Scalar]]]) -> (i32, Vec<C::Scalar>) {
+        let queries = self.vk.cs.instance_queries();
+        if queries.is_empty() {
+            return (0, vec![]);
+        }
+        let min_rotation = queries.iter().map(|(_, at)| at.0).min().unwrap().min(0);
+        let max_rotation = queries.iter().map(|(_, at)| at.0).max().unwrap().max(0);
+        let max_len = instances
+            .iter()
+            .flat_map(|instance| instance.iter().map(|column| column.len()))
+            .max()
+            .unwrap_or(0);
+
+        let omega = self.vk.get_domain().get_omega();
+        let rows = max_len + (max_rotation - min_rotation) as usize;
+        let omegas = std::iter::successors(Some(self.omega_powers[&-max_rotation]), |power| {
+            cost::record(|c| c.field_muls += 1);
+            Some(*power * omega)
+        })
+        .take(rows)
+        .collect();
+        (max_rotation, omegas)
+    }
+
+    /// The evaluations at x of the instance columns, one for every instance
+    /// query, for every instance of the circuit. With KZG the instance columns
+    /// are not committed to, so the verifier evaluates them itself from the
+    /// values: the column at rotation r is sum_j value_j l_{j - r}(x), with the
+    /// inverted denominators 1 / (x - omega^i) of the rows from `instance_rows`.
+    fn instance_evals(
+        &self,
+        common: C::Scalar,
+        max_rotation: i32,
+        omegas: &[C::Scalar],
+        denominators: &[C::Scalar],
+        instances: &[&[&[C::Scalar]]],
+    ) -> Vec<Vec<C::Scalar>> {
+        let queries = self.vk.cs.instance_queries();
+        if queries.is_empty() {
+            return vec![vec![]; instances.len()];
+        }
+
+        let common = common * self.n_inv;
+        cost::record(|c| c.field_muls += 1);
+        let l_evals = omegas
+            .iter()
+            .zip(denominators.iter())
+            .map(|(omega, denominator)| {
+                cost::record(|c| c.field_muls += 2);
+                common * omega * denominator
+            })
+            .collect::<Vec<_>>();
+
+        instances
+            .iter()
+            .map(|instance| {
+                queries
+                    .iter()
+                    .map(|(column, at)| {
+                        let values = instance[column.index()];
+                        let offset = (max_rotation - at.0) as usize;
+                        cost::record(|c| {
+                            c.field_muls += values.len();
+                            c.field_adds += values.len();
+                        });
+                        values
+                            .iter()
+                            .zip(&l_evals[offset..])
+                            .fold(C::Scalar::ZERO, |acc, (value, l)| acc + *value * l)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,292 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::circuit::{Chip, Layouter, Region, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{
+    Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Selector, TableColumn,
+};
+use halo2_proofs::poly::Rotation;
+
+use crate::{FieldChip, FieldConfig, NumericInstructions, Number};
+
+/// Number of bits in a single limb. The lookup table holds every value in
+/// `0..2^LIMB_BITS`, so it needs `2^LIMB_BITS` usable rows.
+pub const LIMB_BITS: usize = 4;
+
+/// Number of limbs checked by `RangeCheckCircuit`, i.e. it proves 16-bit bounds.
+pub const NUM_LIMBS: usize = 4;
+
+pub trait RangeCheckInstructions<F: PrimeField>: Chip<F> {
+    /// Variable representing a number.
+    type Num;
+
+    /// Loads the fixed table `0..2^limb_bits` that every limb is looked up in.
+    fn load_table(&self, layouter: impl Layouter<F>) -> Result<(), Error>;
+
+    /// Constrains `a < 2^(num_limbs * limb_bits)` by decomposing it into
+    /// `num_limbs` limbs, most significant first, and returns those limbs.
+    fn range_check(
+        &self,
+        layouter: impl Layouter<F>,
+        a: Self::Num,
+        num_limbs: usize,
+    ) -> Result<Vec<Self::Num>, Error>;
+}
+
+/// A chip that proves bounds on a `Number<F>` by splitting it into k-bit limbs
+/// and looking every limb up in a fixed table.
+pub struct RangeCheckChip<F: PrimeField> {
+    config: RangeCheckConfig,
+    _marker: PhantomData<F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RangeCheckConfig {
+    /// The running sum lives in `advice[0]` and the limbs in `advice[1]`. These
+    /// can be the same columns that `FieldChip` uses.
+    advice: [Column<Advice>; 2],
+
+    /// Every limb must be an entry of this table.
+    table: TableColumn,
+
+    /// Enables the running sum gate `acc' = acc * 2^k + limb'`.
+    s_decompose: Selector,
+
+    /// Enables the lookup on the limb column. Lookups need a complex selector,
+    /// since the selector ends up inside the lookup expression.
+    s_lookup: Selector,
+
+    limb_bits: usize,
+}
+
+impl<F: PrimeField> RangeCheckChip<F> {
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 2],
+        limb_bits: usize,
+    ) -> <Self as Chip<F>>::Config {
+        for column in &advice {
+            meta.enable_equality(*column);
+        }
+        let table = meta.lookup_table_column();
+        let s_decompose = meta.selector();
+        let s_lookup = meta.complex_selector();
+
+        // We recompose the limbs with Horner's rule, starting from the most
+        // significant limb:
+        //
+        // | a0   | a1     | s_decompose | s_lookup |
+        // |------|--------|-------------|----------|
+        // | 0    |        | 1           | 0        |
+        // | acc1 | limb_1 | 1           | 1        |
+        // | acc2 | limb_2 | 1           | 1        |
+        // | ...  | ...    | ...         | ...      |
+        // | accn | limb_n | 0           | 1        |
+        //
+        // The first accumulator is the constant zero, and the last accumulator is
+        // constrained to equal the value being range checked.
+        meta.create_gate("range check decompose", |meta| {
+            let acc = meta.query_advice(advice[0], Rotation::cur());
+            let acc_next = meta.query_advice(advice[0], Rotation::next());
+            let limb_next = meta.query_advice(advice[1], Rotation::next());
+            let s_decompose = meta.query_selector(s_decompose);
+
+            let radix = F::from(1u64 << limb_bits);
+            vec![s_decompose * (acc_next - (acc * radix + limb_next))]
+        });
+
+        // When s_lookup = 0 the input becomes 0, which is why the table must
+        // contain 0 as well.
+        meta.lookup("range check limb", |meta| {
+            let limb = meta.query_advice(advice[1], Rotation::cur());
+            let s_lookup = meta.query_selector(s_lookup);
+
+            vec![(s_lookup * limb, table)]
+        });
+
+        RangeCheckConfig {
+            advice,
+            table,
+            s_decompose,
+            s_lookup,
+            limb_bits,
+        }
+    }
+}
+
+impl<F: PrimeField> Chip<F> for RangeCheckChip<F> {
+    type Config = RangeCheckConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+/// Splits the little-endian representation of `value` into `num_limbs` limbs of
+/// `limb_bits` bits each, most significant limb first. Any bits above the
+/// requested range are dropped, which makes the recomposition fail for values
+/// that are out of range.
+fn decompose<F: PrimeField>(value: F, limb_bits: usize, num_limbs: usize) -> Vec<F> {
+    let repr = value.to_repr();
+    let bit = |i: usize| -> u64 {
+        repr.as_ref()
+            .get(i / 8)
+            .map(|byte| ((byte >> (i % 8)) & 1) as u64)
+            .unwrap_or(0)
+    };
+
+    (0..num_limbs)
+        .rev()
+        .map(|limb| {
+            let limb = (0..limb_bits).fold(0u64, |acc, j| acc | (bit(limb * limb_bits + j) << j));
+            F::from(limb)
+        })
+        .collect()
+}
+
+impl<F: PrimeField> RangeCheckInstructions<F> for RangeCheckChip<F> {
+    type Num = Number<F>;
+
+    fn load_table(&self, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let config = self.config();
+
+        layouter.assign_table(
+            || "range check table",
+            |mut table| {
+                for value in 0..(1 << config.limb_bits) {
+                    table.assign_cell(
+                        || "table value",
+                        config.table,
+                        value,
+                        || Value::known(F::from(value as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn range_check(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: Self::Num,
+        num_limbs: usize,
+    ) -> Result<Vec<Self::Num>, Error> {
+        let config = self.config();
+        let radix = F::from(1u64 << config.limb_bits);
+
+        layouter.assign_region(
+            || "range check",
+            |mut region: Region<'_, F>| {
+                let limbs = a
+                    .0
+                    .value()
+                    .map(|a| decompose(*a, config.limb_bits, num_limbs))
+                    .transpose_vec(num_limbs);
+
+                let mut acc = region.assign_advice_from_constant(
+                    || "initial accumulator",
+                    config.advice[0],
+                    0,
+                    F::ZERO,
+                )?;
+
+                let mut assigned_limbs = Vec::with_capacity(num_limbs);
+                for (i, limb) in limbs.into_iter().enumerate() {
+                    let offset = i + 1;
+                    config.s_decompose.enable(&mut region, i)?;
+                    config.s_lookup.enable(&mut region, offset)?;
+
+                    let limb =
+                        region.assign_advice(|| "limb", config.advice[1], offset, || limb)?;
+                    let value = acc.value().copied() * Value::known(radix) + limb.value();
+                    acc = region.assign_advice(|| "accumulator", config.advice[0], offset, || value)?;
+
+                    assigned_limbs.push(Number(limb));
+                }
+
+                // The recomposed value must be the value we were asked to check.
+                region.constrain_equal(acc.cell(), a.0.cell())?;
+
+                Ok(assigned_limbs)
+            },
+        )
+    }
+}
+
+/// Proves that a private value fits in `NUM_LIMBS * LIMB_BITS` bits.
+#[derive(Default)]
+pub struct RangeCheckCircuit<F: PrimeField> {
+    pub value: Value<F>,
+}
+
+impl<F: PrimeField> Circuit<F> for RangeCheckCircuit<F> {
+    type Config = (FieldConfig, RangeCheckConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        // Both chips share the same two advice columns.
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let constant: Column<Fixed> = meta.fixed_column();
+
+        let field_config = FieldChip::configure(meta, advice, constant);
+        let range_config = RangeCheckChip::configure(meta, advice, LIMB_BITS);
+
+        (field_config, range_config)
+    }
+
+    fn synthesize(
+        &self,
+        (field_config, range_config): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<F>::construct(field_config);
+        let range_chip = RangeCheckChip::<F>::construct(range_config);
+
+        range_chip.load_table(layouter.namespace(|| "load table"))?;
+
+        let value = field_chip.load_private(layouter.namespace(|| "load value"), self.value)?;
+        range_chip.range_check(layouter.namespace(|| "range check value"), value, NUM_LIMBS)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+
+    use super::*;
+    use crate::prove_and_verify;
+
+    #[test]
+    fn range_check() {
+        // A 16 bit value passes, a 17 bit one does not. This is the lookup
+        // argument (and thus `theta`) the verifiers have to handle.
+        let range_circuit = RangeCheckCircuit { value: Value::known(Scalar::from(0xbeef)) };
+        let prover = MockProver::run(6, &range_circuit, vec![]).unwrap();
+        prover.assert_satisfied();
+        let out_of_range = RangeCheckCircuit { value: Value::known(Scalar::from(0x1_0000)) };
+        let prover = MockProver::run(6, &out_of_range, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+        prove_and_verify(6, range_circuit, &[]);
+    }
+}
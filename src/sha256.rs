@@ -0,0 +1,271 @@
+use std::cell::Cell;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{Circuit, Column, ConstraintSystem, Error, Instance};
+
+use crate::spread::{SpreadChip, SpreadConfig, Word, WordInstructions};
+
+pub const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+pub const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// Appends the `0x80` byte, zeros and the 64 bit big-endian bit length, so the
+/// result is a multiple of 64 bytes.
+pub fn pad(message: &[u8]) -> Vec<u8> {
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend((message.len() as u64 * 8).to_be_bytes());
+    padded
+}
+
+/// The native compression function.
+pub fn compress(state: &mut [u32; 8], block: &[u8]) {
+    let mut w = [0u32; 64];
+    for (i, chunk) in block.chunks(4).enumerate() {
+        w[i] = u32::from_be_bytes(chunk.try_into().unwrap());
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+/// Native SHA-256, returning the digest as eight big-endian words.
+pub fn sha256(message: &[u8]) -> [u32; 8] {
+    let mut state = IV;
+    for block in pad(message).chunks(64) {
+        compress(&mut state, block);
+    }
+    state
+}
+
+/// `rotr(w, r0) ^ rotr(w, r1) ^ rotr(w, r2)`, or with a shift by `r2` instead of
+/// the last rotation for the message schedule functions.
+fn sigma<F: PrimeField>(
+    chip: &SpreadChip<F>,
+    mut layouter: impl Layouter<F>,
+    w: &Word<F>,
+    [r0, r1, r2]: [usize; 3],
+    shift: bool,
+) -> Result<Word<F>, Error> {
+    let x = chip.rotr(layouter.namespace(|| "rotr 0"), w, r0)?;
+    let y = chip.rotr(layouter.namespace(|| "rotr 1"), w, r1)?;
+    let z = if shift {
+        chip.shr(layouter.namespace(|| "shr"), w, r2)?
+    } else {
+        chip.rotr(layouter.namespace(|| "rotr 2"), w, r2)?
+    };
+    chip.xor(layouter.namespace(|| "xor"), &[&x, &y, &z])
+}
+
+/// The compression function in the circuit, mirroring `compress`.
+pub fn compress_in_circuit<F: PrimeField>(
+    chip: &SpreadChip<F>,
+    mut layouter: impl Layouter<F>,
+    state: &[Word<F>; 8],
+    block: &[Word<F>; 16],
+) -> Result<[Word<F>; 8], Error> {
+    let mut w = block.to_vec();
+    for i in 16..64 {
+        let mut layouter = layouter.namespace(|| format!("schedule {}", i));
+        let s0 = sigma(chip, layouter.namespace(|| "sigma 0"), &w[i - 15], [7, 18, 3], true)?;
+        let s1 = sigma(chip, layouter.namespace(|| "sigma 1"), &w[i - 2], [17, 19, 10], true)?;
+        let wi = chip.add(layouter.namespace(|| "w"), &[&w[i - 16], &s0, &w[i - 7], &s1])?;
+        w.push(wi);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state.clone();
+    for (i, wi) in w.iter().enumerate() {
+        let mut layouter = layouter.namespace(|| format!("round {}", i));
+        let s1 = sigma(chip, layouter.namespace(|| "Sigma 1"), &e, [6, 11, 25], false)?;
+        let ch = chip.ch(layouter.namespace(|| "ch"), &e, &f, &g)?;
+        let k = chip.constant_word(layouter.namespace(|| "k"), K[i] as u64, 4)?;
+        let t1 = chip.add(layouter.namespace(|| "t1"), &[&h, &s1, &ch, &k, wi])?;
+        let s0 = sigma(chip, layouter.namespace(|| "Sigma 0"), &a, [2, 13, 22], false)?;
+        let maj = chip.maj(layouter.namespace(|| "maj"), &a, &b, &c)?;
+        let t2 = chip.add(layouter.namespace(|| "t2"), &[&s0, &maj])?;
+
+        h = g;
+        g = f;
+        f = e;
+        e = chip.add(layouter.namespace(|| "e"), &[&d, &t1])?;
+        d = c;
+        c = b;
+        b = a;
+        a = chip.add(layouter.namespace(|| "a"), &[&t1, &t2])?;
+    }
+
+    let mut out = Vec::with_capacity(8);
+    for (s, v) in state.iter().zip([a, b, c, d, e, f, g, h]) {
+        out.push(chip.add(layouter.namespace(|| "feed forward"), &[s, &v])?);
+    }
+    Ok(out.try_into().unwrap_or_else(|_| unreachable!()))
+}
+
+/// Proves knowledge of a message of a fixed length whose SHA-256 digest is the
+/// first eight rows of the instance column, one big-endian word per row.
+pub struct Sha256Circuit {
+    pub message: Vec<Value<u8>>,
+    /// Rows used by the spread chip in the last synthesis, excluding the table.
+    pub rows: Cell<usize>,
+}
+
+impl Sha256Circuit {
+    pub fn new(message: Vec<Value<u8>>) -> Self {
+        Sha256Circuit {
+            message,
+            rows: Cell::new(0),
+        }
+    }
+}
+
+impl<F: PrimeField> Circuit<F> for Sha256Circuit {
+    type Config = (SpreadConfig, Column<Instance>);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self::new(vec![Value::unknown(); self.message.len()])
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+
+        (SpreadChip::configure(meta, advice, constant), instance)
+    }
+
+    fn synthesize(
+        &self,
+        (spread_config, instance): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let chip = SpreadChip::<F>::construct(spread_config);
+        chip.load_table(layouter.namespace(|| "load table"))?;
+
+        // Only the length of the message is known at keygen time, so the padding
+        // bytes are known and constrained to be constants below.
+        let len = self.message.len();
+        let padding = pad(&vec![0u8; len]);
+        let padded = (0..padding.len())
+            .map(|i| self.message.get(i).copied().unwrap_or(Value::known(padding[i])))
+            .collect::<Vec<_>>();
+
+        let mut state = Vec::with_capacity(8);
+        for iv in IV {
+            state.push(chip.constant_word(layouter.namespace(|| "iv"), iv as u64, 4)?);
+        }
+        let mut state: [Word<F>; 8] = state.try_into().unwrap_or_else(|_| unreachable!());
+
+        for (b, block) in padded.chunks(64).enumerate() {
+            let mut layouter = layouter.namespace(|| format!("block {}", b));
+
+            let mut words = Vec::with_capacity(16);
+            for (j, bytes) in block.chunks(4).enumerate() {
+                let value = bytes.iter().fold(Value::known(0u64), |acc, byte| {
+                    acc.zip(*byte).map(|(acc, byte)| (acc << 8) | byte as u64)
+                });
+                let word = chip.load_word(layouter.namespace(|| "message word"), value, 4)?;
+
+                // Big-endian, so byte k of the chunk is byte 3 - k of the word.
+                for k in 0..4 {
+                    let position = 64 * b + 4 * j + k;
+                    if position >= len {
+                        let cell = word.bytes[3 - k].0.cell();
+                        layouter.assign_region(
+                            || "padding",
+                            |mut region| region.constrain_constant(cell, F::from(padding[position] as u64)),
+                        )?;
+                    }
+                }
+                words.push(word);
+            }
+            let words: [Word<F>; 16] = words.try_into().unwrap_or_else(|_| unreachable!());
+
+            state = compress_in_circuit(&chip, layouter.namespace(|| "compress"), &state, &words)?;
+        }
+
+        for (row, word) in state.iter().enumerate() {
+            layouter.constrain_instance(word.dense.0.cell(), instance, row)?;
+        }
+        self.rows.set(chip.rows());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+    use halo2_proofs::halo2curves::ff::Field;
+
+    use super::*;
+
+    #[test]
+    fn abc() {
+        // The test vector of FIPS 180-2, natively and in the circuit with the
+        // digest words as instances.
+        let digest = sha256(b"abc");
+        assert_eq!(
+            digest,
+            [0xba7816bf, 0x8f01cfea, 0x414140de, 0x5dae2223, 0xb00361a3, 0x96177a9c, 0xb410ff61, 0xf20015ad]
+        );
+        let message = b"abc".iter().map(|byte| Value::known(*byte)).collect::<Vec<_>>();
+        let instance = digest.iter().map(|word| Scalar::from(*word as u64)).collect::<Vec<_>>();
+        let prover = MockProver::run(16, &Sha256Circuit::new(message.clone()), vec![instance.clone()]).unwrap();
+        prover.assert_satisfied();
+        let mut wrong = instance;
+        wrong[0] += Scalar::ONE;
+        let prover = MockProver::run(16, &Sha256Circuit::new(message), vec![wrong]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::circuit::{Chip, Layouter, Region, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::ff::Field;
+use halo2_proofs::plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Selector};
+use halo2_proofs::poly::Rotation;
+
+use crate::{FieldChip, FieldConfig, NumericInstructions, Number};
+
+pub trait ShuffleInstructions<F: Field>: Chip<F> {
+    /// Variable representing a number.
+    type Num;
+
+    /// Constrains `b` to be a permutation of `a`. Together with a check that `b`
+    /// is ordered, this is how a circuit sorts a list.
+    fn assert_shuffle(
+        &self,
+        layouter: impl Layouter<F>,
+        a: &[Self::Num],
+        b: &[Self::Num],
+    ) -> Result<(), Error>;
+}
+
+/// A chip that checks that two lists hold the same values with halo2's shuffle
+/// argument. Unlike `multiset::MultisetChip`, the running product is handled by
+/// the proving system, so the chip needs no second phase or challenge itself.
+pub struct ShuffleChip<F: Field> {
+    config: ShuffleConfig,
+    _marker: PhantomData<F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ShuffleConfig {
+    advice: [Column<Advice>; 2],
+    s_shuffle: Selector,
+}
+
+impl<F: Field> ShuffleChip<F> {
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
+        Self {
+            config,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 2],
+    ) -> <Self as Chip<F>>::Config {
+        // Selectors in a shuffle can't be combined with other selectors.
+        let s_shuffle = meta.complex_selector();
+
+        // | a0 | a1 | s_shuffle |
+        // |----|----|-----------|
+        // | a  | b  | s_shuffle |
+        //
+        // The values of s_shuffle * a over all rows are a permutation of those of
+        // s_shuffle * b. Rows without the selector add a zero to both sides.
+        meta.shuffle("a ~ b", |meta| {
+            let a = meta.query_advice(advice[0], Rotation::cur());
+            let b = meta.query_advice(advice[1], Rotation::cur());
+            let s_shuffle = meta.query_selector(s_shuffle);
+
+            vec![(s_shuffle.clone() * a, s_shuffle * b)]
+        });
+
+        ShuffleConfig { advice, s_shuffle }
+    }
+}
+
+impl<F: Field> Chip<F> for ShuffleChip<F> {
+    type Config = ShuffleConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: Field> ShuffleInstructions<F> for ShuffleChip<F> {
+    type Num = Number<F>;
+
+    fn assert_shuffle(
+        &self,
+        mut layouter: impl Layouter<F>,
+        a: &[Self::Num],
+        b: &[Self::Num],
+    ) -> Result<(), Error> {
+        let config = self.config();
+        assert_eq!(a.len(), b.len());
+
+        layouter.assign_region(
+            || "shuffle",
+            |mut region: Region<'_, F>| {
+                for (row, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                    config.s_shuffle.enable(&mut region, row)?;
+
+                    a.0.copy_advice(|| "a", &mut region, config.advice[0], row)?;
+                    b.0.copy_advice(|| "b", &mut region, config.advice[1], row)?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Proves that `b` is a permutation of `a`.
+pub struct ShuffleCircuit<F: Field> {
+    pub a: Vec<Value<F>>,
+    pub b: Vec<Value<F>>,
+}
+
+impl<F: Field> Circuit<F> for ShuffleCircuit<F> {
+    type Config = (FieldConfig, ShuffleConfig);
+    type FloorPlanner = SimpleFloorPlanner;
+    type Params = ();
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            a: vec![Value::unknown(); self.a.len()],
+            b: vec![Value::unknown(); self.b.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let advice = [meta.advice_column(), meta.advice_column()];
+        let constant = meta.fixed_column();
+
+        let field_config = FieldChip::configure(meta, advice, constant);
+        let shuffle_config = ShuffleChip::configure(meta, advice);
+
+        (field_config, shuffle_config)
+    }
+
+    fn synthesize(
+        &self,
+        (field_config, shuffle_config): Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let field_chip = FieldChip::<F>::construct(field_config);
+        let shuffle_chip = ShuffleChip::<F>::construct(shuffle_config);
+
+        let a = self
+            .a
+            .iter()
+            .map(|a| field_chip.load_private(layouter.namespace(|| "load a"), *a))
+            .collect::<Result<Vec<_>, _>>()?;
+        let b = self
+            .b
+            .iter()
+            .map(|b| field_chip.load_private(layouter.namespace(|| "load b"), *b))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        shuffle_chip.assert_shuffle(layouter.namespace(|| "a ~ b"), &a, &b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bls12_381::Scalar;
+
+    use super::*;
+    use crate::{explicit_verify, prove_and_verify, PreparedVerifyingKey};
+
+    fn shuffle(a: &[u64], b: &[u64]) -> ShuffleCircuit<Scalar> {
+        ShuffleCircuit {
+            a: a.iter().map(|a| Value::known(Scalar::from(*a))).collect(),
+            b: b.iter().map(|b| Value::known(Scalar::from(*b))).collect(),
+        }
+    }
+
+    #[test]
+    fn permutation() {
+        let prover = MockProver::run(5, &shuffle(&[5, 8, 1, 3], &[1, 3, 5, 8]), vec![]).unwrap();
+        prover.assert_satisfied();
+        let prover = MockProver::run(5, &shuffle(&[5, 8, 1, 3], &[1, 3, 5, 5]), vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn explicit_verify_shuffle() {
+        // The product commitments and evaluations of the shuffle argument come
+        // after those of the permutation argument.
+        let (params, pk, proof) = prove_and_verify(5, shuffle(&[5, 8, 1, 3], &[1, 3, 5, 8]), &[]);
+        let (accumulator, _) = explicit_verify(&params, &PreparedVerifyingKey::new(pk.get_vk()), &proof, &[&[]]);
+        assert!(accumulator.check());
+    }
+}
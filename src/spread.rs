@@ -0,0 +1,607 @@
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+use halo2_proofs::circuit::{AssignedCell, Chip, Layouter, Region, Value};
+use halo2_proofs::halo2curves::ff::PrimeField;
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Selector, TableColumn};
+use halo2_proofs::poly::Rotation;
+
+use crate::Number;
+
+/// Interleaves the bits of a byte with zeros, e.g. `0b1011 -> 0b1000101`.
+///
+/// Adding spread values never carries between bit positions (as long as at
+/// most three values are added), so the even bits of the sum are the XOR of
+/// the inputs, and the odd bits are their AND (two inputs) or majority (three
+/// inputs).
+pub fn spread(byte: u64) -> u64 {
+    (0..8).fold(0, |acc, i| acc | (((byte >> i) & 1) << (2 * i)))
+}
+
+/// A word of `bytes.len()` bytes (4 for SHA-256, 8 for Blake2b).
+///
+/// Besides the dense value we keep every byte and its spread form, all of
+/// which are looked up in the spread table, so a word is always range checked.
+#[derive(Clone)]
+pub struct Word<F: PrimeField> {
+    pub dense: Number<F>,
+    /// The bytes, least significant first.
+    pub bytes: Vec<Number<F>>,
+    pub spreads: Vec<Number<F>>,
+    pub value: Value<u64>,
+}
+
+impl<F: PrimeField> Word<F> {
+    fn bits(&self) -> usize {
+        8 * self.bytes.len()
+    }
+}
+
+pub trait WordInstructions<F: PrimeField>: Chip<F> {
+    /// Variable representing a word.
+    type Word;
+
+    /// Loads the spread table.
+    fn load_table(&self, layouter: impl Layouter<F>) -> Result<(), Error>;
+
+    /// Loads a word of `n_bytes` bytes as a private input.
+    fn load_word(&self, layouter: impl Layouter<F>, value: Value<u64>, n_bytes: usize) -> Result<Self::Word, Error>;
+
+    /// Loads a word of `n_bytes` bytes as a fixed constant.
+    fn constant_word(&self, layouter: impl Layouter<F>, value: u64, n_bytes: usize) -> Result<Self::Word, Error>;
+
+    /// Bitwise XOR of two or three words.
+    fn xor(&self, layouter: impl Layouter<F>, words: &[&Self::Word]) -> Result<Self::Word, Error>;
+
+    /// `(a AND b) XOR (a AND c) XOR (b AND c)`.
+    fn maj(&self, layouter: impl Layouter<F>, a: &Self::Word, b: &Self::Word, c: &Self::Word) -> Result<Self::Word, Error>;
+
+    /// `(e AND f) XOR (NOT e AND g)`.
+    fn ch(&self, layouter: impl Layouter<F>, e: &Self::Word, f: &Self::Word, g: &Self::Word) -> Result<Self::Word, Error>;
+
+    /// Rotates right by `r` bits.
+    fn rotr(&self, layouter: impl Layouter<F>, word: &Self::Word, r: usize) -> Result<Self::Word, Error>;
+
+    /// Shifts right by `r` bits.
+    fn shr(&self, layouter: impl Layouter<F>, word: &Self::Word, r: usize) -> Result<Self::Word, Error>;
+
+    /// Adds words modulo `2^bits`.
+    fn add(&self, layouter: impl Layouter<F>, words: &[&Self::Word]) -> Result<Self::Word, Error>;
+}
+
+/// A chip for bitwise operations on 32 and 64 bit words, using a single lookup
+/// table that holds
+///
+/// - `(0, x, spread(x))` for every byte `x`, and
+/// - `(t, x, x mod 2^t)` for every byte `x` and `1 <= t < 8`, used to split
+///   bytes for rotations and shifts.
+///
+/// Every operation is expressed as one or more linear combinations
+/// `sum c_a * a + c_b * b = 0` over rows of the region, with the coefficients
+/// in fixed columns. All non-linearity comes from the table.
+pub struct SpreadChip<F: PrimeField> {
+    config: SpreadConfig,
+    rows: Cell<usize>,
+    _marker: PhantomData<F>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SpreadConfig {
+    a: Column<Advice>,
+    b: Column<Advice>,
+    acc: Column<Advice>,
+    tag: Column<Fixed>,
+    ca: Column<Fixed>,
+    cb: Column<Fixed>,
+    table: [TableColumn; 3],
+    q_acc: Selector,
+    q_lookup: Selector,
+}
+
+/// An operand of a linear combination row.
+enum Operand<F: PrimeField> {
+    Empty,
+    Copy(AssignedCell<F, F>),
+    Witness(Value<F>),
+    Constant(F),
+}
+
+/// One row of a linear combination: contributes `ca * a + cb * b`, and if `tag`
+/// is set, `(tag, a, b)` is looked up in the table.
+struct Row<F: PrimeField> {
+    a: Operand<F>,
+    ca: F,
+    b: Operand<F>,
+    cb: F,
+    tag: Option<u64>,
+}
+
+impl<F: PrimeField> Row<F> {
+    fn term(a: Operand<F>, ca: F) -> Self {
+        Row {
+            a,
+            ca,
+            b: Operand::Empty,
+            cb: F::ZERO,
+            tag: None,
+        }
+    }
+
+    fn lookup(tag: u64, a: Operand<F>, ca: F, b: Operand<F>, cb: F) -> Self {
+        Row {
+            a,
+            ca,
+            b,
+            cb,
+            tag: Some(tag),
+        }
+    }
+}
+
+fn pow2<F: PrimeField>(exp: usize) -> F {
+    F::from(2).pow_vartime([exp as u64])
+}
+
+fn mask(bits: usize) -> u64 {
+    if bits == 64 {
+        u64::MAX
+    } else {
+        (1 << bits) - 1
+    }
+}
+
+impl<F: PrimeField> SpreadChip<F> {
+    pub fn construct(config: <Self as Chip<F>>::Config) -> Self {
+        Self {
+            config,
+            rows: Cell::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        advice: [Column<Advice>; 3],
+        constant: Column<Fixed>,
+    ) -> <Self as Chip<F>>::Config {
+        meta.enable_constant(constant);
+        for column in &advice {
+            meta.enable_equality(*column);
+        }
+        let [a, b, acc] = advice;
+        let tag = meta.fixed_column();
+        let ca = meta.fixed_column();
+        let cb = meta.fixed_column();
+        let table = [
+            meta.lookup_table_column(),
+            meta.lookup_table_column(),
+            meta.lookup_table_column(),
+        ];
+        let q_acc = meta.selector();
+        let q_lookup = meta.complex_selector();
+
+        // | a   | b   | acc                      | ca   | cb   | tag |
+        // |-----|-----|--------------------------|------|------|-----|
+        // |     |     | 0                        |      |      |     |
+        // | a_1 | b_1 | acc_0 + ca_1*a_1+cb_1*b_1 | ca_1 | cb_1 | t_1 |
+        // | ... | ... | ...                      | ...  | ...  | ... |
+        // | a_n | b_n | 0                        | ca_n | cb_n | t_n |
+        //
+        // The last accumulator is constrained to be zero.
+        meta.create_gate("linear combination", |meta| {
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let acc_prev = meta.query_advice(acc, Rotation::prev());
+            let acc = meta.query_advice(acc, Rotation::cur());
+            let ca = meta.query_fixed(ca, Rotation::cur());
+            let cb = meta.query_fixed(cb, Rotation::cur());
+            let q_acc = meta.query_selector(q_acc);
+
+            vec![q_acc * (acc - acc_prev - ca * a - cb * b)]
+        });
+
+        // Disabled rows look up (0, 0, 0), which is the spread of the zero byte.
+        meta.lookup("spread table", |meta| {
+            let tag = meta.query_fixed(tag, Rotation::cur());
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let q_lookup = meta.query_selector(q_lookup);
+
+            vec![
+                (q_lookup.clone() * tag, table[0]),
+                (q_lookup.clone() * a, table[1]),
+                (q_lookup * b, table[2]),
+            ]
+        });
+
+        SpreadConfig {
+            a,
+            b,
+            acc,
+            tag,
+            ca,
+            cb,
+            table,
+            q_acc,
+            q_lookup,
+        }
+    }
+
+    /// Number of rows assigned by this chip so far, not counting the table.
+    pub fn rows(&self) -> usize {
+        self.rows.get()
+    }
+
+    fn assign_operand(
+        &self,
+        region: &mut Region<'_, F>,
+        column: Column<Advice>,
+        offset: usize,
+        operand: &Operand<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        match operand {
+            // Every cell queried by the gate must be assigned, so empty operands
+            // are assigned zero. Their coefficient is zero anyway.
+            Operand::Empty => region.assign_advice(|| "empty", column, offset, || Value::known(F::ZERO)),
+            Operand::Copy(cell) => cell.copy_advice(|| "copy", region, column, offset),
+            Operand::Witness(value) => region.assign_advice(|| "witness", column, offset, || *value),
+            Operand::Constant(value) => region.assign_advice_from_constant(|| "constant", column, offset, *value),
+        }
+    }
+
+    /// Assigns `rows` as one linear combination starting at `offset`, and returns
+    /// the assigned `a` and `b` cells of every row. `offset` is advanced past the
+    /// combination.
+    fn assign_combination(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        rows: &[Row<F>],
+    ) -> Result<Vec<(AssignedCell<F, F>, AssignedCell<F, F>)>, Error> {
+        let config = &self.config;
+
+        let mut acc = region.assign_advice_from_constant(|| "initial acc", config.acc, *offset, F::ZERO)?;
+        let mut cells = Vec::with_capacity(rows.len());
+        for row in rows {
+            *offset += 1;
+            config.q_acc.enable(region, *offset)?;
+            region.assign_fixed(|| "ca", config.ca, *offset, || Value::known(row.ca))?;
+            region.assign_fixed(|| "cb", config.cb, *offset, || Value::known(row.cb))?;
+            if let Some(tag) = row.tag {
+                config.q_lookup.enable(region, *offset)?;
+                region.assign_fixed(|| "tag", config.tag, *offset, || Value::known(F::from(tag)))?;
+            }
+
+            let a = self.assign_operand(region, config.a, *offset, &row.a)?;
+            let b = self.assign_operand(region, config.b, *offset, &row.b)?;
+            let value = acc.value().copied()
+                + a.value().map(|a| *a * row.ca)
+                + b.value().map(|b| *b * row.cb);
+            acc = region.assign_advice(|| "acc", config.acc, *offset, || value)?;
+
+            cells.push((a, b));
+        }
+        region.constrain_constant(acc.cell(), F::ZERO)?;
+        *offset += 1;
+
+        Ok(cells)
+    }
+
+    /// Assigns a region and adds its height to the row count.
+    fn assign_region<T>(
+        &self,
+        mut layouter: impl Layouter<F>,
+        name: &'static str,
+        mut assignment: impl FnMut(&mut Region<'_, F>, &mut usize) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut height = 0;
+        let result = layouter.assign_region(
+            || name,
+            |mut region| {
+                let mut offset = 0;
+                let result = assignment(&mut region, &mut offset)?;
+                height = offset;
+                Ok(result)
+            },
+        )?;
+        self.rows.set(self.rows.get() + height);
+        Ok(result)
+    }
+
+    /// Decomposes `dense` into `n_bytes` bytes, looking up every byte and its
+    /// spread in the table.
+    fn decompose(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        dense: Operand<F>,
+        value: Value<u64>,
+        n_bytes: usize,
+    ) -> Result<Word<F>, Error> {
+        let mut rows = vec![Row::term(dense, -F::ONE)];
+        for i in 0..n_bytes {
+            let byte = value.map(|value| (value >> (8 * i)) & 0xff);
+            rows.push(Row::lookup(
+                0,
+                Operand::Witness(byte.map(F::from)),
+                pow2(8 * i),
+                Operand::Witness(byte.map(|byte| F::from(spread(byte)))),
+                F::ZERO,
+            ));
+        }
+
+        let cells = self.assign_combination(region, offset, &rows)?;
+        Ok(Word {
+            dense: Number(cells[0].0.clone()),
+            bytes: cells[1..].iter().map(|(byte, _)| Number(byte.clone())).collect(),
+            spreads: cells[1..].iter().map(|(_, spread)| Number(spread.clone())).collect(),
+            value,
+        })
+    }
+
+    /// Adds up the spread forms of `inputs` byte by byte (a negated input
+    /// contributes `spread(0xff) - spread(x)`, i.e. the spread of `NOT x`) and
+    /// splits every sum into its even and odd bits. Returns the word made of the
+    /// odd bits if `odd` is set, and of the even bits otherwise.
+    fn spread_split(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        inputs: &[(&Word<F>, bool)],
+        odd: bool,
+    ) -> Result<Word<F>, Error> {
+        assert!((2..=3).contains(&inputs.len()));
+        let n_bytes = inputs[0].0.bytes.len();
+        let bits = 8 * n_bytes;
+
+        let value = inputs.iter().fold(Value::known(Vec::new()), |acc, (word, negate)| {
+            acc.zip(word.value).map(|(mut acc, value)| {
+                acc.push(if *negate { !value & mask(bits) } else { value });
+                acc
+            })
+        });
+
+        let mut out_bytes = Vec::with_capacity(n_bytes);
+        for i in 0..n_bytes {
+            let sum = value.as_ref().map(|values| {
+                values.iter().map(|value| spread((value >> (8 * i)) & 0xff)).sum::<u64>()
+            });
+            let even = sum.map(|sum| (0..8).fold(0, |acc, j| acc | (((sum >> (2 * j)) & 1) << j)));
+            let odd_bits = sum.map(|sum| (0..8).fold(0, |acc, j| acc | (((sum >> (2 * j + 1)) & 1) << j)));
+
+            let mut rows = Vec::new();
+            for (word, negate) in inputs {
+                if *negate {
+                    rows.push(Row::term(Operand::Constant(F::from(spread(0xff))), F::ONE));
+                    rows.push(Row::term(Operand::Copy(word.spreads[i].0.clone()), -F::ONE));
+                } else {
+                    rows.push(Row::term(Operand::Copy(word.spreads[i].0.clone()), F::ONE));
+                }
+            }
+            rows.push(Row::lookup(
+                0,
+                Operand::Witness(even.map(F::from)),
+                F::ZERO,
+                Operand::Witness(even.map(|even| F::from(spread(even)))),
+                -F::ONE,
+            ));
+            rows.push(Row::lookup(
+                0,
+                Operand::Witness(odd_bits.map(F::from)),
+                F::ZERO,
+                Operand::Witness(odd_bits.map(|odd| F::from(spread(odd)))),
+                -F::from(2),
+            ));
+
+            let cells = self.assign_combination(region, offset, &rows)?;
+            let (byte, spread_cell) = cells[cells.len() - if odd { 1 } else { 2 }].clone();
+            out_bytes.push((Number(byte), Number(spread_cell)));
+        }
+
+        let out_value = value.map(|values| {
+            (0..bits).fold(0u64, |acc, j| {
+                let sum: u64 = values.iter().map(|value| (value >> j) & 1).sum();
+                let bit = if odd { sum >> 1 } else { sum & 1 };
+                acc | (bit << j)
+            })
+        });
+        self.recompose(region, offset, out_bytes, out_value)
+    }
+
+    /// Computes the dense value of a word from bytes that are already looked up.
+    fn recompose(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        bytes: Vec<(Number<F>, Number<F>)>,
+        value: Value<u64>,
+    ) -> Result<Word<F>, Error> {
+        let mut rows = vec![Row::term(Operand::Witness(value.map(F::from)), -F::ONE)];
+        for (i, (byte, _)) in bytes.iter().enumerate() {
+            rows.push(Row::term(Operand::Copy(byte.0.clone()), pow2(8 * i)));
+        }
+        let cells = self.assign_combination(region, offset, &rows)?;
+
+        let (bytes, spreads) = bytes.into_iter().unzip();
+        Ok(Word {
+            dense: Number(cells[0].0.clone()),
+            bytes,
+            spreads,
+            value,
+        })
+    }
+
+    /// Shared implementation of `rotr` and `shr`. With `r = 8q + t`, every byte
+    /// is split at bit `t` into a low and a high part, and each part moves to
+    /// its new position as a whole. For a shift the parts that move below bit 0
+    /// are dropped.
+    fn shift(
+        &self,
+        region: &mut Region<'_, F>,
+        offset: &mut usize,
+        word: &Word<F>,
+        r: usize,
+        rotate: bool,
+    ) -> Result<Word<F>, Error> {
+        let bits = word.bits();
+        let n_bytes = word.bytes.len();
+        assert!(r > 0 && r < bits);
+        let t = r % 8;
+
+        let value = word.value.map(|value| {
+            if rotate {
+                ((value >> r) | (value << (bits - r))) & mask(bits)
+            } else {
+                value >> r
+            }
+        });
+
+        // The coefficient of a piece that starts at bit `position` of the input.
+        let coeff = |position: usize| -> F {
+            if position >= r {
+                pow2(position - r)
+            } else if rotate {
+                pow2(position + bits - r)
+            } else {
+                F::ZERO
+            }
+        };
+
+        let mut rows = vec![Row::term(Operand::Witness(value.map(F::from)), -F::ONE)];
+        for (i, byte) in word.bytes.iter().enumerate() {
+            if t == 0 {
+                rows.push(Row::term(Operand::Copy(byte.0.clone()), coeff(8 * i)));
+            } else {
+                // byte = low + 2^t * high, so
+                // c_low * low + c_high * high = c_high / 2^t * byte + (c_low - c_high / 2^t) * low
+                let c_high = coeff(8 * i + t) * pow2::<F>(t).invert().unwrap();
+                let c_low = coeff(8 * i);
+                let low = word.value.map(|value| F::from((value >> (8 * i)) & 0xff & mask(t)));
+                rows.push(Row::lookup(
+                    t as u64,
+                    Operand::Copy(byte.0.clone()),
+                    c_high,
+                    Operand::Witness(low),
+                    c_low - c_high,
+                ));
+            }
+        }
+        let cells = self.assign_combination(region, offset, &rows)?;
+
+        self.decompose(region, offset, Operand::Copy(cells[0].0.clone()), value, n_bytes)
+    }
+}
+
+impl<F: PrimeField> Chip<F> for SpreadChip<F> {
+    type Config = SpreadConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl<F: PrimeField> WordInstructions<F> for SpreadChip<F> {
+    type Word = Word<F>;
+
+    fn load_table(&self, mut layouter: impl Layouter<F>) -> Result<(), Error> {
+        let config = self.config();
+
+        layouter.assign_table(
+            || "spread table",
+            |mut table| {
+                for tag in 0..8u64 {
+                    for x in 0..256u64 {
+                        let offset = (tag * 256 + x) as usize;
+                        let aux = if tag == 0 { spread(x) } else { x & mask(tag as usize) };
+                        for (column, value) in config.table.iter().zip([tag, x, aux]) {
+                            table.assign_cell(|| "spread table", *column, offset, || Value::known(F::from(value)))?;
+                        }
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+
+    fn load_word(&self, layouter: impl Layouter<F>, value: Value<u64>, n_bytes: usize) -> Result<Self::Word, Error> {
+        self.assign_region(layouter, "load word", |region, offset| {
+            self.decompose(region, offset, Operand::Witness(value.map(F::from)), value, n_bytes)
+        })
+    }
+
+    fn constant_word(&self, layouter: impl Layouter<F>, value: u64, n_bytes: usize) -> Result<Self::Word, Error> {
+        self.assign_region(layouter, "constant word", |region, offset| {
+            self.decompose(region, offset, Operand::Constant(F::from(value)), Value::known(value), n_bytes)
+        })
+    }
+
+    fn xor(&self, layouter: impl Layouter<F>, words: &[&Self::Word]) -> Result<Self::Word, Error> {
+        let inputs = words.iter().map(|word| (*word, false)).collect::<Vec<_>>();
+        self.assign_region(layouter, "xor", |region, offset| self.spread_split(region, offset, &inputs, false))
+    }
+
+    fn maj(&self, layouter: impl Layouter<F>, a: &Self::Word, b: &Self::Word, c: &Self::Word) -> Result<Self::Word, Error> {
+        self.assign_region(layouter, "maj", |region, offset| {
+            self.spread_split(region, offset, &[(a, false), (b, false), (c, false)], true)
+        })
+    }
+
+    fn ch(&self, mut layouter: impl Layouter<F>, e: &Self::Word, f: &Self::Word, g: &Self::Word) -> Result<Self::Word, Error> {
+        let e_and_f = self.assign_region(layouter.namespace(|| "e and f"), "and", |region, offset| {
+            self.spread_split(region, offset, &[(e, false), (f, false)], true)
+        })?;
+        let not_e_and_g = self.assign_region(layouter.namespace(|| "!e and g"), "and", |region, offset| {
+            self.spread_split(region, offset, &[(e, true), (g, false)], true)
+        })?;
+
+        // The two terms never have a bit set in the same position, so their XOR
+        // is just their sum.
+        self.add(layouter.namespace(|| "xor"), &[&e_and_f, &not_e_and_g])
+    }
+
+    fn rotr(&self, layouter: impl Layouter<F>, word: &Self::Word, r: usize) -> Result<Self::Word, Error> {
+        self.assign_region(layouter, "rotr", |region, offset| self.shift(region, offset, word, r, true))
+    }
+
+    fn shr(&self, layouter: impl Layouter<F>, word: &Self::Word, r: usize) -> Result<Self::Word, Error> {
+        self.assign_region(layouter, "shr", |region, offset| self.shift(region, offset, word, r, false))
+    }
+
+    fn add(&self, layouter: impl Layouter<F>, words: &[&Self::Word]) -> Result<Self::Word, Error> {
+        let n_bytes = words[0].bytes.len();
+        let bits = 8 * n_bytes;
+        assert!(words.len() <= 256, "the carry must fit in a byte");
+
+        let sum = words
+            .iter()
+            .fold(Value::known(0u128), |acc, word| acc + word.value.map(|value| value as u128));
+        let value = sum.map(|sum| (sum & mask(bits) as u128) as u64);
+        let carry = sum.map(|sum| (sum >> bits) as u64);
+
+        self.assign_region(layouter, "add", |region, offset| {
+            // sum words - carry * 2^bits - out = 0, where the carry is range
+            // checked by looking it up as a byte.
+            let mut rows = words
+                .iter()
+                .map(|word| Row::term(Operand::Copy(word.dense.0.clone()), F::ONE))
+                .collect::<Vec<_>>();
+            rows.push(Row::lookup(
+                0,
+                Operand::Witness(carry.map(F::from)),
+                -pow2::<F>(bits),
+                Operand::Witness(carry.map(|carry| F::from(spread(carry)))),
+                F::ZERO,
+            ));
+            rows.push(Row::term(Operand::Witness(value.map(F::from)), -F::ONE));
+            let cells = self.assign_combination(region, offset, &rows)?;
+
+            let out = cells[cells.len() - 1].0.clone();
+            self.decompose(region, offset, Operand::Copy(out), value, n_bytes)
+        })
+    }
+}